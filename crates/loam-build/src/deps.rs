@@ -67,6 +67,8 @@ pub enum Error {
     ParentNotFound(PathBuf),
     #[error(transparent)]
     Metadata(#[from] cargo_metadata::Error),
+    #[error("contracts depend on each other, so none of them can be built first: {}", .0.join(", "))]
+    DependencyCycle(Vec<String>),
 }
 
 /// Retrieves all dependencies for the given manifest path.
@@ -295,19 +297,76 @@ pub fn subcontract(manifest_path: &Path) -> Result<Vec<Package>, Error> {
 /// * There's an issue retrieving contract dependencies for any of the packages.
 /// * The dependency graph contains cycles, making topological sorting impossible.
 pub fn get_workspace(packages: &[Package]) -> Result<Vec<Package>, Error> {
+    Ok(get_workspace_levels(packages)?
+        .into_iter()
+        .flatten()
+        .collect())
+}
+
+/// Groups a list of packages into levels of their contract dependency graph.
+///
+/// Every package in a level only depends on contracts in earlier levels, so the packages within
+/// a level are independent of each other and can be built in parallel. Within a level, packages
+/// keep the order in which they were given.
+///
+/// # Arguments
+///
+/// * `packages` - A slice of Package structs to process.
+///
+/// # Returns
+///
+/// A Result containing a Vec of levels, each a Vec of Package structs,
+/// or an Error if the operation fails.
+///
+/// # Errors
+///
+/// This function will return an Error if:
+/// * There's an issue retrieving contract dependencies for any of the packages.
+/// * The dependency graph contains cycles, naming the packages in them.
+pub fn get_workspace_levels(packages: &[Package]) -> Result<Vec<Vec<Package>>, Error> {
+    levels(packages, |p| {
+        Ok(contract(&p.manifest_path.clone().into_std_path_buf())?
+            .into_iter()
+            .map(|dep| dep.id)
+            .collect())
+    })
+}
+
+/// Groups `packages` into levels, given the ids of the contracts each depends on
+fn levels(
+    packages: &[Package],
+    contract_deps: impl Fn(&Package) -> Result<Vec<PackageId>, Error>,
+) -> Result<Vec<Vec<Package>>, Error> {
     let mut graph: TopologicalSort<PackageId> = TopologicalSort::new();
     for p in packages {
-        let contract_deps = contract(&p.manifest_path.clone().into_std_path_buf())?;
-        for dep in contract_deps {
-            graph.add_dependency(dep.id.clone(), p.id.clone());
+        for dep in contract_deps(p)? {
+            graph.add_dependency(dep, p.id.clone());
         }
         graph.insert(p.id.clone());
     }
     let mut res = Vec::new();
-    while let Some(p) = graph.pop() {
-        if let Some(contract) = packages.iter().find(|p2| p2.id == p) {
-            res.push(contract.clone());
+    loop {
+        let ids = graph.pop_all();
+        if ids.is_empty() {
+            break;
         }
+        let level = packages
+            .iter()
+            .filter(|p| ids.contains(&p.id))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !level.is_empty() {
+            res.push(level);
+        }
+    }
+    if !graph.is_empty() {
+        let sorted = res.iter().flatten().map(|p| &p.id).collect::<Vec<_>>();
+        let cycle = packages
+            .iter()
+            .filter(|p| !sorted.contains(&&p.id))
+            .map(|p| p.name.clone())
+            .collect();
+        return Err(Error::DependencyCycle(cycle));
     }
     Ok(res)
 }
@@ -331,4 +390,60 @@ mod tests {
         let deps = subcontract_paths(&manifest_path).unwrap();
         println!("{deps:#?}\n{}", deps.len());
     }
+
+    fn workspace_packages(names: &[&str]) -> Vec<Package> {
+        let pwd = std::env::current_dir().unwrap();
+        let metadata = cargo_metadata::MetadataCommand::new()
+            .manifest_path(pwd.join("../../Cargo.toml"))
+            .no_deps()
+            .exec()
+            .unwrap();
+        names
+            .iter()
+            .map(|name| {
+                metadata
+                    .packages
+                    .iter()
+                    .find(|p| p.name == *name)
+                    .unwrap()
+                    .clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_get_workspace_levels_reports_cycles() {
+        let packages = workspace_packages(&["dep-normal", "other", "normal"]);
+        let id = |name: &str| packages.iter().find(|p| p.name == name).unwrap().id.clone();
+        let err = levels(&packages, |p| {
+            Ok(match p.name.as_str() {
+                "other" => vec![id("normal")],
+                "normal" => vec![id("other")],
+                _ => vec![],
+            })
+        })
+        .unwrap_err();
+        assert!(
+            matches!(&err, Error::DependencyCycle(cycle) if cycle == &["other", "normal"]),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_get_workspace_levels() {
+        let packages = workspace_packages(&["dep-normal", "other", "normal-dep", "normal"]);
+        let levels = get_workspace_levels(&packages)
+            .unwrap()
+            .into_iter()
+            .map(|level| level.into_iter().map(|p| p.name).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            levels,
+            vec![
+                vec!["other".to_string(), "normal".to_string()],
+                vec!["normal-dep".to_string()],
+                vec!["dep-normal".to_string()],
+            ]
+        );
+    }
 }
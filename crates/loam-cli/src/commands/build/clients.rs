@@ -1,38 +1,47 @@
 #![allow(clippy::struct_excessive_bools)]
-use crate::commands::build::env_toml;
+use crate::commands::{
+    build::{
+        env_toml::{self, Fund, Keys, OnInitFailure, Signers},
+        init_script, manifest,
+        multisig::{self, Outcome},
+        plan::{self, Action, Alias, ContractPlan, Plan},
+        state::{self, InitStatus, State},
+    },
+    publish,
+};
+use crate::registry;
 use indexmap::IndexMap;
-use regex::Regex;
 use serde_json;
-use shlex::split;
 use soroban_cli::commands::NetworkRunnable;
+use soroban_cli::config::secret::Secret;
 use soroban_cli::utils::contract_hash;
 use soroban_cli::{commands as cli, CommandParser};
 use std::fmt::Debug;
-use std::hash::Hash;
-use std::process::Command;
 use stellar_strkey;
-use stellar_xdr::curr::Error as xdrError;
+use stellar_xdr::curr::{
+    AccountId, ConfigSettingEntry, ConfigSettingId, CreateAccountOp, Error as xdrError,
+    LedgerEntryData, LedgerKey, LedgerKeyConfigSetting, Memo, MuxedAccount, Operation,
+    OperationBody, Preconditions, PublicKey, SequenceNumber, Transaction, TransactionExt, Uint256,
+};
 
-use super::env_toml::Network;
-
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, clap::ValueEnum)]
-pub enum LoamEnv {
-    Development,
-    Testing,
-    Staging,
-    Production,
-}
+const STROOPS_PER_LUMEN: i64 = 10_000_000;
 
-impl std::fmt::Display for LoamEnv {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", format!("{self:?}").to_lowercase())
-    }
-}
+use super::env_toml::Network;
 
-#[derive(clap::Args, Debug, Clone, Copy)]
+#[derive(clap::Args, Debug, Clone)]
 pub struct Args {
-    #[arg(env = "LOAM_ENV", value_enum)]
-    pub env: Option<LoamEnv>,
+    /// Environment of `environments.toml` to use
+    #[arg(env = "LOAM_ENV")]
+    pub env: Option<String>,
+    /// Print what would be done for each contract, as a table or as JSON, without doing it.
+    /// Contracts are still built, and aliases are still compared with the network.
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "table")]
+    pub dry_run: Option<plan::Format>,
+    /// Run the init of every contract, even those it already ran against. Otherwise, inits
+    /// recorded in `.stellar/loam-state.json` only run again when they change, or the ids of the
+    /// contracts they refer to do.
+    #[arg(long)]
+    pub reinit: bool,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -42,9 +51,9 @@ pub enum Error {
     #[error("⛔ ️invalid network: must either specify a network name or both network_passphrase and rpc_url")]
     MalformedNetwork,
     #[error(transparent)]
-    ParsingNetwork(#[from] cli::network::Error),
+    ParsingNetwork(Box<cli::network::Error>),
     #[error(transparent)]
-    GeneratingKey(#[from] cli::keys::generate::Error),
+    GeneratingKey(Box<cli::keys::generate::Error>),
     #[error("⛔ ️can only have one default account; marked as default: {0:?}")]
     OnlyOneDefaultAccount(Vec<String>),
     #[error("⛔ ️you need to provide at least one account, to use as the source account for contract deployment and other operations")]
@@ -53,29 +62,57 @@ pub enum Error {
     BadContractName(String),
     #[error("⛔ ️Invalid contract ID: {0:?}")]
     InvalidContractID(String),
-    #[error("⛔ ️An ID must be set for a contract in production or staging. E.g. <name>.id = C...")]
-    MissingContractID(String),
-    #[error("⛔ ️Unable to parse init script: {0:?}")]
-    InitParseFailure(String),
-    #[error("⛔ ️Failed to execute subcommand: {0:?}\n{1:?}")]
-    SubCommandExecutionFailure(String, String),
+    #[error("⛔ ️environment {1:?} requires ids, so contract {0:?} needs one: set `{1}.contracts.{0}.id = \"C...\"`, or `require-ids = false`")]
+    MissingContractID(String, String),
     #[error(transparent)]
-    ContractInstall(#[from] cli::contract::install::Error),
+    ContractInstall(Box<cli::contract::install::Error>),
     #[error(transparent)]
-    ContractDeploy(#[from] cli::contract::deploy::wasm::Error),
+    ContractDeploy(Box<cli::contract::deploy::wasm::Error>),
     #[error(transparent)]
-    ContractBindings(#[from] cli::contract::bindings::typescript::Error),
+    ContractBindings(Box<cli::contract::bindings::typescript::Error>),
     #[error(transparent)]
-    ContractFetch(#[from] cli::contract::fetch::Error),
+    ContractFetch(Box<cli::contract::fetch::Error>),
     #[error(transparent)]
     ConfigLocator(#[from] soroban_cli::config::locator::Error),
     #[error(transparent)]
-    ConfigNetwork(#[from] soroban_cli::config::network::Error),
+    ConfigNetwork(Box<soroban_cli::config::network::Error>),
+    #[error(transparent)]
+    ContractInvoke(Box<cli::contract::invoke::Error>),
+    #[error(transparent)]
+    Init(#[from] init_script::Error),
+    #[error(transparent)]
+    State(#[from] state::Error),
+    #[error("⛔ ️init of {contract:?} failed after {completed} of its {total} calls succeeded, {}: {source}", after_init_failure(*.redeploy))]
+    InitFailed {
+        contract: String,
+        completed: usize,
+        total: usize,
+        redeploy: bool,
+        source: Box<init_script::Error>,
+    },
+    #[error("⛔ ️the init of each of these contracts refers to another of them, so none can be deployed first: {}", .0.join(", "))]
+    DependencyCycle(Vec<String>),
     #[error(transparent)]
-    ContractInvoke(#[from] cli::contract::invoke::Error),
+    Secret(#[from] soroban_cli::config::secret::Error),
+    #[error("⛔ ️account {0:?} {1}")]
+    InvalidAccount(String, String),
+    #[error("⛔ ️account {0:?} has its keys in ${1}, which isn't set in the environment or .env")]
+    MissingKeyVariable(String, String),
+    #[error(transparent)]
+    Rpc(Box<soroban_rpc::Error>),
+    #[error(transparent)]
+    Signer(#[from] soroban_cli::signer::Error),
+    #[error(transparent)]
+    Multisig(#[from] multisig::Error),
     #[error(transparent)]
     Clap(#[from] clap::Error),
     #[error(transparent)]
+    Registry(#[from] registry::Error),
+    #[error(transparent)]
+    Publish(Box<publish::Error>),
+    #[error(transparent)]
+    Metadata(#[from] cargo_metadata::Error),
+    #[error(transparent)]
     WasmHash(#[from] xdrError),
     #[error(transparent)]
     Io(#[from] std::io::Error),
@@ -83,36 +120,82 @@ pub enum Error {
     Json(#[from] serde_json::Error),
 }
 
+impl_from_boxed!(Error {
+    ParsingNetwork(cli::network::Error),
+    GeneratingKey(cli::keys::generate::Error),
+    ContractInstall(cli::contract::install::Error),
+    ContractDeploy(cli::contract::deploy::wasm::Error),
+    ContractBindings(cli::contract::bindings::typescript::Error),
+    ContractFetch(cli::contract::fetch::Error),
+    ConfigNetwork(soroban_cli::config::network::Error),
+    ContractInvoke(cli::contract::invoke::Error),
+    Rpc(soroban_rpc::Error),
+    Publish(publish::Error),
+});
+
 impl Args {
     pub async fn run(
         &self,
         workspace_root: &std::path::Path,
         package_names: Vec<String>,
     ) -> Result<(), Error> {
-        let Some(current_env) =
-            env_toml::Environment::get(workspace_root, &self.loam_env(LoamEnv::Production))?
+        let Some(current_env) = env_toml::Environment::get(workspace_root, &self.loam_env())?
         else {
             return Ok(());
         };
 
         Self::add_network_to_env(&current_env.network)?;
+        Self::check_contracts(workspace_root, &current_env)?;
+        if let Some(format) = self.dry_run {
+            self.plan(workspace_root, &current_env, package_names)
+                .await?
+                .print(format)?;
+            return Ok(());
+        }
         // Create the '.stellar' directory if it doesn't exist - for saving contract aliases and account aliases
         std::fs::create_dir_all(workspace_root.join(".stellar"))
             .map_err(soroban_cli::config::locator::Error::Io)?;
-        Self::handle_accounts(current_env.accounts.as_deref()).await?;
-        self.handle_contracts(
-            workspace_root,
-            current_env.contracts.as_ref(),
-            package_names,
-            &current_env.network,
-        )
-        .await?;
+        Self::handle_accounts(workspace_root, current_env.accounts.as_deref()).await?;
+        Self::fund_accounts_on_contract_networks(&current_env).await?;
+        let plan = self
+            .plan(workspace_root, &current_env, package_names)
+            .await?;
+        Self::check_registry_versions(workspace_root, &current_env, &plan).await?;
+        self.apply(workspace_root, &current_env, plan).await?;
 
         Ok(())
     }
 
-    fn loam_env(self, default: LoamEnv) -> String {
-        self.env.unwrap_or(default).to_string().to_lowercase()
+    /// Check the accounts which sign for the contracts, and the inits which would run against the
+    /// built contracts' specs, so that mistakes are reported all at once, before anything is
+    /// deployed
+    fn check_contracts(
+        workspace_root: &std::path::Path,
+        environment: &env_toml::Environment,
+    ) -> Result<(), Error> {
+        let mut problems = environment.signer_problems();
+        problems.extend(environment.registry_problems());
+        if environment.runs_init() {
+            let identities = Self::get_config_locator(workspace_root)
+                .list_identities()
+                .unwrap_or_default();
+            problems.extend(init_script::check_environment(
+                workspace_root,
+                environment,
+                &identities,
+            ));
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(env_toml::Error::Invalid(problems).into())
+        }
+    }
+
+    fn loam_env(&self) -> String {
+        self.env
+            .clone()
+            .unwrap_or_else(|| env_toml::DEFAULT_ENV.to_string())
     }
 
     /// Parse the network settings from the environments.toml file and set `STELLAR_RPC_URL` and
@@ -121,7 +204,7 @@ impl Args {
     /// We could set `STELLAR_NETWORK` instead, but when importing contracts, we want to hard-code
     /// the network passphrase. So if given a network name, we use soroban-cli to fetch the RPC url
     /// & passphrase for that named network, and still set the environment variables.
-    fn add_network_to_env(network: &env_toml::Network) -> Result<(), Error> {
+    pub(crate) fn add_network_to_env(network: &env_toml::Network) -> Result<(), Error> {
         match &network {
             Network {
                 name: Some(name), ..
@@ -157,6 +240,35 @@ impl Args {
         Ok(())
     }
 
+    /// Switch to `network`, unless it is already the `current` one
+    fn switch_network(current: &mut Network, network: &Network) -> Result<(), Error> {
+        if current != network {
+            Self::add_network_to_env(network)?;
+            current.clone_from(network);
+        }
+        Ok(())
+    }
+
+    /// Fund the accounts of the environment on the networks of contracts which have their own,
+    /// leaving the environment's network selected
+    async fn fund_accounts_on_contract_networks(
+        environment: &env_toml::Environment,
+    ) -> Result<(), Error> {
+        let mut funded = vec![&environment.network];
+        for contract in environment.contracts.iter().flat_map(IndexMap::values) {
+            let Some(network) = &contract.network else {
+                continue;
+            };
+            if funded.contains(&network) {
+                continue;
+            }
+            Self::add_network_to_env(network)?;
+            Self::fund_accounts(environment.accounts.as_deref().unwrap_or_default()).await?;
+            funded.push(network);
+        }
+        Self::add_network_to_env(&environment.network)
+    }
+
     fn get_network_args(network: &Network) -> soroban_cli::config::network::Args {
         soroban_cli::config::network::Args {
             rpc_url: network.rpc_url.clone(),
@@ -165,6 +277,48 @@ impl Args {
         }
     }
 
+    /// Value of the `contract_max_size_bytes` setting of the environment's network, or `None` if
+    /// there is no such environment or its network can't be reached
+    pub(crate) async fn network_max_wasm_size(
+        &self,
+        workspace_root: &std::path::Path,
+    ) -> Option<u64> {
+        let environment = env_toml::Environment::get(workspace_root, &self.loam_env()).ok()??;
+        let network = Self::get_network_args(&environment.network)
+            .get(&soroban_cli::config::locator::Args::default())
+            .ok()?;
+        let key = LedgerKey::ConfigSetting(LedgerKeyConfigSetting {
+            config_setting_id: ConfigSettingId::ContractMaxSizeBytes,
+        });
+        let client = soroban_rpc::Client::new(&network.rpc_url).map_err(|e| e.to_string());
+        let fetched = match client {
+            Ok(client) => client
+                .get_full_ledger_entries(&[key])
+                .await
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e),
+        };
+        match fetched.map(|r| r.entries.into_iter().next().map(|e| e.val)) {
+            Ok(Some(LedgerEntryData::ConfigSetting(ConfigSettingEntry::ContractMaxSizeBytes(
+                max,
+            )))) => Some(u64::from(max)),
+            Ok(_) => {
+                eprintln!(
+                    "⚠️ {} has no contract_max_size_bytes setting",
+                    network.rpc_url
+                );
+                None
+            }
+            Err(e) => {
+                eprintln!(
+                    "⚠️ could not read contract_max_size_bytes from {}: {e}",
+                    network.rpc_url
+                );
+                None
+            }
+        }
+    }
+
     fn get_config_locator(workspace_root: &std::path::Path) -> soroban_cli::config::locator::Args {
         soroban_cli::config::locator::Args {
             global: false,
@@ -182,13 +336,13 @@ impl Args {
         config_dir.get_contract_id(name, &network_passphrase)
     }
 
-    async fn contract_hash_matches(
+    /// Hash of the wasm run by a deployed contract, or `None` if there is no such contract
+    async fn deployed_hash(
         &self,
         contract_id: &str,
-        hash: &str,
         network: &Network,
         workspace_root: &std::path::Path,
-    ) -> Result<bool, Error> {
+    ) -> Result<Option<String>, Error> {
         let result = cli::contract::fetch::Cmd {
             contract_id: contract_id.to_string(),
             out_file: None,
@@ -199,15 +353,12 @@ impl Args {
         .await;
 
         match result {
-            Ok(result) => {
-                let ctrct_hash = contract_hash(&result)?;
-                Ok(hex::encode(ctrct_hash) == hash)
-            }
+            Ok(result) => Ok(Some(hex::encode(contract_hash(&result)?))),
             Err(e) => {
                 if e.to_string().contains("Contract not found") {
-                    Ok(false)
+                    Ok(None)
                 } else {
-                    Err(Error::ContractFetch(e))
+                    Err(Error::ContractFetch(Box::new(e)))
                 }
             }
         }
@@ -216,24 +367,21 @@ impl Args {
     fn save_contract_alias(
         name: &str,
         contract_id: &str,
-        network: &Network,
         workspace_root: &std::path::Path,
     ) -> Result<(), soroban_cli::config::locator::Error> {
         let config_dir = Self::get_config_locator(workspace_root);
-        let passphrase = network
-            .network_passphrase
-            .clone()
-            .expect("You must set a network passphrase.");
+        let passphrase = std::env::var("STELLAR_NETWORK_PASSPHRASE")
+            .expect("No STELLAR_NETWORK_PASSPHRASE environment variable set");
         config_dir.save_contract_id(&passphrase, contract_id, name)
     }
 
     fn write_contract_template(
-        self,
         workspace_root: &std::path::Path,
         name: &str,
         contract_id: &str,
+        allow_http: bool,
     ) -> Result<(), Error> {
-        let allow_http = if self.loam_env(LoamEnv::Production) == "development" {
+        let allow_http = if allow_http {
             "\n  allowHttp: true,"
         } else {
             ""
@@ -241,7 +389,7 @@ impl Args {
         let network = std::env::var("STELLAR_NETWORK_PASSPHRASE")
             .expect("No STELLAR_NETWORK_PASSPHRASE environment variable set");
         let template = format!(
-            r#"import * as Client from '{name}';
+            r"import * as Client from '{name}';
 import {{ rpcUrl }} from './util';
     
 export default new Client.Client({{
@@ -250,26 +398,174 @@ export default new Client.Client({{
   rpcUrl,{allow_http}
   publicKey: undefined,
 }});
-"#
+"
         );
         let path = workspace_root.join(format!("src/contracts/{name}.ts"));
         std::fs::write(path, template)?;
         Ok(())
     }
 
-    async fn account_exists(account_name: &str) -> Result<bool, Error> {
-        // TODO: this is a workaround until generate is changed to not overwrite accounts
-        Ok(cli::keys::fund::Cmd::parse_arg_vec(&[account_name])?
-            .run()
-            .await
-            .is_ok())
+    /// Make sure the stellar CLI has the keys of an account: generate them, unless it already
+    /// has keys by the account's name, or import them from a variable. Only the local config is
+    /// read, not the network.
+    fn handle_keys(
+        locator: &soroban_cli::config::locator::Args,
+        account: &env_toml::Account,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), Error> {
+        let name = &account.name;
+        let keys = account
+            .keys()
+            .map_err(|message| Error::InvalidAccount(name.clone(), message))?;
+        let (var_name, secret) = match keys {
+            Keys::PublicKey(_) => return Ok(()),
+            Keys::Generated => {
+                if locator.read_identity(name).is_ok() {
+                    eprintln!("ℹ️ account {name:?} already exists, skipping key creation");
+                } else {
+                    eprintln!("🔐 creating keys for {name:?}");
+                    locator.write_identity(name, &Secret::from_seed(None)?)?;
+                }
+                return Ok(());
+            }
+            Keys::SecretKeyEnv(var_name) => (
+                var_name,
+                var(var_name).map(|secret_key| Secret::SecretKey { secret_key }),
+            ),
+            Keys::SeedPhraseEnv(var_name) => (
+                var_name,
+                var(var_name).map(|seed_phrase| Secret::SeedPhrase { seed_phrase }),
+            ),
+        };
+        let secret =
+            secret.ok_or_else(|| Error::MissingKeyVariable(name.clone(), var_name.to_string()))?;
+        let public_key = secret.public_key(None)?;
+        let existing = locator
+            .read_identity(name)
+            .ok()
+            .and_then(|existing| existing.public_key(None).ok());
+        if existing == Some(public_key) {
+            eprintln!("ℹ️ account {name:?} already exists, skipping key import");
+        } else {
+            eprintln!("🔑 importing keys for {name:?} from ${var_name}");
+            locator.write_identity(name, &secret)?;
+        }
+        Ok(())
+    }
+
+    /// Address of an account, if the stellar CLI has its keys or it is watch-only
+    fn account_address(
+        locator: &soroban_cli::config::locator::Args,
+        account: &env_toml::Account,
+    ) -> Result<Option<String>, Error> {
+        if let Some(public_key) = &account.public_key {
+            return Ok(Some(public_key.clone()));
+        }
+        match locator.read_identity(&account.name) {
+            Ok(secret) => Ok(Some(secret.public_key(None)?.to_string())),
+            Err(_) => Ok(None),
+        }
     }
 
-    async fn generate_contract_bindings(
-        self,
+    /// Fund the accounts which don't exist on the current network yet, as set by their `fund`.
+    /// The default account comes first, since it pays for the accounts funded with an amount.
+    async fn fund_accounts(accounts: &[env_toml::Account]) -> Result<(), Error> {
+        let locator = soroban_cli::config::locator::Args::default();
+        let rpc_url =
+            std::env::var("STELLAR_RPC_URL").expect("No STELLAR_RPC_URL environment variable set");
+        let client = soroban_rpc::Client::new(&rpc_url)?;
+        let default_account = std::env::var("STELLAR_ACCOUNT").ok();
+        let mut accounts = accounts.iter().collect::<Vec<_>>();
+        accounts.sort_by_key(|account| Some(&account.name) != default_account.as_ref());
+        for account in accounts {
+            let name = &account.name;
+            if account.fund == Fund::Friendbot(false) {
+                continue;
+            }
+            let Some(address) = Self::account_address(&locator, account)? else {
+                continue;
+            };
+            match client.get_account(&address).await {
+                Ok(_) => continue,
+                Err(soroban_rpc::Error::NotFound(..)) => {}
+                Err(e) => return Err(e.into()),
+            }
+            let public_key =
+                stellar_strkey::ed25519::PublicKey::from_string(&address).map_err(|_| {
+                    Error::InvalidAccount(name.clone(), format!("`{address}` is not a public key"))
+                })?;
+            match account.fund {
+                Fund::Friendbot(_) => {
+                    eprintln!("💰 funding {name:?} with friendbot");
+                    if let Err(e) = cli::keys::fund::Cmd::parse_arg_vec(&[&address])?
+                        .run()
+                        .await
+                    {
+                        eprintln!("⚠️ could not fund {name:?}: {e}");
+                    }
+                }
+                Fund::Lumens(lumens) => {
+                    let funder = default_account
+                        .as_deref()
+                        .expect("the default account is set before accounts are funded");
+                    eprintln!("💰 funding {name:?} with {lumens} XLM from {funder:?}");
+                    Self::create_account(&client, funder, &public_key, lumens).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Create an account with `lumens`, paid by `funder`
+    async fn create_account(
+        client: &soroban_rpc::Client,
+        funder: &str,
+        destination: &stellar_strkey::ed25519::PublicKey,
+        lumens: u64,
+    ) -> Result<(), Error> {
+        let starting_balance = i64::try_from(lumens)
+            .ok()
+            .and_then(|lumens| lumens.checked_mul(STROOPS_PER_LUMEN))
+            .ok_or_else(|| {
+                Error::InvalidAccount(funder.to_string(), format!("can't fund {lumens} XLM"))
+            })?;
+        let key = soroban_cli::config::locator::Args::default()
+            .read_identity(funder)?
+            .key_pair(None)?;
+        let source = key.verifying_key().to_bytes();
+        let funder_account = client
+            .get_account(&stellar_strkey::ed25519::PublicKey(source).to_string())
+            .await?;
+        let tx = Transaction {
+            source_account: MuxedAccount::Ed25519(Uint256(source)),
+            fee: 100,
+            seq_num: SequenceNumber(funder_account.seq_num.0 + 1),
+            cond: Preconditions::None,
+            memo: Memo::None,
+            operations: vec![Operation {
+                source_account: None,
+                body: OperationBody::CreateAccount(CreateAccountOp {
+                    destination: AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(destination.0))),
+                    starting_balance,
+                }),
+            }]
+            .try_into()?,
+            ext: TransactionExt::V0,
+        };
+        let passphrase = std::env::var("STELLAR_NETWORK_PASSPHRASE")
+            .expect("No STELLAR_NETWORK_PASSPHRASE environment variable set");
+        let envelope = soroban_cli::signer::sign_tx(&key, &tx, &passphrase)?;
+        client.send_transaction_polling(&envelope).await?;
+        Ok(())
+    }
+
+    /// Generate the TypeScript client of a contract in `packages/<name>`, and import it in
+    /// `src/contracts/<name>.ts`. With `allow_http`, the client may talk to an rpc over HTTP.
+    pub(crate) async fn generate_contract_bindings(
         workspace_root: &std::path::Path,
         name: &str,
         contract_id: &str,
+        allow_http: bool,
     ) -> Result<(), Error> {
         eprintln!("🎭 binding {name:?} contract");
         cli::contract::bindings::typescript::Cmd::parse_arg_vec(&[
@@ -286,12 +582,15 @@ export default new Client.Client({{
         .await?;
 
         eprintln!("🍽️ importing {name:?} contract");
-        self.write_contract_template(workspace_root, name, contract_id)?;
+        Self::write_contract_template(workspace_root, name, contract_id, allow_http)?;
 
         Ok(())
     }
 
-    async fn handle_accounts(accounts: Option<&[env_toml::Account]>) -> Result<(), Error> {
+    async fn handle_accounts(
+        workspace_root: &std::path::Path,
+        accounts: Option<&[env_toml::Account]>,
+    ) -> Result<(), Error> {
         let Some(accounts) = accounts else {
             return Err(Error::NeedAtLeastOneAccount);
         };
@@ -305,27 +604,37 @@ export default new Client.Client({{
         let default_account = match (default_account_candidates.as_slice(), accounts) {
             ([], []) => return Err(Error::NeedAtLeastOneAccount),
             ([], [env_toml::Account { name, .. }, ..]) => name.clone(),
-            ([candidate], _) => candidate.to_string(),
+            ([candidate], _) => candidate.clone(),
             _ => return Err(Error::OnlyOneDefaultAccount(default_account_candidates)),
         };
 
-        for account in accounts {
-            if Self::account_exists(&account.name).await? {
-                eprintln!(
-                    "ℹ️ account {:?} already exists, skipping key creation",
-                    account.name
-                );
-            } else {
-                eprintln!("🔐 creating keys for {:?}", account.name);
-                cli::keys::generate::Cmd::parse_arg_vec(&[&account.name])?
-                    .run()
-                    .await?;
+        if let Some(account) = accounts
+            .iter()
+            .find(|account| account.name == default_account)
+        {
+            if account.public_key.is_some() {
+                return Err(Error::InvalidAccount(
+                    default_account,
+                    "is watch-only, so it can't be the default account, which signs transactions"
+                        .to_string(),
+                ));
+            }
+            if matches!(account.fund, Fund::Lumens(_)) {
+                return Err(Error::InvalidAccount(
+                    default_account,
+                    "is the default account, which pays for the accounts funded with an amount, so it can't be one".to_string(),
+                ));
             }
         }
 
-        std::env::set_var("STELLAR_ACCOUNT", &default_account);
+        let locator = soroban_cli::config::locator::Args::default();
+        let var = env_toml::Environment::vars(workspace_root)?;
+        for account in accounts {
+            Self::handle_keys(&locator, account, &var)?;
+        }
 
-        Ok(())
+        std::env::set_var("STELLAR_ACCOUNT", &default_account);
+        Self::fund_accounts(accounts).await
     }
 
     fn reorder_package_names(
@@ -357,42 +666,119 @@ export default new Client.Client({{
         )
     }
 
-    async fn handle_production_contracts(
-        &self,
+    /// Order contracts so that each comes after the contracts its init refers to, which must be
+    /// deployed first for their ids to be known. Otherwise, contracts keep their order.
+    fn deploy_order(
+        package_names: &[String],
+        contracts: Option<&IndexMap<Box<str>, env_toml::Contract>>,
+    ) -> Result<Vec<String>, Error> {
+        let mut remaining = Self::reorder_package_names(package_names, contracts);
+        let names = remaining.iter().map(String::as_str).collect::<Vec<_>>();
+        let dependencies = remaining
+            .iter()
+            .map(|name| {
+                contracts
+                    .and_then(|contracts| contracts.get(name.as_str()))
+                    .and_then(|settings| settings.init.as_ref())
+                    .map(|init| init_script::dependencies(init, &names))
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+        let mut dependencies = remaining
+            .iter()
+            .cloned()
+            .zip(dependencies)
+            .collect::<IndexMap<_, _>>();
+
+        let mut ordered = Vec::new();
+        while !remaining.is_empty() {
+            let Some(next) = remaining.iter().position(|name| {
+                dependencies[name]
+                    .iter()
+                    .all(|dependency| dependency == name || !remaining.contains(dependency))
+            }) else {
+                return Err(Error::DependencyCycle(remaining));
+            };
+            let name = remaining.remove(next);
+            dependencies.shift_remove(&name);
+            ordered.push(name);
+        }
+        Ok(ordered)
+    }
+
+    /// Addresses of the accounts which have keys, or are watch-only
+    fn account_addresses(
+        accounts: &[env_toml::Account],
+    ) -> Result<IndexMap<String, String>, Error> {
+        let locator = soroban_cli::config::locator::Args::default();
+        let mut addresses = IndexMap::new();
+        for account in accounts {
+            if let Some(address) = Self::account_address(&locator, account)? {
+                addresses.insert(account.name.clone(), address);
+            }
+        }
+        Ok(addresses)
+    }
+
+    /// Plan the contracts of an environment which doesn't deploy them, binding those with an id,
+    /// or else an alias saved by an earlier build
+    fn plan_bound_contracts(
         workspace_root: &std::path::Path,
-        contracts: &IndexMap<Box<str>, env_toml::Contract>,
-    ) -> Result<(), Error> {
-        for (name, contract) in contracts.iter().filter(|(_, settings)| settings.client) {
-            if let Some(id) = &contract.id {
+        environment: &env_toml::Environment,
+    ) -> Result<Vec<ContractPlan>, Error> {
+        let mut plans = Vec::new();
+        let mut current_network = environment.network.clone();
+        for (name, contract) in environment
+            .contracts
+            .iter()
+            .flatten()
+            .filter(|(_, settings)| settings.client)
+        {
+            let id = if let Some(id) = &contract.id {
                 if stellar_strkey::Contract::from_string(id).is_err() {
-                    return Err(Error::InvalidContractID(id.to_string()));
+                    return Err(Error::InvalidContractID(id.clone()));
                 }
-                self.generate_contract_bindings(workspace_root, name, id)
-                    .await?;
+                id.clone()
+            } else if environment.requires_ids() {
+                return Err(Error::MissingContractID(
+                    name.to_string(),
+                    environment.name.clone(),
+                ));
             } else {
-                return Err(Error::MissingContractID(name.to_string()));
-            }
+                Self::switch_network(&mut current_network, environment.network_of(Some(contract)))?;
+                let Some(id) = Self::get_contract_alias(name, workspace_root)? else {
+                    eprintln!("⚠️ skipping {name:?}, which has no id or alias yet");
+                    continue;
+                };
+                id
+            };
+            plans.push(
+                ContractPlan::bind_only(name, &id, false).on_network(contract.network.as_ref()),
+            );
         }
-        Ok(())
+        Self::switch_network(&mut current_network, &environment.network)?;
+        Ok(plans)
     }
 
-    async fn handle_contracts(
-        self,
+    /// Decide what to do with each contract, without changing anything. Contracts built from the
+    /// workspace are compared with the contracts their aliases point to, if any.
+    async fn plan(
+        &self,
         workspace_root: &std::path::Path,
-        contracts: Option<&IndexMap<Box<str>, env_toml::Contract>>,
+        environment: &env_toml::Environment,
         package_names: Vec<String>,
-        network: &Network,
-    ) -> Result<(), Error> {
+    ) -> Result<Plan, Error> {
+        let contracts = environment.contracts.as_ref();
+        let mut plan = Plan {
+            env: environment.name.clone(),
+            contracts: Vec::new(),
+        };
         if package_names.is_empty() {
-            return Ok(());
+            return Ok(plan);
         }
-        let env = self.loam_env(LoamEnv::Production);
-        if env == "production" || env == "staging" {
-            if let Some(contracts) = contracts {
-                self.handle_production_contracts(workspace_root, contracts)
-                    .await?;
-            }
-            return Ok(());
+        if !environment.deploys() {
+            plan.contracts = Self::plan_bound_contracts(workspace_root, environment)?;
+            return Ok(plan);
         }
 
         // ensure contract names are valid
@@ -404,175 +790,565 @@ export default new Client.Client({{
                 }
             }
         }
+        let state = State::load(workspace_root)?;
+        let mut current_network = environment.network.clone();
         // Reorder package_names based on contracts order
-        let reordered_names = Self::reorder_package_names(&package_names, contracts);
-        for name in reordered_names {
-            let settings = match contracts {
-                Some(contracts) => contracts.get(&name as &str),
-                None => None,
+        let order = Self::deploy_order(&package_names, contracts)?;
+        for name in order {
+            let settings = contracts.and_then(|contracts| contracts.get(&name as &str));
+            // Skip if contract is found and its `client` setting is false
+            if settings.is_some_and(|settings| !settings.client) {
+                continue;
+            }
+            let init = Plan::runs_init(environment, settings);
+            let init_status = |contract_id: &str| {
+                settings.and_then(|settings| settings.init.as_ref()).map_or(
+                    InitStatus::Unknown,
+                    |script| {
+                        Self::init_status(&state, environment, &plan, &name, contract_id, script)
+                    },
+                )
             };
-            let contract_id = if let Some(settings) = settings {
-                // Skip if contract is found and its `client` setting is false
-                if !settings.client {
-                    continue;
-                }
-                // If contract ID is set, use it directly
-                settings.id.clone()
-            } else {
-                None
+            let own_network = settings.and_then(|settings| settings.network.as_ref());
+            // If contract ID is set, use it directly
+            if let Some(id) = settings.and_then(|settings| settings.id.as_ref()) {
+                plan.contracts.push(
+                    ContractPlan::bind_only(&name, id, init)
+                        .init_again(init, init_status(id), self.reinit)
+                        .on_network(own_network),
+                );
+                continue;
+            }
+            if settings.is_some() && environment.requires_ids() {
+                return Err(Error::MissingContractID(
+                    name.clone(),
+                    environment.name.clone(),
+                ));
+            }
+            let wasm_path = workspace_root.join(format!("target/loam/{name}.wasm"));
+            if !wasm_path.exists() {
+                return Err(Error::BadContractName(name.clone()));
+            }
+            let hash = manifest::sha256_file(&wasm_path)?;
+            let network = environment.network_of(settings);
+            Self::switch_network(&mut current_network, network)?;
+            // Check if we have an alias saved for this contract
+            let alias = match Self::get_contract_alias(&name, workspace_root)? {
+                Some(contract_id) => match self
+                    .deployed_hash(&contract_id, network, workspace_root)
+                    .await?
+                {
+                    Some(deployed) if deployed == hash => Alias::UpToDate(contract_id),
+                    Some(_) => Alias::Outdated(contract_id),
+                    None => Alias::Missing(contract_id),
+                },
+                None => Alias::None,
             };
-            let contract_id = if let Some(id) = contract_id {
-                // If we have a contract ID, use it
-                id
-            } else {
-                // If we don't have a contract ID, proceed with installation and deployment
-                let wasm_path = workspace_root.join(format!("target/loam/{name}.wasm"));
-                if !wasm_path.exists() {
-                    return Err(Error::BadContractName(name.to_string()));
-                }
-                eprintln!("📲 installing {name:?} wasm bytecode on-chain...");
-                let hash = cli::contract::install::Cmd::parse_arg_vec(&[
-                    "--wasm",
-                    wasm_path
-                        .to_str()
-                        .expect("we do not support non-utf8 paths"),
-                ])?
-                .run_against_rpc_server(None, None)
-                .await?
-                .into_result()
-                .expect("no hash returned by 'contract install'")
-                .to_string();
-                eprintln!("    ↳ hash: {hash}");
-
-                // Check if we have an alias saved for this contract
-                let alias = Self::get_contract_alias(&name, workspace_root)?;
-                if let Some(contract_id) = alias {
-                    match self
-                        .contract_hash_matches(&contract_id, &hash, network, workspace_root)
-                        .await
-                    {
-                        Ok(true) => {
-                            eprintln!("✅ Contract {name:?} is up to date");
-                            continue;
-                        }
-                        Ok(false) => eprintln!("🔄 Updating contract {name:?}"),
-                        Err(e) => return Err(e),
-                    }
-                }
+            let upgrade = settings
+                .map(|settings| settings.upgrade)
+                .unwrap_or_default();
+            let contract = ContractPlan::from_wasm(&name, &hash, alias, upgrade, init);
+            let status = contract
+                .contract_id
+                .as_deref()
+                .map_or(InitStatus::Unknown, init_status);
+            plan.contracts.push(
+                contract
+                    .init_again(init, status, self.reinit)
+                    .on_network(own_network),
+            );
+        }
+        Self::switch_network(&mut current_network, &environment.network)?;
+        Ok(plan)
+    }
 
-                eprintln!("🪞 instantiating {name:?} smart contract");
-                let new_contract_id = cli::contract::deploy::wasm::Cmd::parse_arg_vec(&[
-                    "--alias",
-                    &name,
-                    "--wasm-hash",
-                    &hash,
-                ])?
-                .run_against_rpc_server(None, None)
-                .await?
-                .into_result()
-                .expect("no contract id returned by 'contract deploy'");
-                eprintln!("    ↳ contract_id: {new_contract_id}");
+    /// Whether `init` ran against the contract `name`, deployed as `contract_id`, and neither it
+    /// nor the ids of the contracts it refers to changed since. Those are among the contracts
+    /// planned so far, and their ids are unknown if they are about to be deployed anew.
+    fn init_status(
+        state: &State,
+        environment: &env_toml::Environment,
+        plan: &Plan,
+        name: &str,
+        contract_id: &str,
+        init: &env_toml::Init,
+    ) -> InitStatus {
+        let mut ids = Self::known_contract_ids(environment, plan);
+        ids.insert(name.to_string(), contract_id.to_string());
+        let names = ids
+            .keys()
+            .map(String::as_str)
+            .chain(plan.contracts.iter().map(|contract| contract.name.as_str()))
+            .collect::<Vec<_>>();
+        let referenced = init_script::referenced_ids(init, &names, &ids);
+        state.init_status(contract_id, init, referenced.as_deref())
+    }
 
-                // Save the alias for future use
-                Self::save_contract_alias(&name, &new_contract_id, network, workspace_root)?;
+    /// Install the wasm built for a contract, signed by `deployer`, returning its hash
+    async fn install_wasm(
+        workspace_root: &std::path::Path,
+        name: &str,
+        deployer: &[String],
+    ) -> Result<String, Error> {
+        let wasm_path = workspace_root.join(format!("target/loam/{name}.wasm"));
+        eprintln!("📲 installing {name:?} wasm bytecode on-chain...");
+        let wasm = wasm_path
+            .to_str()
+            .expect("we do not support non-utf8 paths");
+        let hash = match multisig::run::<cli::contract::install::Cmd, _, Error>(
+            &["--wasm", wasm],
+            deployer,
+        )
+        .await?
+        {
+            Outcome::Sent(hash) => hash.to_string(),
+            Outcome::Signed(_) => contract_hash(&std::fs::read(&wasm_path)?)?.to_string(),
+        };
+        eprintln!("    ↳ hash: {hash}");
+        Ok(hash)
+    }
 
-                new_contract_id
-            };
+    /// Switch a deployed contract to the built wasm with its `redeploy` function, installed by
+    /// the contract's deployer and called by its admin
+    async fn upgrade_contract(
+        workspace_root: &std::path::Path,
+        name: &str,
+        contract_id: &str,
+        settings: Option<&env_toml::Contract>,
+    ) -> Result<(), Error> {
+        let deployer = Signers::of(settings.and_then(|settings| settings.deployer.as_ref()));
+        let admin = Signers::of(settings.and_then(|settings| settings.admin.as_ref()));
+        let hash = Self::install_wasm(workspace_root, name, deployer).await?;
+        eprintln!("⬆️ upgrading {name:?} in place with its redeploy function");
+        multisig::run::<cli::contract::invoke::Cmd, _, Error>(
+            &["--id", contract_id, "--", "redeploy", "--wasm_hash", &hash],
+            admin,
+        )
+        .await?;
+        eprintln!("    ↳ contract_id: {contract_id}");
+        Ok(())
+    }
 
-            // Run init script if we're in development or test environment
-            if env == "development" || env == "testing" {
-                if let Some(settings) = settings {
-                    if let Some(init_script) = &settings.init {
-                        eprintln!("🚀 Running initialization script for {name:?}");
-                        self.run_init_script(&name, &contract_id, init_script)
-                            .await?;
-                    }
-                }
+    /// Install the built wasm and deploy a contract from it, signed by `deployer`, saving its id
+    /// as an alias
+    async fn deploy_contract(
+        workspace_root: &std::path::Path,
+        name: &str,
+        deployer: &[String],
+    ) -> Result<String, Error> {
+        let hash = Self::install_wasm(workspace_root, name, deployer).await?;
+        eprintln!("🪞 instantiating {name:?} smart contract");
+        let contract_id = match multisig::run::<cli::contract::deploy::wasm::Cmd, _, Error>(
+            &["--alias", name, "--wasm-hash", &hash],
+            deployer,
+        )
+        .await?
+        {
+            Outcome::Sent(contract_id) => contract_id,
+            Outcome::Signed(tx) => {
+                let passphrase = std::env::var("STELLAR_NETWORK_PASSPHRASE")
+                    .expect("No STELLAR_NETWORK_PASSPHRASE environment variable set");
+                multisig::deployed_contract_id(&tx, &passphrase)?
+                    .expect("deploy transactions create a contract")
             }
-            self.generate_contract_bindings(workspace_root, &name, &contract_id)
-                .await?;
-        }
+        };
+        eprintln!("    ↳ contract_id: {contract_id}");
 
-        Ok(())
+        // Save the alias for future use
+        Self::save_contract_alias(name, &contract_id, workspace_root)?;
+        Ok(contract_id)
     }
 
-    fn resolve_line(re: &Regex, line: &str, shell: &str, flag: &str) -> Result<String, Error> {
-        let mut result = String::new();
-        let mut last_match = 0;
-        for cap in re.captures_iter(line) {
-            let whole_match = cap.get(0).unwrap();
-            result.push_str(&line[last_match..whole_match.start()]);
-            let cmd = &cap[1];
-            let output = Self::execute_subcommand(shell, flag, cmd)?;
-            result.push_str(&output);
-            last_match = whole_match.end();
-        }
-        result.push_str(&line[last_match..]);
-        Ok(result)
-    }
+    /// Publish the built wasm of a contract to `registry` as `<crate name>@<crate version>` and
+    /// deploy it through the registry under its name, owned by the deployer, saving its id as an
+    /// alias. A contract the registry has already deployed under the name is redeployed with the
+    /// new version instead, keeping its id and storage, which is returned as `true`.
+    async fn deploy_through_registry(
+        workspace_root: &std::path::Path,
+        name: &str,
+        registry: &registry::Args,
+    ) -> Result<(String, bool), Error> {
+        let package = Self::contract_package(workspace_root, name)?;
+        let version = package.version.to_string();
+        let repo = package.repository.clone().unwrap_or_default();
+        let wasm_path = workspace_root.join(format!("target/loam/{name}.wasm"));
+        publish::publish(registry, &package.name, &version, &repo, &wasm_path).await?;
 
-    fn execute_subcommand(shell: &str, flag: &str, cmd: &str) -> Result<String, Error> {
-        match Command::new(shell).arg(flag).arg(cmd).output() {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let existing = registry.contract_id(name).await?;
+        let (contract_id, redeployed) = if let Some(contract_id) = existing {
+            eprintln!(
+                "⬆️ redeploying {name:?} with {}@{version} through the registry",
+                package.name
+            );
+            registry.redeploy_contract(name, Some(&version)).await?;
+            (contract_id, true)
+        } else {
+            eprintln!(
+                "🪞 deploying {}@{version} as {name:?} through the registry",
+                package.name
+            );
+            let contract_id = registry
+                .deploy_contract(name, &package.name, Some(&version), None)
+                .await?;
+            (contract_id, false)
+        };
+        eprintln!("    ↳ contract_id: {contract_id}");
+        Self::save_contract_alias(name, &contract_id, workspace_root)?;
+        Ok((contract_id, redeployed))
+    }
 
-                if output.status.success() {
-                    Ok(stdout)
-                } else {
-                    Err(Error::SubCommandExecutionFailure(cmd.to_string(), stderr))
-                }
+    /// Check that no contract to be deployed through the environment's registry changed without
+    /// a bump of its crate version, whose wasm is already published, before anything is deployed
+    async fn check_registry_versions(
+        workspace_root: &std::path::Path,
+        environment: &env_toml::Environment,
+        plan: &Plan,
+    ) -> Result<(), Error> {
+        let contracts = environment.contracts.as_ref();
+        for contract in &plan.contracts {
+            if !matches!(
+                contract.action,
+                Action::Deploy | Action::Replace | Action::Upgrade
+            ) {
+                continue;
             }
-            Err(e) => Err(Error::SubCommandExecutionFailure(
-                cmd.to_string(),
-                e.to_string(),
-            )),
+            let settings = contracts.and_then(|contracts| contracts.get(&*contract.name));
+            let deployer = Signers::of(settings.and_then(|settings| settings.deployer.as_ref()));
+            let Some(registry) = Self::registry_args(environment, deployer) else {
+                return Ok(());
+            };
+            let package = Self::contract_package(workspace_root, &contract.name)?;
+            let wasm_path = workspace_root.join(format!("target/loam/{}.wasm", contract.name));
+            publish::is_published(
+                &registry,
+                &package.name,
+                &package.version.to_string(),
+                &wasm_path,
+            )
+            .await?;
         }
+        Ok(())
     }
 
-    async fn run_init_script(
+    /// The package of the workspace which builds the contract `name`
+    fn contract_package(
+        workspace_root: &std::path::Path,
+        name: &str,
+    ) -> Result<cargo_metadata::Package, Error> {
+        cargo_metadata::MetadataCommand::new()
+            .manifest_path(workspace_root.join("Cargo.toml"))
+            .no_deps()
+            .exec()?
+            .packages
+            .into_iter()
+            .find(|p| p.name.replace('-', "_") == name)
+            .ok_or_else(|| Error::BadContractName(name.to_string()))
+    }
+
+    /// The registry of `environment`, if it has one, signed for by `deployer` or else the
+    /// default account, on the network currently selected
+    fn registry_args(
+        environment: &env_toml::Environment,
+        deployer: &[String],
+    ) -> Option<registry::Args> {
+        Some(registry::Args {
+            registry: environment.registry.clone()?,
+            source_account: deployer
+                .first()
+                .cloned()
+                .or_else(|| std::env::var("STELLAR_ACCOUNT").ok()),
+            rpc_url: std::env::var("STELLAR_RPC_URL").ok(),
+            network_passphrase: std::env::var("STELLAR_NETWORK_PASSPHRASE").ok(),
+            network: None,
+        })
+    }
+
+    /// Run the init of a contract, resuming it if it failed against the contract before. When a
+    /// call fails, the calls which succeeded are recorded so that the next build resumes from
+    /// the one which failed, unless the contract is to be dropped and deployed again. Calls which
+    /// don't name their own source are signed by `init_source`.
+    #[allow(clippy::too_many_arguments)]
+    async fn initialize(
         &self,
+        workspace_root: &std::path::Path,
         name: &str,
         contract_id: &str,
-        init_script: &str,
+        init: &env_toml::Init,
+        init_source: &[String],
+        references: &init_script::References<'_>,
+        redeploy: bool,
+        state: &mut State,
     ) -> Result<(), Error> {
-        let re = Regex::new(r"\$\((.*?)\)").expect("Invalid regex pattern");
-
-        let (shell, flag) = if cfg!(windows) {
-            ("cmd", "/C")
+        eprintln!("🚀 Running initialization script for {name:?}");
+        let ids = references.ids_referenced_by(init);
+        let mut completed = if self.reinit {
+            Vec::new()
         } else {
-            ("sh", "-c")
+            state.completed_calls(contract_id)
+        };
+        let result = init_script::run(
+            workspace_root,
+            name,
+            contract_id,
+            init,
+            init_source,
+            references,
+            &mut completed,
+        )
+        .await;
+        let Err(source) = result else {
+            state.record_init(contract_id, name, init, &ids);
+            state.save(workspace_root)?;
+            return Ok(());
         };
+        if redeploy {
+            state.forget(contract_id);
+            let passphrase = std::env::var("STELLAR_NETWORK_PASSPHRASE")
+                .expect("No STELLAR_NETWORK_PASSPHRASE environment variable set");
+            Self::get_config_locator(workspace_root).remove_contract_id(&passphrase, name)?;
+        } else {
+            state.record_failed_init(contract_id, name, init, &ids, completed.clone());
+        }
+        state.save(workspace_root)?;
+        Err(Error::InitFailed {
+            contract: name.to_string(),
+            completed: completed.len(),
+            total: init_script::calls(init).len(),
+            redeploy,
+            source: Box::new(source),
+        })
+    }
 
-        for line in init_script.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
+    /// Ids of the contracts which are already known before a plan is carried out: those pinned in
+    /// the environment and those the plan reuses
+    fn known_contract_ids(
+        environment: &env_toml::Environment,
+        plan: &Plan,
+    ) -> IndexMap<String, String> {
+        environment
+            .contracts
+            .iter()
+            .flat_map(|contracts| contracts.iter())
+            .filter_map(|(name, settings)| Some((name.to_string(), settings.id.clone()?)))
+            .chain(plan.contracts.iter().filter_map(|contract| {
+                Some((contract.name.clone(), contract.contract_id.clone()?))
+            }))
+            .collect()
+    }
 
-            // resolve any $() patterns
-            let resolved_line = Self::resolve_line(&re, line, shell, flag)?;
-            let parts = split(&resolved_line)
-                .ok_or_else(|| Error::InitParseFailure(resolved_line.to_string()))?;
-            let (source_account, command_parts): (Vec<_>, Vec<_>) = parts
-                .iter()
-                .partition(|&part| part.starts_with("STELLAR_ACCOUNT="));
-
-            let mut args = vec!["--id", contract_id];
-            if let Some(account) = source_account.first() {
-                let account = account.strip_prefix("STELLAR_ACCOUNT=").unwrap();
-                args.extend_from_slice(&["--source-account", account]);
-            }
-            args.extend_from_slice(&["--"]);
-            args.extend(command_parts.iter().map(|s| s.as_str()));
+    /// Carry out a plan: install and deploy contracts, run their init scripts and generate their
+    /// clients
+    async fn apply(
+        &self,
+        workspace_root: &std::path::Path,
+        environment: &env_toml::Environment,
+        plan: Plan,
+    ) -> Result<(), Error> {
+        let contracts = environment.contracts.as_ref();
+        let accounts =
+            Self::account_addresses(environment.accounts.as_deref().unwrap_or_default())?;
+        let watch_only = environment
+            .accounts
+            .iter()
+            .flatten()
+            .filter(|account| account.public_key.is_some())
+            .map(|account| account.name.clone())
+            .collect::<Vec<_>>();
+        // ids of the contracts deployed so far, which inits can refer to
+        let mut contract_ids = Self::known_contract_ids(environment, &plan);
+        let mut state = State::load(workspace_root)?;
+        let mut current_network = environment.network.clone();
+        for contract in plan.contracts {
+            let name = &contract.name;
+            let settings = contracts.and_then(|contracts| contracts.get(name as &str));
+            let deployer = Signers::of(settings.and_then(|settings| settings.deployer.as_ref()));
+            let network = contract.network.as_ref().unwrap_or(&environment.network);
+            Self::switch_network(&mut current_network, network)?;
+            let registry = Self::registry_args(environment, deployer);
+            let mut init = contract.init;
+            let contract_id = match contract.action {
+                Action::Reuse => {
+                    eprintln!("✅ Contract {name:?} is up to date");
+                    if !init {
+                        continue;
+                    }
+                    contract
+                        .contract_id
+                        .clone()
+                        .expect("contracts to reuse have an id")
+                }
+                Action::BindOnly => contract
+                    .contract_id
+                    .clone()
+                    .expect("contracts to bind have an id"),
+                Action::Deploy | Action::Replace | Action::Upgrade if registry.is_some() => {
+                    let registry = registry.as_ref().expect("checked by the match guard");
+                    let (contract_id, redeployed) =
+                        Self::deploy_through_registry(workspace_root, name, registry).await?;
+                    // a redeployed contract keeps its storage, so it was initialized already
+                    init = !redeployed && Plan::runs_init(environment, settings);
+                    contract_ids.insert(name.clone(), contract_id.clone());
+                    contract_id
+                }
+                Action::Upgrade => {
+                    let contract_id = contract
+                        .contract_id
+                        .clone()
+                        .expect("contracts to upgrade have an id");
+                    Self::upgrade_contract(workspace_root, name, &contract_id, settings).await?;
+                    contract_id
+                }
+                Action::Deploy | Action::Replace => {
+                    if contract.action == Action::Replace {
+                        eprintln!("🔄 Updating contract {name:?}");
+                    }
+                    let new_contract_id =
+                        Self::deploy_contract(workspace_root, name, deployer).await?;
+                    contract_ids.insert(name.clone(), new_contract_id.clone());
+                    new_contract_id
+                }
+            };
 
-            eprintln!("  ↳ Executing: stellar contract invoke {}", args.join(" "));
-            let result = cli::contract::invoke::Cmd::parse_arg_vec(&args)?
-                .run_against_rpc_server(None, None)
+            if init {
+                if let Some((settings, init)) =
+                    settings.and_then(|settings| Some((settings, settings.init.as_ref()?)))
+                {
+                    let references = init_script::References {
+                        accounts: &accounts,
+                        contracts: &contract_ids,
+                        watch_only: &watch_only,
+                    };
+                    let redeploy = contract.action != Action::BindOnly
+                        && settings.on_init_failure == OnInitFailure::Redeploy;
+                    self.initialize(
+                        workspace_root,
+                        name,
+                        &contract_id,
+                        init,
+                        Signers::of(settings.init_source.as_ref()),
+                        &references,
+                        redeploy,
+                        &mut state,
+                    )
+                    .await?;
+                }
+            }
+            if contract.bind {
+                Self::generate_contract_bindings(
+                    workspace_root,
+                    name,
+                    &contract_id,
+                    environment.allows_http(),
+                )
                 .await?;
-            eprintln!("  ↳ Result: {result:?}");
+            }
         }
-        eprintln!("✅ Initialization script for {name:?} completed successfully");
+        Self::switch_network(&mut current_network, &environment.network)?;
+
         Ok(())
     }
 }
+
+fn after_init_failure(redeploy: bool) -> &'static str {
+    if redeploy {
+        "so the contract was dropped, and the next build deploys a new one"
+    } else {
+        "and the next build resumes from the call which failed"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn contracts(toml: &str) -> IndexMap<Box<str>, env_toml::Contract> {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn keys_are_generated_or_imported_without_the_network() {
+        let config_dir = assert_fs::TempDir::new().unwrap();
+        let locator = soroban_cli::config::locator::Args {
+            global: false,
+            config_dir: Some(config_dir.to_path_buf()),
+        };
+        let secret_key = Secret::from_seed(None)
+            .unwrap()
+            .private_key(None)
+            .unwrap()
+            .to_string();
+        let var = |name: &str| (name == "BOB_SECRET").then(|| secret_key.clone());
+        let account = |toml: &str| toml::from_str::<env_toml::Account>(toml).unwrap();
+        let address = |name: &str| {
+            let secret = locator.read_identity(name).unwrap();
+            secret.public_key(None).unwrap().to_string()
+        };
+
+        let alice = account(r#"name = "alice""#);
+        Args::handle_keys(&locator, &alice, var).unwrap();
+        let generated = address("alice");
+        Args::handle_keys(&locator, &alice, var).unwrap();
+        assert_eq!(address("alice"), generated);
+
+        let bob = account(
+            r#"name = "bob"
+secret-key-env = "BOB_SECRET""#,
+        );
+        Args::handle_keys(&locator, &bob, var).unwrap();
+        let imported = Secret::SecretKey {
+            secret_key: secret_key.clone(),
+        }
+        .public_key(None)
+        .unwrap();
+        assert_eq!(address("bob"), imported.to_string());
+
+        let carol = account(
+            r#"name = "carol"
+seed-phrase-env = "CAROL_SEED""#,
+        );
+        assert!(matches!(
+            Args::handle_keys(&locator, &carol, var),
+            Err(Error::MissingKeyVariable(name, var)) if name == "carol" && var == "CAROL_SEED"
+        ));
+
+        let dave = account(
+            r#"name = "dave"
+public-key = "GAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAWHF""#,
+        );
+        Args::handle_keys(&locator, &dave, var).unwrap();
+        assert!(locator.read_identity("dave").is_err());
+        assert_eq!(
+            Args::account_address(&locator, &dave).unwrap().as_deref(),
+            dave.public_key.as_deref()
+        );
+    }
+
+    #[test]
+    fn contracts_are_deployed_after_those_their_init_refers_to() {
+        let contracts = contracts(
+            r#"
+            vault = { init = "init --token {{ contracts.token.id }}" }
+            token = { init = "initialize --admin {{accounts.alice.address}}" }
+            "#,
+        );
+        let order = Args::deploy_order(&names(&["vault", "token", "oracle"]), Some(&contracts));
+        assert_eq!(order.unwrap(), names(&["token", "vault", "oracle"]));
+    }
+
+    #[test]
+    fn contracts_whose_inits_refer_to_each_other_cannot_be_deployed() {
+        let contracts = contracts(
+            r#"
+            a = { init = "init --b {{contracts.b.id}}" }
+            b = { init = "init --a {{contracts.a.id}}" }
+            c = { init = "init --c {{contracts.c.id}}" }
+            "#,
+        );
+        let order = Args::deploy_order(&names(&["a", "b", "c"]), Some(&contracts));
+        assert!(matches!(order, Err(Error::DependencyCycle(cycle)) if cycle == names(&["a", "b"])));
+    }
+}
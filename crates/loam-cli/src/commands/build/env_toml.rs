@@ -1,6 +1,9 @@
 use indexmap::IndexMap;
+use itertools::Itertools;
+use regex::Regex;
 use serde::Deserialize;
 use std::collections::BTreeMap as Map;
+use std::fmt;
 use std::path::Path;
 use toml::value::Table;
 
@@ -10,19 +13,457 @@ pub const ENV_FILE: &str = "environments.toml";
 pub enum Error {
     #[error("⛔ ️parsing environments.toml: {0}")]
     ParsingToml(#[from] toml::de::Error),
-    #[error("⛔ ️no settings for current LOAM_ENV ({0:?}) found in environments.toml")]
-    NoSettingsForCurrentEnv(String),
+    #[error("⛔ ️no settings for current LOAM_ENV ({0:?}) found in environments.toml{}", .1.as_ref().map(|name| format!(", did you mean {name:?}?")).unwrap_or_default())]
+    NoSettingsForCurrentEnv(String, Option<String>),
     #[error("⛔ ️reading environments.toml as a string: {0}")]
     ParsingString(#[from] std::io::Error),
+    #[error("⛔ ️environments.toml is invalid:\n{}", .0.iter().map(|problem| format!("  {problem}")).join("\n"))]
+    Invalid(Vec<Problem>),
 }
 
-type Environments = Map<Box<str>, Environment>;
+/// Something wrong at a key of `environments.toml`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    /// Dotted path of the key, e.g. `development.contracts.hello_world`
+    pub path: String,
+    pub message: String,
+}
+
+impl Problem {
+    pub fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Problem {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+pub const ENVIRONMENT_KEYS: &[&str] = &[
+    "extends",
+    "accounts",
+    "network",
+    "contracts",
+    "deploy",
+    "run-init",
+    "require-ids",
+    "allow-http",
+    "registry",
+];
+pub const NETWORK_KEYS: &[&str] = &["name", "rpc-url", "network-passphrase", "run-locally"];
+pub const ACCOUNT_KEYS: &[&str] = &[
+    "name",
+    "default",
+    "secret-key-env",
+    "seed-phrase-env",
+    "public-key",
+    "fund",
+];
+pub const CONTRACT_KEYS: &[&str] = &[
+    "client",
+    "init",
+    "id",
+    "upgrade",
+    "on-init-failure",
+    "network",
+    "deployer",
+    "admin",
+    "init-source",
+];
+pub const INIT_STEP_KEYS: &[&str] = &["fn", "args", "source"];
+
+/// The closest of `candidates` to a misspelled `name`, if any is close enough
+pub fn did_you_mean<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    candidates
+        .into_iter()
+        .map(|candidate| (strsim::jaro_winkler(name, candidate), candidate))
+        .filter(|(similarity, _)| *similarity > 0.8)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, candidate)| candidate)
+}
+
+/// Describe `name`, which isn't one of `expected`
+pub fn unknown(kind: &str, name: &str, expected: &[&str]) -> String {
+    match did_you_mean(name, expected.iter().copied()) {
+        Some(suggestion) => format!("unknown {kind} `{name}`, did you mean `{suggestion}`?"),
+        None => format!(
+            "unknown {kind} `{name}`, expected one of {}",
+            expected.iter().map(|key| format!("`{key}`")).join(", ")
+        ),
+    }
+}
+
+fn check_keys(path: &str, table: &Table, expected: &[&str], problems: &mut Vec<Problem>) {
+    for key in table.keys() {
+        if !expected.contains(&key.as_str()) {
+            problems.push(Problem::new(path, unknown("key", key, expected)));
+        }
+    }
+}
+
+fn check_network(path: &str, network: &toml::Value, problems: &mut Vec<Problem>) {
+    if let toml::Value::Table(network) = network {
+        check_keys(path, network, NETWORK_KEYS, problems);
+    }
+}
+
+/// Environments named after the stages of a project, whose policy defaults by name
+const WELL_KNOWN_ENVIRONMENTS: &[&str] = &["development", "testing", "staging", "production"];
+
+/// Keys of an environment which set its policy, so that it doesn't depend on the name
+const POLICY_KEYS: &[&str] = &["deploy", "run-init", "require-ids", "allow-http"];
+
+/// Whether `name` looks like a misspelling of a well-known environment or of `networks`. An
+/// environment which sets any of its policy, or extends another, doesn't rely on its name, so it
+/// can be called anything.
+fn is_misspelled(name: &str, table: &Table) -> Option<&'static str> {
+    if table.contains_key(EXTENDS) || POLICY_KEYS.iter().any(|key| table.contains_key(*key)) {
+        return None;
+    }
+    let names = WELL_KNOWN_ENVIRONMENTS.iter().copied().chain([NETWORKS]);
+    did_you_mean(name, names).filter(|suggestion| *suggestion != name)
+}
+
+/// Check the keys of `environments.toml`, which serde would otherwise only reject one at a time,
+/// without suggestions. The types of the values are left to serde.
+pub fn check(toml: &Table) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for (name, value) in toml {
+        let toml::Value::Table(table) = value else {
+            problems.push(Problem::new(name, "is not an environment table"));
+            continue;
+        };
+        if let Some(suggestion) = is_misspelled(name, table) {
+            problems.push(Problem::new(
+                name,
+                format!("unknown environment `{name}`, did you mean `{suggestion}`?"),
+            ));
+            continue;
+        }
+        if name == NETWORKS {
+            for (network_name, network) in table {
+                check_network(
+                    &format!("{NETWORKS}.{network_name}"),
+                    network,
+                    &mut problems,
+                );
+            }
+            continue;
+        }
+        check_keys(name, table, ENVIRONMENT_KEYS, &mut problems);
+        if let Some(network) = table.get("network") {
+            check_network(&format!("{name}.network"), network, &mut problems);
+        }
+        if let Some(toml::Value::Array(accounts)) = table.get("accounts") {
+            for (i, account) in accounts.iter().enumerate() {
+                if let toml::Value::Table(account) = account {
+                    let path = format!("{name}.accounts[{i}]");
+                    check_keys(&path, account, ACCOUNT_KEYS, &mut problems);
+                }
+            }
+        }
+        if let Some(toml::Value::Table(contracts)) = table.get("contracts") {
+            for (contract_name, contract) in contracts {
+                let path = format!("{name}.contracts.{contract_name}");
+                if let toml::Value::Table(contract) = contract {
+                    check_keys(&path, contract, CONTRACT_KEYS, &mut problems);
+                    if let Some(network) = contract.get("network") {
+                        check_network(&format!("{path}.network"), network, &mut problems);
+                    }
+                    if let Some(toml::Value::Array(steps)) = contract.get("init") {
+                        for (i, step) in steps.iter().enumerate() {
+                            if let toml::Value::Table(step) = step {
+                                let path = format!("{path}.init[{i}]");
+                                check_keys(&path, step, INIT_STEP_KEYS, &mut problems);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    problems
+}
+
+/// Key naming the environment which an environment extends
+const EXTENDS: &str = "extends";
+
+/// Merge each environment which `extends` another over the environment it extends, recursively
+fn extend(toml: &mut Table) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut extended = Vec::new();
+    for name in toml.keys().filter(|name| *name != NETWORKS) {
+        match extended_environment(toml, name, &mut vec![]) {
+            Ok(Some(environment)) => extended.push((name.clone(), environment)),
+            Ok(None) => {}
+            Err(problem) => problems.push(problem),
+        }
+    }
+    for (name, environment) in extended {
+        toml.insert(name, toml::Value::Table(environment));
+    }
+    problems
+}
+
+/// The environment `name` merged over the environments it extends, if it extends any. `chain`
+/// holds the environments which extend it, to catch cycles.
+fn extended_environment(
+    toml: &Table,
+    name: &str,
+    chain: &mut Vec<String>,
+) -> Result<Option<Table>, Problem> {
+    let Some(toml::Value::Table(environment)) = toml.get(name) else {
+        return Ok(None);
+    };
+    let Some(base) = environment.get(EXTENDS) else {
+        return Ok(None);
+    };
+    let path = format!("{name}.{EXTENDS}");
+    let toml::Value::String(base) = base else {
+        return Err(Problem::new(path, "must be the name of an environment"));
+    };
+    let environments = toml
+        .keys()
+        .map(String::as_str)
+        .filter(|environment| *environment != NETWORKS && *environment != name)
+        .collect::<Vec<_>>();
+    if !environments.contains(&base.as_str()) {
+        return Err(Problem::new(
+            path,
+            unknown("environment", base, &environments),
+        ));
+    }
+    chain.push(name.to_string());
+    if chain.contains(base) {
+        chain.push(base.clone());
+        return Err(Problem::new(
+            format!("{}.{EXTENDS}", chain[0]),
+            format!("environments extend each other: {}", chain.join(" → ")),
+        ));
+    }
+    let mut merged = match extended_environment(toml, base, chain)? {
+        Some(merged) => merged,
+        None => match toml.get(base) {
+            Some(toml::Value::Table(base)) => base.clone(),
+            _ => Table::new(),
+        },
+    };
+    let mut environment = environment.clone();
+    environment.remove(EXTENDS);
+    merge(&mut merged, environment);
+    Ok(Some(merged))
+}
+
+/// Merge `overrides` into `base`: tables are merged key by key, `accounts` are merged by name,
+/// and any other value replaces the one it overrides
+fn merge(base: &mut Table, overrides: Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overrides)) => {
+                merge(base, overrides);
+            }
+            (Some(toml::Value::Array(base)), toml::Value::Array(overrides))
+                if key == "accounts" =>
+            {
+                merge_accounts(base, overrides);
+            }
+            (Some(slot), value) => *slot = value,
+            (None, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
 
-#[derive(Debug, Clone)]
+/// Merge accounts by name. A new default account replaces the default of `base`.
+fn merge_accounts(base: &mut Vec<toml::Value>, overrides: Vec<toml::Value>) {
+    fn name(account: &toml::Value) -> Option<&str> {
+        match account {
+            toml::Value::String(name) => Some(name),
+            toml::Value::Table(account) => account.get("name").and_then(toml::Value::as_str),
+            _ => None,
+        }
+    }
+    fn is_default(account: &toml::Value) -> bool {
+        account
+            .get("default")
+            .and_then(toml::Value::as_bool)
+            .unwrap_or_default()
+    }
+    if overrides.iter().any(is_default) {
+        for account in base.iter_mut() {
+            if let toml::Value::Table(account) = account {
+                account.remove("default");
+            }
+        }
+    }
+    for account in overrides {
+        match base
+            .iter_mut()
+            .find(|existing| name(existing) == name(&account))
+        {
+            Some(existing) => *existing = account,
+            None => base.push(account),
+        }
+    }
+}
+
+/// Variables set in the `.env` file of the workspace, if it has one
+fn dotenv(workspace_root: &Path) -> Result<Map<String, String>, Error> {
+    let path = workspace_root.join(".env");
+    if !path.exists() {
+        return Ok(Map::new());
+    }
+    Ok(std::fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.strip_prefix("export ").unwrap_or(line).split_once('='))
+        .map(|(name, value)| {
+            let value = value.trim();
+            let unquoted = [('"', '"'), ('\'', '\'')]
+                .iter()
+                .find_map(|(start, end)| value.strip_prefix(*start)?.strip_suffix(*end))
+                .unwrap_or(value);
+            (name.trim().to_string(), unquoted.to_string())
+        })
+        .collect())
+}
+
+/// Replace each `${VAR}` in the strings of `value` with the variable's value, reporting variables
+/// which aren't set
+fn interpolate(
+    path: &str,
+    value: &mut toml::Value,
+    var: &impl Fn(&str) -> Option<String>,
+    problems: &mut Vec<Problem>,
+) {
+    match value {
+        toml::Value::String(string) => {
+            let re = Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}").expect("valid regex");
+            let mut missing = Vec::new();
+            let interpolated = re.replace_all(string, |captures: &regex::Captures| {
+                var(&captures[1]).unwrap_or_else(|| {
+                    missing.push(captures[1].to_string());
+                    String::new()
+                })
+            });
+            *string = interpolated.into_owned();
+            for name in missing {
+                problems.push(Problem::new(
+                    path,
+                    format!("`${{{name}}}` is not set, in the environment or in .env"),
+                ));
+            }
+        }
+        toml::Value::Array(values) => {
+            for (i, value) in values.iter_mut().enumerate() {
+                interpolate(&format!("{path}[{i}]"), value, var, problems);
+            }
+        }
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                interpolate(&format!("{path}.{key}"), value, var, problems);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Interpolate the values of an environment, except for `init` scripts, whose `${...}` are left
+/// for the shell which runs them
+fn interpolate_environment(
+    name: &str,
+    environment: &mut Table,
+    var: &impl Fn(&str) -> Option<String>,
+    problems: &mut Vec<Problem>,
+) {
+    for (key, value) in environment {
+        let path = format!("{name}.{key}");
+        match value {
+            toml::Value::Table(contracts) if key == "contracts" => {
+                for (contract_name, contract) in contracts {
+                    let path = format!("{path}.{contract_name}");
+                    let toml::Value::Table(contract) = contract else {
+                        continue;
+                    };
+                    for (key, value) in contract.iter_mut().filter(|(key, _)| *key != "init") {
+                        interpolate(&format!("{path}.{key}"), value, var, problems);
+                    }
+                }
+            }
+            value => interpolate(&path, value, var, problems),
+        }
+    }
+}
+
+/// Each environment by name, or the problems which kept it from being parsed
+pub type ParsedEnvironments = Vec<(String, Result<Environment, Vec<Problem>>)>;
+
+/// Networks defined in the top-level `[networks]` table, which environments and contracts can
+/// refer to by name
+type Networks = Map<Box<str>, Network>;
+
+/// Key of the `[networks]` table, which is therefore not an environment
+const NETWORKS: &str = "networks";
+
+/// Name of the environment used by `loam build` when `LOAM_ENV` isn't set
+pub const DEFAULT_ENV: &str = "production";
+
+/// Name of the environment used by `loam dev` when `LOAM_ENV` isn't set
+pub const DEFAULT_DEV_ENV: &str = "development";
+
+#[derive(Debug, Clone, Default)]
 pub struct Environment {
+    /// Name of the environment's table in `environments.toml`
+    pub name: String,
     pub accounts: Option<Vec<Account>>,
     pub network: Network,
     pub contracts: Option<IndexMap<Box<str>, Contract>>,
+    pub policy: Policy,
+    /// Id or alias of a registry contract, which the workspace's contracts are published to and
+    /// deployed through
+    pub registry: Option<String>,
+}
+
+/// What `loam build` does in an environment. Flags which aren't set depend on the name of the
+/// environment, so that `development`, `testing`, `staging` and `production` keep working as they
+/// always have.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Policy {
+    /// Deploy the workspace's contracts which have no `id`. Defaults to true for `development`
+    /// and `testing`.
+    pub deploy: Option<bool>,
+    /// Run the `init` scripts of newly deployed contracts. Defaults to `deploy`.
+    pub run_init: Option<bool>,
+    /// Require every contract to have an `id`. Defaults to the opposite of `deploy`.
+    pub require_ids: Option<bool>,
+    /// Let the generated clients talk to an rpc over plain HTTP. Defaults to true for
+    /// `development`.
+    pub allow_http: Option<bool>,
+}
+
+fn deserialize_network<'de, D>(deserializer: D) -> Result<Network, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(NetworkRepresentation::deserialize(deserializer)?.into())
+}
+
+fn deserialize_optional_network<'de, D>(deserializer: D) -> Result<Option<Network>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let opt: Option<NetworkRepresentation> = Option::deserialize(deserializer)?;
+    Ok(opt.map(Network::from))
 }
 
 fn deserialize_accounts<'de, D>(deserializer: D) -> Result<Option<Vec<Account>>, D::Error>
@@ -39,11 +480,18 @@ impl<'de> Deserialize<'de> for Environment {
         D: serde::Deserializer<'de>,
     {
         #[derive(Deserialize)]
+        #[serde(rename_all = "kebab-case", deny_unknown_fields)]
         struct EnvironmentHelper {
             #[serde(default, deserialize_with = "deserialize_accounts")]
             accounts: Option<Vec<Account>>,
+            #[serde(deserialize_with = "deserialize_network")]
             network: Network,
             contracts: Option<Table>,
+            deploy: Option<bool>,
+            run_init: Option<bool>,
+            require_ids: Option<bool>,
+            allow_http: Option<bool>,
+            registry: Option<String>,
         }
 
         let helper = EnvironmentHelper::deserialize(deserializer)?;
@@ -63,20 +511,78 @@ impl<'de> Deserialize<'de> for Environment {
             .transpose()?;
 
         Ok(Environment {
+            name: String::new(),
             accounts: helper.accounts,
             network: helper.network,
             contracts,
+            policy: Policy {
+                deploy: helper.deploy,
+                run_init: helper.run_init,
+                require_ids: helper.require_ids,
+                allow_http: helper.allow_http,
+            },
+            registry: helper.registry,
         })
     }
 }
 
-#[derive(Debug, serde::Deserialize, Clone)]
-#[serde(rename_all = "kebab-case")]
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct Network {
+    /// Name of a network in the `[networks]` table, or else in the stellar CLI's config
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub rpc_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub network_passphrase: Option<String>,
-    // run_locally: Option<bool>,
+    /// Whether the network is a node run on this machine
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub run_locally: bool,
+}
+
+/// A network can be given by name, as a shorthand for `{ name = "..." }`
+#[derive(Debug, serde::Deserialize)]
+#[serde(untagged)]
+enum NetworkRepresentation {
+    Simple(String),
+    Detailed(Network),
+}
+
+impl From<NetworkRepresentation> for Network {
+    fn from(rep: NetworkRepresentation) -> Self {
+        match rep {
+            NetworkRepresentation::Simple(name) => Network {
+                name: Some(name),
+                ..Default::default()
+            },
+            NetworkRepresentation::Detailed(network) => network,
+        }
+    }
+}
+
+impl Network {
+    /// Replace a reference to a network of the `[networks]` table with its definition. Other
+    /// names are left to be looked up in the stellar CLI's config.
+    fn resolve(self, networks: &Networks) -> Self {
+        match &self {
+            Network {
+                name: Some(name),
+                rpc_url: None,
+                network_passphrase: None,
+                run_locally: false,
+            } => networks.get(name.as_str()).cloned().unwrap_or(self),
+            _ => self,
+        }
+    }
+
+    /// How to refer to the network in messages
+    pub fn label(&self) -> &str {
+        self.name
+            .as_deref()
+            .or(self.rpc_url.as_deref())
+            .unwrap_or("unknown network")
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -86,11 +592,102 @@ pub enum AccountRepresentation {
     Detailed(Account),
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
 pub struct Account {
     pub name: String,
     #[serde(default)]
     pub default: bool,
+    /// Variable with the account's secret key, which is imported rather than generated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_key_env: Option<String>,
+    /// Variable with the account's seed phrase, which is imported rather than generated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed_phrase_env: Option<String>,
+    /// Address of an account which `loam build` has no keys for, so it can't sign
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    #[serde(default)]
+    pub fund: Fund,
+}
+
+/// Where the keys of an account come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keys<'a> {
+    /// Generated by `loam build`, unless the stellar CLI already has keys by the account's name
+    Generated,
+    /// Read from a variable, of the process's environment or the workspace's `.env`
+    SecretKeyEnv(&'a str),
+    SeedPhraseEnv(&'a str),
+    /// None: the account is watch-only
+    PublicKey(&'a str),
+}
+
+/// How to fund an account which doesn't exist on the network yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum Fund {
+    /// Whether to fund the account with the network's friendbot
+    Friendbot(bool),
+    /// Create the account with this many lumens, paid by the default account
+    Lumens(u64),
+}
+
+impl Default for Fund {
+    fn default() -> Self {
+        Fund::Friendbot(true)
+    }
+}
+
+impl Account {
+    /// Where the account's keys come from, or why that's ambiguous
+    pub fn keys(&self) -> Result<Keys<'_>, String> {
+        match (
+            self.secret_key_env.as_deref(),
+            self.seed_phrase_env.as_deref(),
+            self.public_key.as_deref(),
+        ) {
+            (None, None, None) => Ok(Keys::Generated),
+            (Some(var), None, None) => Ok(Keys::SecretKeyEnv(var)),
+            (None, Some(var), None) => Ok(Keys::SeedPhraseEnv(var)),
+            (None, None, Some(public_key)) => {
+                if stellar_strkey::ed25519::PublicKey::from_string(public_key).is_err() {
+                    return Err(format!("`{public_key}` is not a public key"));
+                }
+                Ok(Keys::PublicKey(public_key))
+            }
+            _ => Err(
+                "can only have one of `secret-key-env`, `seed-phrase-env` and `public-key`"
+                    .to_string(),
+            ),
+        }
+    }
+
+    /// Problems with the account's settings, reported at `path`
+    pub fn problems(&self, path: &str) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        match self.keys() {
+            Err(message) => problems.push(Problem::new(path, message)),
+            Ok(Keys::PublicKey(_)) if self.default => problems.push(Problem::new(
+                path,
+                "is watch-only, so it can't be the default account, which signs transactions",
+            )),
+            Ok(_) => {}
+        }
+        if self.default && matches!(self.fund, Fund::Lumens(_)) {
+            problems.push(Problem::new(
+                format!("{path}.fund"),
+                "can't be an amount for the default account, which pays for the accounts which are",
+            ));
+        }
+        if self.fund == Fund::Lumens(0) {
+            problems.push(Problem::new(
+                format!("{path}.fund"),
+                "must be `true`, `false` or a number of lumens greater than 0",
+            ));
+        }
+        problems
+    }
 }
 
 impl From<AccountRepresentation> for Account {
@@ -98,7 +695,7 @@ impl From<AccountRepresentation> for Account {
         match rep {
             AccountRepresentation::Simple(name) => Account {
                 name,
-                default: false,
+                ..Account::default()
             },
             AccountRepresentation::Detailed(account) => account,
         }
@@ -106,15 +703,127 @@ impl From<AccountRepresentation> for Account {
 }
 
 #[derive(Debug, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
 pub struct Contract {
     #[serde(default = "default_client", skip_serializing_if = "std::ops::Not::not")]
     pub client: bool,
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub init: Option<String>,
+    pub init: Option<Init>,
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub id: Option<String>,
+
+    #[serde(default)]
+    pub upgrade: Upgrade,
+
+    #[serde(default, rename = "on-init-failure")]
+    pub on_init_failure: OnInitFailure,
+
+    /// Network to deploy the contract to, instead of the environment's
+    #[serde(default, deserialize_with = "deserialize_optional_network")]
+    pub network: Option<Network>,
+
+    /// Accounts which install and deploy the contract, instead of the default account
+    #[serde(default)]
+    pub deployer: Option<Signers>,
+
+    /// Accounts which upgrade the contract with its `redeploy` function, instead of the default
+    /// account
+    #[serde(default)]
+    pub admin: Option<Signers>,
+
+    /// Accounts which sign the calls of the init that don't name their own, instead of the
+    /// default account
+    #[serde(default, rename = "init-source")]
+    pub init_source: Option<Signers>,
+}
+
+impl Contract {
+    /// Accounts which sign the contract's transactions instead of the default account, by the
+    /// key which names them
+    pub fn signers(&self) -> impl Iterator<Item = (&'static str, &[String])> {
+        [
+            ("deployer", &self.deployer),
+            ("admin", &self.admin),
+            ("init-source", &self.init_source),
+        ]
+        .into_iter()
+        .filter_map(|(key, signers)| Some((key, signers.as_ref()?.names())))
+    }
+}
+
+/// Accounts which sign a transaction: one account, or several which each sign it, the first
+/// being its source. Several are needed by an account which has other accounts as signers, such
+/// as one held by a custody setup whose thresholds need more than one signature.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Signers {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Signers {
+    pub fn names(&self) -> &[String] {
+        match self {
+            Signers::One(name) => std::slice::from_ref(name),
+            Signers::Many(names) => names,
+        }
+    }
+
+    /// Names of the accounts, or none if the default account signs
+    pub fn of(signers: Option<&Signers>) -> &[String] {
+        signers.map_or(&[], Signers::names)
+    }
+}
+
+/// How to initialize a newly deployed contract
+#[derive(Debug, Deserialize, serde::Serialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Init {
+    /// Arguments of `stellar contract invoke`, one call per line, split like a shell would
+    Script(String),
+    /// Calls given as tables, e.g. `[[development.contracts.token.init]]`, which are checked
+    /// against the contract's spec before any is made
+    Steps(Vec<InitStep>),
+}
+
+/// A call of one of the contract's functions
+#[derive(Debug, Deserialize, serde::Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct InitStep {
+    #[serde(rename = "fn")]
+    pub function: String,
+    /// Arguments by name. Arguments which are addresses can be given as the name of an account
+    /// or of another contract of the environment.
+    #[serde(default)]
+    pub args: IndexMap<String, toml::Value>,
+    /// Account to sign the call with, instead of the default account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// What to do with an aliased contract when the wasm built for it changes
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Upgrade {
+    /// Deploy a new contract, with a new id and empty storage, and point the alias to it
+    #[default]
+    Deploy,
+    /// Keep the contract, calling the `redeploy` function of its Core subcontract with the hash
+    /// of the new wasm
+    Redeploy,
+}
+
+/// What to do with a contract deployed by `loam build` whose init fails partway through
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OnInitFailure {
+    /// Keep the contract, and resume its init from the call which failed on the next build
+    #[default]
+    Resume,
+    /// Drop the contract, so that the next build deploys a new one and runs all of its init
+    Redeploy,
 }
 
 fn default_client() -> bool {
@@ -122,7 +831,112 @@ fn default_client() -> bool {
 }
 
 impl Environment {
-    pub fn get(workspace_root: &Path, loam_env: &str) -> Result<Option<Environment>, Error> {
+    /// Whether the workspace's contracts which have no `id` are deployed
+    pub fn deploys(&self) -> bool {
+        self.policy
+            .deploy
+            .unwrap_or(matches!(self.name.as_str(), "development" | "testing"))
+    }
+
+    /// Whether the `init` scripts of newly deployed contracts are run
+    pub fn runs_init(&self) -> bool {
+        self.policy.run_init.unwrap_or_else(|| self.deploys())
+    }
+
+    /// Whether every contract must have an `id`
+    pub fn requires_ids(&self) -> bool {
+        self.policy.require_ids.unwrap_or_else(|| !self.deploys())
+    }
+
+    /// Whether the generated clients may talk to an rpc over plain HTTP
+    pub fn allows_http(&self) -> bool {
+        self.policy.allow_http.unwrap_or(self.name == "development")
+    }
+
+    /// Problems with the accounts which sign the transactions of the environment's contracts:
+    /// each must be one of its accounts which can sign, listed once
+    pub fn signer_problems(&self) -> Vec<Problem> {
+        let accounts = self.accounts.iter().flatten().collect::<Vec<_>>();
+        let names = accounts
+            .iter()
+            .map(|account| account.name.as_str())
+            .collect::<Vec<_>>();
+        let mut problems = Vec::new();
+        for (contract_name, contract) in self.contracts.iter().flatten() {
+            for (key, signers) in contract.signers() {
+                let path = format!("{}.contracts.{contract_name}.{key}", self.name);
+                if signers.is_empty() {
+                    problems.push(Problem::new(&path, "needs at least one account"));
+                }
+                for (i, signer) in signers.iter().enumerate() {
+                    let message = match accounts.iter().find(|account| &account.name == signer) {
+                        None => unknown("account", signer, &names),
+                        Some(account) if account.public_key.is_some() => {
+                            format!("`{signer}` is watch-only, so it can't sign")
+                        }
+                        Some(_) if signers[..i].contains(signer) => {
+                            format!("lists `{signer}` more than once")
+                        }
+                        Some(_) => continue,
+                    };
+                    problems.push(Problem::new(&path, message));
+                }
+            }
+        }
+        problems
+    }
+
+    /// Problems with deploying the environment's contracts through its registry, if it has one:
+    /// the registry makes the deployer the contract's owner, so there can only be one, and the
+    /// registry is on the environment's network
+    pub fn registry_problems(&self) -> Vec<Problem> {
+        if self.registry.is_none() {
+            return Vec::new();
+        }
+        let mut problems = Vec::new();
+        for (contract_name, contract) in self.contracts.iter().flatten() {
+            let path = format!("{}.contracts.{contract_name}", self.name);
+            if contract
+                .deployer
+                .as_ref()
+                .is_some_and(|deployer| deployer.names().len() > 1)
+            {
+                problems.push(Problem::new(
+                    format!("{path}.deployer"),
+                    "contracts deployed through the registry are owned by their deployer, so there can only be one",
+                ));
+            }
+            if contract.network.is_some() {
+                problems.push(Problem::new(
+                    format!("{path}.network"),
+                    "contracts deployed through the registry are on the environment's network",
+                ));
+            }
+        }
+        problems
+    }
+
+    fn resolve(mut self, name: &str, networks: &Networks) -> Self {
+        self.name = name.to_string();
+        self.network = self.network.resolve(networks);
+        for contract in self.contracts.iter_mut().flat_map(IndexMap::values_mut) {
+            contract.network = contract.network.take().map(|n| n.resolve(networks));
+        }
+        self
+    }
+
+    /// Network of a contract, which is the environment's unless it has its own
+    pub fn network_of<'a>(&'a self, contract: Option<&'a Contract>) -> &'a Network {
+        contract
+            .and_then(|contract| contract.network.as_ref())
+            .unwrap_or(&self.network)
+    }
+
+    /// Read `environments.toml`, checking its keys and merging environments over those they
+    /// extend, and split off its `[networks]` table. Variables are interpolated later, in
+    /// [`Environment::load`], so that one environment's variables don't need to be set to use
+    /// another.
+    fn read(workspace_root: &Path) -> Result<Option<(Table, Table)>, Error> {
         let env_toml = workspace_root.join(ENV_FILE);
 
         if !env_toml.exists() {
@@ -130,11 +944,549 @@ impl Environment {
         }
 
         let toml_str = std::fs::read_to_string(env_toml)?;
-        let mut parsed_toml: Environments = toml::from_str(&toml_str)?;
-        let current_env = parsed_toml.remove(loam_env);
-        if current_env.is_none() {
-            return Err(Error::NoSettingsForCurrentEnv(loam_env.to_string()));
+        let mut parsed_toml: Table = toml::from_str(&toml_str)?;
+        let mut problems = check(&parsed_toml);
+        if problems.is_empty() {
+            problems = extend(&mut parsed_toml);
+        }
+        if !problems.is_empty() {
+            return Err(Error::Invalid(problems));
+        }
+        let networks = match parsed_toml.remove(NETWORKS) {
+            Some(toml::Value::Table(networks)) => networks,
+            _ => Table::new(),
+        };
+        Ok(Some((networks, parsed_toml)))
+    }
+
+    /// Interpolate and parse the environment `name`, resolving the networks of `[networks]` it
+    /// refers to. Only those networks are interpolated.
+    fn load(
+        name: &str,
+        value: toml::Value,
+        networks: &Table,
+        var: &impl Fn(&str) -> Option<String>,
+    ) -> Result<Environment, Vec<Problem>> {
+        let toml::Value::Table(mut table) = value else {
+            return Err(vec![Problem::new(name, "is not an environment table")]);
         };
-        Ok(current_env)
+        let mut problems = Vec::new();
+        interpolate_environment(name, &mut table, var, &mut problems);
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+        let environment = Environment::deserialize(toml::Value::Table(table))
+            .map_err(|e| vec![Problem::new(name, e.message())])?;
+
+        let referenced = std::iter::once(&environment.network)
+            .chain(
+                environment
+                    .contracts
+                    .iter()
+                    .flat_map(IndexMap::values)
+                    .filter_map(|contract| contract.network.as_ref()),
+            )
+            .filter_map(|network| network.name.as_deref())
+            .collect::<Vec<_>>();
+        let mut resolved = Networks::new();
+        for (network_name, network) in networks {
+            if !referenced.contains(&network_name.as_str()) {
+                continue;
+            }
+            let path = format!("{NETWORKS}.{network_name}");
+            let mut network = network.clone();
+            interpolate(&path, &mut network, var, &mut problems);
+            match Network::deserialize(network) {
+                Ok(network) => {
+                    resolved.insert(network_name.clone().into_boxed_str(), network);
+                }
+                Err(e) => problems.push(Problem::new(path, e.message())),
+            }
+        }
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+        Ok(environment.resolve(name, &resolved))
+    }
+
+    /// Look up `${VAR}`s in the process's environment, then in the workspace's `.env`
+    pub fn vars(workspace_root: &Path) -> Result<impl Fn(&str) -> Option<String>, Error> {
+        let dotenv = dotenv(workspace_root)?;
+        Ok(move |name: &str| {
+            std::env::var(name)
+                .ok()
+                .or_else(|| dotenv.get(name).cloned())
+        })
+    }
+
+    pub fn get(workspace_root: &Path, loam_env: &str) -> Result<Option<Environment>, Error> {
+        let Some((networks, mut environments)) = Self::read(workspace_root)? else {
+            return Ok(None);
+        };
+        let Some(current_env) = environments.remove(loam_env) else {
+            let suggestion = did_you_mean(loam_env, environments.keys().map(String::as_str));
+            return Err(Error::NoSettingsForCurrentEnv(
+                loam_env.to_string(),
+                suggestion.map(ToString::to_string),
+            ));
+        };
+        let var = Self::vars(workspace_root)?;
+        Self::load(loam_env, current_env, &networks, &var)
+            .map(Some)
+            .map_err(Error::Invalid)
+    }
+
+    /// Every environment of `environments.toml`. An environment which can't be parsed is
+    /// reported with its problems rather than failing the rest.
+    pub fn all(workspace_root: &Path) -> Result<Option<ParsedEnvironments>, Error> {
+        let Some((networks, environments)) = Self::read(workspace_root)? else {
+            return Ok(None);
+        };
+        let var = Self::vars(workspace_root)?;
+        let environments = environments
+            .into_iter()
+            .map(|(name, value)| {
+                let environment = Self::load(&name, value, &networks, &var);
+                (name, environment)
+            })
+            .collect();
+        Ok(Some(environments))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn networks_are_resolved_by_name() {
+        let root = assert_fs::TempDir::new().unwrap();
+        root.child(ENV_FILE)
+            .write_str(
+                r#"
+[networks.local]
+rpc-url = "http://localhost:8000/rpc"
+network-passphrase = "Standalone Network ; February 2017"
+run-locally = true
+
+[development]
+network = "local"
+
+[development.contracts]
+hello = { client = true }
+oracle = { client = true, network = "testnet" }
+token = { client = true, network = { rpc-url = "https://rpc.example.com", network-passphrase = "Example" } }
+"#,
+            )
+            .unwrap();
+        let environment = Environment::get(root.path(), "development")
+            .unwrap()
+            .unwrap();
+        assert!(environment.network.run_locally);
+        assert_eq!(
+            environment.network.rpc_url.as_deref(),
+            Some("http://localhost:8000/rpc")
+        );
+        let contracts = environment.contracts.as_ref().unwrap();
+        assert_eq!(
+            environment.network_of(contracts.get("hello")),
+            &environment.network
+        );
+        // names which aren't in `[networks]` are left for the stellar CLI to look up
+        assert_eq!(
+            environment
+                .network_of(contracts.get("oracle"))
+                .name
+                .as_deref(),
+            Some("testnet")
+        );
+        assert_eq!(
+            environment.network_of(contracts.get("token")).label(),
+            "https://rpc.example.com"
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_reported_with_suggestions() {
+        let toml = toml::from_str(
+            r#"
+[developmnet]
+network = "testnet"
+
+[development]
+network = { name = "testnet", rpc_url = "http://localhost:8000/rpc" }
+accounts = [{ name = "alice", defualt = true }]
+
+[development.contracts]
+hello = { clinet = false }
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            check(&toml),
+            vec![
+                Problem::new(
+                    "developmnet",
+                    "unknown environment `developmnet`, did you mean `development`?"
+                ),
+                Problem::new(
+                    "development.network",
+                    "unknown key `rpc_url`, did you mean `rpc-url`?"
+                ),
+                Problem::new(
+                    "development.accounts[0]",
+                    "unknown key `defualt`, did you mean `default`?"
+                ),
+                Problem::new(
+                    "development.contracts.hello",
+                    "unknown key `clinet`, did you mean `client`?"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn environments_have_any_name() {
+        let root = assert_fs::TempDir::new().unwrap();
+        root.child(ENV_FILE)
+            .write_str(
+                r#"
+[preview]
+network = "testnet"
+deploy = true
+allow-http = true
+
+[production]
+network = "mainnet"
+"#,
+            )
+            .unwrap();
+        let preview = Environment::get(root.path(), "preview").unwrap().unwrap();
+        assert_eq!(preview.name, "preview");
+        assert!(preview.deploys() && preview.runs_init() && preview.allows_http());
+        assert!(!preview.requires_ids());
+
+        let production = Environment::get(root.path(), "production")
+            .unwrap()
+            .unwrap();
+        assert!(!production.deploys() && !production.runs_init() && !production.allows_http());
+        assert!(production.requires_ids());
+
+        // a name close to a well-known one is fine once the environment sets its policy
+        let toml = toml::from_str("[productions]\nnetwork = \"mainnet\"\ndeploy = false").unwrap();
+        assert_eq!(check(&toml), vec![]);
+
+        let Err(Error::NoSettingsForCurrentEnv(_, suggestion)) =
+            Environment::get(root.path(), "previwe")
+        else {
+            panic!("previwe is not an environment");
+        };
+        assert_eq!(suggestion.as_deref(), Some("preview"));
+    }
+
+    #[test]
+    fn environments_are_merged_over_those_they_extend() {
+        let root = assert_fs::TempDir::new().unwrap();
+        root.child(ENV_FILE)
+            .write_str(
+                r#"
+[development]
+network = { rpc-url = "http://localhost:8000/rpc", network-passphrase = "Standalone Network ; February 2017" }
+accounts = [{ name = "alice", default = true }, "bob"]
+
+[development.contracts]
+hello = { client = true, init = "hello --to alice" }
+token = { client = false }
+
+[testing]
+extends = "development"
+network = { rpc-url = "http://localhost:9000/rpc" }
+accounts = [{ name = "carol", default = true }, "bob"]
+
+[testing.contracts]
+token = { client = true }
+
+[staging]
+extends = "testing"
+deploy = false
+"#,
+            )
+            .unwrap();
+        let testing = Environment::get(root.path(), "testing").unwrap().unwrap();
+        // tables are merged key by key
+        assert_eq!(
+            testing.network.rpc_url.as_deref(),
+            Some("http://localhost:9000/rpc")
+        );
+        assert_eq!(
+            testing.network.network_passphrase.as_deref(),
+            Some("Standalone Network ; February 2017")
+        );
+        let contracts = testing.contracts.as_ref().unwrap();
+        assert_eq!(
+            contracts["hello"].init,
+            Some(Init::Script("hello --to alice".to_string()))
+        );
+        assert!(contracts["token"].client);
+        // accounts are merged by name, and a new default replaces the old one
+        let accounts = testing.accounts.as_ref().unwrap();
+        assert_eq!(
+            accounts
+                .iter()
+                .map(|account| (account.name.as_str(), account.default))
+                .collect::<Vec<_>>(),
+            vec![("alice", false), ("bob", false), ("carol", true)]
+        );
+        // and environments can extend environments which extend others
+        let staging = Environment::get(root.path(), "staging").unwrap().unwrap();
+        assert!(!staging.deploys());
+        assert_eq!(staging.network, testing.network);
+        assert_eq!(staging.accounts.unwrap().len(), 3);
+    }
+
+    #[test]
+    fn extends_must_name_an_environment_without_cycles() {
+        let mut toml = toml::from_str(
+            r#"
+[a]
+extends = "b"
+network = "testnet"
+
+[b]
+extends = "a"
+network = "testnet"
+
+[c]
+extends = "developmnet"
+network = "testnet"
+
+[development]
+network = "testnet"
+"#,
+        )
+        .unwrap();
+        assert_eq!(check(&toml), vec![]);
+        assert_eq!(
+            extend(&mut toml),
+            vec![
+                Problem::new("a.extends", "environments extend each other: a → b → a"),
+                Problem::new("b.extends", "environments extend each other: b → a → b"),
+                Problem::new(
+                    "c.extends",
+                    "unknown environment `developmnet`, did you mean `development`?"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn variables_are_interpolated_from_the_process_then_dotenv() {
+        let root = assert_fs::TempDir::new().unwrap();
+        root.child(".env")
+            .write_str(
+                "# local settings\nLOAM_TEST_RPC_URL=http://localhost:8000/rpc\nexport LOAM_TEST_HELLO_ID=\"CFROMDOTENV\"\n",
+            )
+            .unwrap();
+        root.child(ENV_FILE)
+            .write_str(
+                r#"
+[networks.local]
+rpc-url = "${LOAM_TEST_RPC_URL}"
+network-passphrase = "Standalone Network ; February 2017"
+
+[networks.unused]
+rpc-url = "${LOAM_TEST_UNSET}"
+
+[development]
+network = "local"
+
+[development.contracts]
+hello = { id = "${LOAM_TEST_HELLO_ID}", init = "hello --to ${USER}" }
+
+[production]
+network = { rpc-url = "${LOAM_TEST_UNSET}", network-passphrase = "Public" }
+"#,
+            )
+            .unwrap();
+        std::env::set_var("LOAM_TEST_HELLO_ID", "CFROMPROCESS");
+
+        let development = Environment::get(root.path(), "development")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            development.network.rpc_url.as_deref(),
+            Some("http://localhost:8000/rpc")
+        );
+        let hello = &development.contracts.as_ref().unwrap()["hello"];
+        // the process's environment wins over .env
+        assert_eq!(hello.id.as_deref(), Some("CFROMPROCESS"));
+        // init scripts are left for the shell
+        assert_eq!(
+            hello.init,
+            Some(Init::Script("hello --to ${USER}".to_string()))
+        );
+
+        let Err(Error::Invalid(problems)) = Environment::get(root.path(), "production") else {
+            panic!("LOAM_TEST_UNSET is not set");
+        };
+        assert_eq!(
+            problems,
+            vec![Problem::new(
+                "production.network.rpc-url",
+                "`${LOAM_TEST_UNSET}` is not set, in the environment or in .env"
+            )]
+        );
+    }
+
+    #[test]
+    fn accounts_have_one_source_of_keys() {
+        let account = |toml: &str| toml::from_str::<Account>(toml).unwrap();
+        assert_eq!(account(r#"name = "alice""#).keys(), Ok(Keys::Generated));
+        assert_eq!(
+            account(
+                r#"name = "alice"
+secret-key-env = "ALICE_SECRET""#
+            )
+            .keys(),
+            Ok(Keys::SecretKeyEnv("ALICE_SECRET"))
+        );
+        assert_eq!(
+            account(
+                r#"name = "alice"
+seed-phrase-env = "ALICE_SEED"
+public-key = "GAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAWHF""#
+            )
+            .problems("development.accounts[0]"),
+            [Problem::new(
+                "development.accounts[0]",
+                "can only have one of `secret-key-env`, `seed-phrase-env` and `public-key`"
+            )]
+        );
+        assert_eq!(
+            account(
+                r#"name = "bob"
+public-key = "GBOB""#
+            )
+            .keys(),
+            Err("`GBOB` is not a public key".to_string())
+        );
+
+        let watched = account(
+            r#"name = "dave"
+default = true
+fund = 100
+public-key = "GAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAWHF""#,
+        );
+        assert_eq!(watched.fund, Fund::Lumens(100));
+        assert_eq!(
+            watched
+                .problems("staging.accounts[1]")
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "staging.accounts[1]: is watch-only, so it can't be the default account, which signs transactions",
+                "staging.accounts[1].fund: can't be an amount for the default account, which pays for the accounts which are",
+            ]
+        );
+        assert_eq!(
+            account(
+                r#"name = "carol"
+fund = false"#
+            )
+            .fund,
+            Fund::Friendbot(false)
+        );
+        assert_eq!(account(r#"name = "carol""#).fund, Fund::Friendbot(true));
+    }
+
+    #[test]
+    fn contracts_are_signed_by_accounts_which_can_sign() {
+        let root = assert_fs::TempDir::new().unwrap();
+        root.child(ENV_FILE)
+            .write_str(
+                r#"
+[staging]
+network = "testnet"
+accounts = [
+    "deployer",
+    "alice",
+    "bob",
+    { name = "vault", public-key = "GAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAWHF" },
+]
+
+[staging.contracts]
+token = { deployer = "deployer", admin = ["alice", "bob"], init-source = "alice" }
+vault = { deployer = ["alice", "alcie", "alice"], admin = "vault", init-source = [] }
+"#,
+            )
+            .unwrap();
+
+        let staging = Environment::get(root.path(), "staging").unwrap().unwrap();
+        let token = &staging.contracts.as_ref().unwrap()["token"];
+        assert_eq!(token.deployer, Some(Signers::One("deployer".to_string())));
+        assert_eq!(
+            Signers::of(token.admin.as_ref()),
+            ["alice".to_string(), "bob".to_string()]
+        );
+        assert!(Signers::of(None).is_empty());
+        assert_eq!(
+            staging
+                .signer_problems()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "staging.contracts.vault.deployer: unknown account `alcie`, did you mean `alice`?",
+                "staging.contracts.vault.deployer: lists `alice` more than once",
+                "staging.contracts.vault.admin: `vault` is watch-only, so it can't sign",
+                "staging.contracts.vault.init-source: needs at least one account",
+            ]
+        );
+    }
+
+    #[test]
+    fn contracts_deployed_through_a_registry_have_one_owner_on_its_network() {
+        let root = assert_fs::TempDir::new().unwrap();
+        root.child(ENV_FILE)
+            .write_str(
+                r#"
+[staging]
+network = "testnet"
+accounts = ["alice", "bob"]
+registry = "registry"
+
+[staging.contracts]
+token = { deployer = "alice" }
+vault = { deployer = ["alice", "bob"], network = "futurenet" }
+
+[production]
+network = "testnet"
+accounts = ["alice", "bob"]
+
+[production.contracts]
+vault = { deployer = ["alice", "bob"] }
+"#,
+            )
+            .unwrap();
+
+        let staging = Environment::get(root.path(), "staging").unwrap().unwrap();
+        assert_eq!(staging.registry.as_deref(), Some("registry"));
+        assert_eq!(
+            staging
+                .registry_problems()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "staging.contracts.vault.deployer: contracts deployed through the registry are owned by their deployer, so there can only be one",
+                "staging.contracts.vault.network: contracts deployed through the registry are on the environment's network",
+            ]
+        );
+        let production = Environment::get(root.path(), "production")
+            .unwrap()
+            .unwrap();
+        assert!(production.registry_problems().is_empty());
     }
 }
@@ -0,0 +1,203 @@
+use cargo_metadata::Package;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Environment variables which affect the output of a build without cargo knowing about them,
+/// e.g. `STELLAR_NETWORK` is read by `stellar_asset!` at compile time.
+const TRACKED_ENV_VARS: &[&str] = &["RUSTFLAGS", "STELLAR_NETWORK"];
+
+/// Tracks the inputs of the last successful build of each contract, so that contracts whose
+/// inputs have not changed can be skipped.
+pub struct Fingerprints {
+    target_dir: PathBuf,
+    dir: PathBuf,
+}
+
+impl Fingerprints {
+    pub fn new(target_dir: &Path) -> Self {
+        Self {
+            target_dir: target_dir.to_path_buf(),
+            dir: target_dir.join("loam").join(".fingerprint"),
+        }
+    }
+
+    /// Hash everything that goes into building `package`: how it is built, the workspace's
+    /// `Cargo.lock`, and the source files of the package and its local path dependencies.
+    ///
+    /// `workspace` is used to follow path dependencies which are themselves workspace members.
+    pub fn compute(
+        &self,
+        build_key: &str,
+        package: &Package,
+        workspace: &[Package],
+        workspace_root: &Path,
+    ) -> Result<String, io::Error> {
+        let mut hasher = Sha256::new();
+        hasher.update(build_key.as_bytes());
+        for var in TRACKED_ENV_VARS {
+            hasher.update(format!(
+                "\n{var}={}",
+                std::env::var(var).unwrap_or_default()
+            ));
+        }
+        let lock_file = workspace_root.join("Cargo.lock");
+        if lock_file.exists() {
+            hasher.update(fs::read(lock_file)?);
+        }
+
+        let mut files = Vec::new();
+        let mut visited = HashSet::new();
+        self.collect_source_files(package_dir(package), workspace, &mut visited, &mut files);
+        files.sort();
+        files.dedup();
+        for file in files {
+            hasher.update(file.to_string_lossy().as_bytes());
+            hasher.update(fs::read(&file)?);
+        }
+        Ok(hex::encode(hasher.finalize()))
+    }
+
+    /// Whether `fingerprint` matches the one saved by the last successful build of `name`
+    pub fn is_fresh(&self, name: &str, fingerprint: &str) -> bool {
+        fs::read_to_string(self.dir.join(name)).is_ok_and(|saved| saved == fingerprint)
+    }
+
+    pub fn save(&self, name: &str, fingerprint: &str) -> Result<(), io::Error> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.dir.join(name), fingerprint)
+    }
+
+    fn collect_source_files(
+        &self,
+        dir: PathBuf,
+        workspace: &[Package],
+        visited: &mut HashSet<PathBuf>,
+        files: &mut Vec<PathBuf>,
+    ) {
+        if !visited.insert(dir.clone()) {
+            return;
+        }
+        // Respect `.gitignore`s, even outside of a git repository
+        files.extend(
+            ignore::WalkBuilder::new(&dir)
+                .require_git(false)
+                .filter_entry({
+                    let target_dir = self.target_dir.clone();
+                    move |entry| !entry.path().starts_with(&target_dir)
+                })
+                .build()
+                .filter_map(Result::ok)
+                .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+                .map(ignore::DirEntry::into_path),
+        );
+        let path_deps = workspace
+            .iter()
+            .find(|p| package_dir(p) == dir)
+            .map(|p| {
+                p.dependencies
+                    .iter()
+                    .filter_map(|dep| dep.path.clone())
+                    .map(cargo_metadata::camino::Utf8PathBuf::into_std_path_buf)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        for dep in path_deps {
+            self.collect_source_files(dep, workspace, visited, files);
+        }
+    }
+}
+
+fn package_dir(package: &Package) -> PathBuf {
+    package
+        .manifest_path
+        .parent()
+        .expect("manifest path has a parent")
+        .as_std_path()
+        .to_path_buf()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    /// A workspace where `contract` has a path dependency on `lib`
+    fn workspace() -> (assert_fs::TempDir, Vec<Package>) {
+        let root = assert_fs::TempDir::new().unwrap();
+        root.child("Cargo.toml")
+            .write_str("[workspace]\nmembers = [\"contract\", \"lib\"]\nresolver = \"2\"\n")
+            .unwrap();
+        root.child("contract/Cargo.toml")
+            .write_str(
+                "[package]\nname = \"contract\"\nversion = \"0.1.0\"\n\n[dependencies]\nlib = { path = \"../lib\" }\n",
+            )
+            .unwrap();
+        root.child("contract/src/lib.rs").write_str("").unwrap();
+        root.child("lib/Cargo.toml")
+            .write_str("[package]\nname = \"lib\"\nversion = \"0.1.0\"\n")
+            .unwrap();
+        root.child("lib/src/lib.rs").write_str("").unwrap();
+        root.child("Cargo.lock").write_str("version = 3\n").unwrap();
+        let packages = cargo_metadata::MetadataCommand::new()
+            .manifest_path(root.join("Cargo.toml"))
+            .no_deps()
+            .exec()
+            .unwrap()
+            .packages;
+        (root, packages)
+    }
+
+    #[test]
+    fn fingerprints_change_with_the_inputs_of_a_build() {
+        let (root, packages) = workspace();
+        // as when the workspace is the contract's own directory
+        let target_dir = root.join("contract/target");
+        let fingerprints = Fingerprints::new(&target_dir);
+        let contract = packages.iter().find(|p| p.name == "contract").unwrap();
+        let compute = |build_key: &str| {
+            fingerprints
+                .compute(build_key, contract, &packages, &root)
+                .unwrap()
+        };
+        let fingerprint = compute("cargo rustc");
+        assert_eq!(compute("cargo rustc"), fingerprint);
+
+        // files in the target directory are not inputs
+        root.child("contract/target/loam/contract.wasm")
+            .write_str("wasm")
+            .unwrap();
+        assert_eq!(compute("cargo rustc"), fingerprint);
+        // nor are ignored files
+        root.child("contract/.gitignore")
+            .write_str("*.log\n")
+            .unwrap();
+        let ignoring = compute("cargo rustc");
+        root.child("contract/build.log").write_str("log").unwrap();
+        assert_eq!(compute("cargo rustc"), ignoring);
+
+        assert_ne!(compute("cargo rustc --release"), ignoring);
+        root.child("lib/src/lib.rs")
+            .write_str("pub fn f() {}")
+            .unwrap();
+        let lib_changed = compute("cargo rustc");
+        assert_ne!(lib_changed, ignoring);
+        root.child("Cargo.lock").write_str("version = 4\n").unwrap();
+        assert_ne!(compute("cargo rustc"), lib_changed);
+    }
+
+    #[test]
+    fn fingerprints_are_fresh_once_saved() {
+        let (root, _) = workspace();
+        let fingerprints = Fingerprints::new(&root.join("target"));
+        assert!(!fingerprints.is_fresh("contract", "abc"));
+        fingerprints.save("contract", "abc").unwrap();
+        assert!(fingerprints.is_fresh("contract", "abc"));
+        assert!(!fingerprints.is_fresh("contract", "def"));
+        assert!(!fingerprints.is_fresh("lib", "abc"));
+        assert!(root.join("target/loam/.fingerprint/contract").is_file());
+    }
+}
@@ -0,0 +1,1157 @@
+use indexmap::IndexMap;
+use regex::Regex;
+use shlex::split;
+use soroban_cli::commands::NetworkRunnable;
+use soroban_cli::{commands as cli, CommandParser};
+use soroban_spec_tools::Spec;
+use std::path::Path;
+use std::process::Command;
+use stellar_xdr::curr::{ScSpecFunctionV0, ScSpecTypeDef};
+
+use super::{
+    env_toml::{self, Environment, Init, InitStep, Problem},
+    multisig,
+    state::State,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("⛔ ️Unable to parse init script: {0:?}")]
+    InitParseFailure(String),
+    #[error("⛔ ️Failed to execute subcommand: {0:?}\n{1:?}")]
+    SubCommandExecutionFailure(String, String),
+    #[error("⛔ ️init step {step} of {contract:?}: {message}")]
+    InvalidStep {
+        contract: String,
+        step: usize,
+        message: String,
+    },
+    #[error("⛔ ️reading the spec of {0:?}: {1}")]
+    Spec(String, soroban_spec_tools::Error),
+    #[error("⛔ ️init of {0:?} refers to `{{{{{1}}}}}`, but {2}")]
+    UnresolvedReference(String, String, String),
+    #[error(transparent)]
+    ContractInvoke(Box<cli::contract::invoke::Error>),
+    #[error(transparent)]
+    ContractFetch(Box<cli::contract::fetch::Error>),
+    #[error(transparent)]
+    Multisig(#[from] multisig::Error),
+    #[error(transparent)]
+    Clap(#[from] clap::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl_from_boxed!(Error {
+    ContractInvoke(cli::contract::invoke::Error),
+    ContractFetch(cli::contract::fetch::Error),
+});
+
+/// A call to make on a contract: the arguments given after `--` to `stellar contract invoke`,
+/// signed by `source` or else the default account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    pub source: Option<String>,
+    pub args: Vec<String>,
+}
+
+/// What init scripts and steps can refer to, by name: in `{{contracts.<name>.id}}` and
+/// `{{accounts.<name>.address}}`, or as the value of an argument which is an address
+pub struct References<'a> {
+    /// Addresses of the environment's accounts
+    pub accounts: &'a IndexMap<String, String>,
+    /// Ids of the environment's contracts which are deployed so far
+    pub contracts: &'a IndexMap<String, String>,
+    /// Accounts which are watch-only, so that their addresses are given rather than their names
+    pub watch_only: &'a [String],
+}
+
+/// A `{{...}}` placeholder
+fn placeholder() -> Regex {
+    Regex::new(r"\{\{\s*([^}]*?)\s*\}\}").expect("valid regex")
+}
+
+impl References<'_> {
+    /// Replace the placeholders of `value`
+    pub fn resolve(&self, contract: &str, value: &str) -> Result<String, Error> {
+        let mut error = None;
+        let resolved = placeholder().replace_all(value, |captures: &regex::Captures| {
+            self.lookup(&captures[1]).unwrap_or_else(|reason| {
+                error.get_or_insert_with(|| {
+                    Error::UnresolvedReference(
+                        contract.to_string(),
+                        captures[1].to_string(),
+                        reason,
+                    )
+                });
+                String::new()
+            })
+        });
+        match error {
+            Some(error) => Err(error),
+            None => Ok(resolved.into_owned()),
+        }
+    }
+
+    /// Names and ids of the contracts `init` refers to, which are part of the hashes of the init
+    /// and its calls, so that it runs again once one of them is deployed anew
+    pub fn ids_referenced_by(&self, init: &Init) -> Vec<(String, String)> {
+        let names = self
+            .contracts
+            .keys()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        referenced_ids(init, &names, self.contracts).expect("the contracts of `names` all have ids")
+    }
+
+    /// Value of a placeholder, or why it has none
+    fn lookup(&self, reference: &str) -> Result<String, String> {
+        match reference.split('.').collect::<Vec<_>>()[..] {
+            ["contracts", name, "id"] => self.contracts.get(name).cloned().ok_or_else(|| {
+                format!("{name:?} isn't deployed yet, or isn't a contract of the environment")
+            }),
+            ["accounts", name, "address"] => self
+                .accounts
+                .get(name)
+                .cloned()
+                .ok_or_else(|| format!("{name:?} isn't an account of the environment")),
+            _ => Err(
+                "placeholders are either `{{contracts.<name>.id}}` or `{{accounts.<name>.address}}`"
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+/// Names of the other contracts an init refers to, whose ids it needs: in `{{contracts.<name>.id}}`,
+/// or as the value of a step's argument
+pub fn dependencies(init: &Init, contracts: &[&str]) -> Vec<String> {
+    fn strings<'a>(value: &'a toml::Value, found: &mut Vec<&'a str>) {
+        match value {
+            toml::Value::String(string) => found.push(string),
+            toml::Value::Array(values) => values.iter().for_each(|value| strings(value, found)),
+            toml::Value::Table(table) => table.values().for_each(|value| strings(value, found)),
+            _ => {}
+        }
+    }
+    let mut values = Vec::new();
+    match init {
+        Init::Script(script) => values.push(script.as_str()),
+        Init::Steps(steps) => {
+            for step in steps {
+                step.args
+                    .values()
+                    .for_each(|value| strings(value, &mut values));
+            }
+        }
+    }
+    let mut names = Vec::new();
+    for value in values {
+        if matches!(init, Init::Steps(_)) && contracts.contains(&value) {
+            names.push(value.to_string());
+        }
+        for captures in placeholder().captures_iter(value) {
+            if let ["contracts", name, "id"] = captures[1].split('.').collect::<Vec<_>>()[..] {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Names and ids of the other contracts of `contracts` an init refers to, or `None` if one of
+/// them has no id in `ids`, such as one which is yet to be deployed
+pub fn referenced_ids(
+    init: &Init,
+    contracts: &[&str],
+    ids: &IndexMap<String, String>,
+) -> Option<Vec<(String, String)>> {
+    dependencies(init, contracts)
+        .into_iter()
+        .map(|name| {
+            let id = ids.get(&name)?.clone();
+            Some((name, id))
+        })
+        .collect()
+}
+
+/// Check the inits of an environment against the specs of the wasms built for their contracts,
+/// without using the network. Scripts' lines are checked as they are written, so arguments made
+/// by `$(...)` or `{{...}}` aren't type checked. `identities` are the names the stellar CLI can
+/// sign with, which scripts can also use as addresses.
+pub fn check_environment(
+    workspace_root: &Path,
+    environment: &Environment,
+    identities: &[String],
+) -> Vec<Problem> {
+    let accounts = environment
+        .accounts
+        .iter()
+        .flatten()
+        .map(|account| account.name.as_str())
+        .collect::<Vec<_>>();
+    let contracts = environment
+        .contracts
+        .iter()
+        .flat_map(|contracts| contracts.keys())
+        .map(AsRef::as_ref)
+        .collect::<Vec<_>>();
+    let watch_only = environment
+        .accounts
+        .iter()
+        .flatten()
+        .filter(|account| account.public_key.is_some())
+        .map(|account| account.name.as_str())
+        .collect::<Vec<_>>();
+    let names = Names {
+        accounts: &accounts,
+        contracts: &contracts,
+        identities: &identities.iter().map(String::as_str).collect::<Vec<_>>(),
+        watch_only: &watch_only,
+    };
+    let mut problems = Vec::new();
+    for (name, contract) in environment.contracts.iter().flatten() {
+        let Some(init) = &contract.init else {
+            continue;
+        };
+        let path = format!("{}.contracts.{name}.init", environment.name);
+        let wasm_path = workspace_root.join(format!("target/loam/{name}.wasm"));
+        let Ok(wasm) = std::fs::read(&wasm_path) else {
+            continue;
+        };
+        match Spec::from_wasm(&wasm) {
+            Ok(spec) => problems.extend(check(&path, init, &spec, &names)),
+            Err(e) => problems.push(Problem::new(
+                path,
+                format!("can't read the spec of {}: {e}", wasm_path.display()),
+            )),
+        }
+    }
+    problems
+}
+
+/// Names which inits can use without them being resolved
+pub struct Names<'a> {
+    /// The environment's accounts
+    pub accounts: &'a [&'a str],
+    /// The environment's contracts
+    pub contracts: &'a [&'a str],
+    /// Identities of the stellar CLI
+    pub identities: &'a [&'a str],
+    /// The environment's accounts which are watch-only, so they can't sign
+    pub watch_only: &'a [&'a str],
+}
+
+/// Problems with the calls of an init, found by checking each line of a script, or each step,
+/// against the contract's spec
+pub fn check(path: &str, init: &Init, spec: &Spec, names: &Names<'_>) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let accounts = names
+        .accounts
+        .iter()
+        .copied()
+        .filter(|account| !names.watch_only.contains(account))
+        .collect::<Vec<_>>();
+    match init {
+        Init::Script(script) => {
+            // scripts are run by the stellar CLI, which takes the names of its identities as
+            // addresses
+            let signers = [&accounts[..], names.identities].concat();
+            let commands = Regex::new(r"\$\((.*?)\)").expect("valid regex");
+            for (i, line) in script.lines().enumerate() {
+                let line = commands.replace_all(line.trim(), "$$(...)");
+                if line.is_empty() {
+                    continue;
+                }
+                let messages = match script_call(&line) {
+                    Ok((source, call)) => {
+                        let mut messages = check_call(spec, &call, &signers, names);
+                        if let Some(source) = source {
+                            messages.extend(check_signer(&source, &signers, names));
+                        }
+                        messages
+                    }
+                    Err(message) => vec![message],
+                };
+                problems.extend(
+                    messages
+                        .into_iter()
+                        .map(|message| Problem::new(path, format!("line {}: {message}", i + 1))),
+                );
+            }
+        }
+        Init::Steps(steps) => {
+            // steps can also use the names of contracts as addresses, which are replaced with
+            // their ids
+            let addresses = [names.accounts, names.contracts].concat();
+            let no_references = References {
+                accounts: &IndexMap::new(),
+                contracts: &IndexMap::new(),
+                watch_only: &[],
+            };
+            for (i, step) in steps.iter().enumerate() {
+                let call = Call {
+                    function: step.function.clone(),
+                    args: step
+                        .args
+                        .iter()
+                        .map(|(name, value)| {
+                            let value = argument(value, &ScSpecTypeDef::Val, &no_references);
+                            (name.clone(), Some(value))
+                        })
+                        .collect(),
+                };
+                let mut messages = check_call(spec, &call, &addresses, names);
+                if let Some(source) = &step.source {
+                    messages.extend(check_signer(source, &accounts, names));
+                }
+                problems.extend(
+                    messages
+                        .into_iter()
+                        .map(|message| Problem::new(format!("{path}[{i}]"), message)),
+                );
+            }
+        }
+    }
+    problems
+}
+
+/// A call as it is written, before anything in it is resolved: arguments are given by name, and
+/// flags have no value
+#[derive(Debug, PartialEq, Eq)]
+struct Call {
+    function: String,
+    args: Vec<(String, Option<String>)>,
+}
+
+/// The signer and call of a line of a script, from `--name value`, `--name=value` and `--flag`
+/// arguments
+fn script_call(line: &str) -> Result<(Option<String>, Call), String> {
+    let parts = split(line).ok_or_else(|| "can't be split into arguments".to_string())?;
+    let (source, parts): (Vec<_>, Vec<_>) = parts
+        .into_iter()
+        .partition(|part| part.starts_with("STELLAR_ACCOUNT="));
+    let source = source
+        .first()
+        .and_then(|source| source.strip_prefix("STELLAR_ACCOUNT="))
+        .map(ToString::to_string);
+    let mut parts = parts.into_iter().peekable();
+    let function = parts
+        .next()
+        .ok_or_else(|| "has no function to call".to_string())?;
+    let mut args = Vec::new();
+    while let Some(part) = parts.next() {
+        let Some(arg) = part.strip_prefix("--") else {
+            return Err(format!(
+                "expected an argument such as `--name value`, not `{part}`"
+            ));
+        };
+        args.push(match arg.split_once('=') {
+            Some((name, value)) => (name.to_string(), Some(value.to_string())),
+            None => (
+                arg.to_string(),
+                parts.next_if(|value| !value.starts_with("--")),
+            ),
+        });
+    }
+    Ok((source, Call { function, args }))
+}
+
+fn check_signer(source: &str, signers: &[&str], names: &Names<'_>) -> Option<String> {
+    if names.watch_only.contains(&source) {
+        Some(format!("`{source}` is watch-only, so it can't sign"))
+    } else {
+        (!signers.contains(&source)).then(|| env_toml::unknown("account", source, signers))
+    }
+}
+
+/// Problems with a call, checked against the function it calls. Arguments which are addresses
+/// can be any of `addresses`.
+fn check_call(spec: &Spec, call: &Call, addresses: &[&str], names: &Names<'_>) -> Vec<String> {
+    let function = match find_function(spec, &call.function) {
+        Ok(function) => function,
+        Err(message) => return vec![message],
+    };
+    let inputs = function
+        .inputs
+        .iter()
+        .map(|input| (input.name.to_utf8_string_lossy(), &input.type_))
+        .collect::<Vec<_>>();
+    let input_names = inputs
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>();
+
+    let mut messages = Vec::new();
+    for (name, value) in &call.args {
+        let (name, from_file) = match name.strip_suffix("-file-path") {
+            Some(name) if input_names.contains(&name) => (name, true),
+            _ => (name.as_str(), false),
+        };
+        let Some((_, type_)) = inputs.iter().find(|(input, _)| input == name) else {
+            messages.push(env_toml::unknown("argument", name, &input_names));
+            continue;
+        };
+        let Some(value) = value else {
+            if inner_type(type_) != &ScSpecTypeDef::Bool {
+                messages.push(format!("argument `{name}` needs a value"));
+            }
+            continue;
+        };
+        if value.contains("{{") {
+            messages.extend(check_placeholders(value, names));
+        } else if !(from_file
+            || value.contains("$(")
+            || (inner_type(type_) == &ScSpecTypeDef::Address
+                && addresses.contains(&value.as_str()))
+            || spec.from_string(value, type_).is_ok())
+        {
+            messages.push(format!(
+                "argument `{name}` is not a valid {type_:?}: `{value}`"
+            ));
+        }
+    }
+    for (name, type_) in &inputs {
+        let given = call
+            .args
+            .iter()
+            .any(|(arg, _)| arg == name || arg.strip_suffix("-file-path") == Some(name.as_str()));
+        if !given && !matches!(type_, ScSpecTypeDef::Option(_) | ScSpecTypeDef::Bool) {
+            messages.push(format!("missing argument `{name}`"));
+        }
+    }
+    messages
+}
+
+/// Problems with the placeholders of a value, which must refer to the environment's contracts
+/// and accounts
+fn check_placeholders(value: &str, names: &Names<'_>) -> Vec<String> {
+    placeholder()
+        .captures_iter(value)
+        .filter_map(|captures| {
+            let reference = &captures[1];
+            let message = match reference.split('.').collect::<Vec<_>>()[..] {
+                ["contracts", name, "id"] if !names.contracts.contains(&name) => {
+                    env_toml::unknown("contract", name, names.contracts)
+                }
+                ["accounts", name, "address"] if !names.accounts.contains(&name) => {
+                    env_toml::unknown("account", name, names.accounts)
+                }
+                ["contracts", _, "id"] | ["accounts", _, "address"] => return None,
+                _ => "placeholders are either `{{contracts.<name>.id}}` or `{{accounts.<name>.address}}`"
+                    .to_string(),
+            };
+            Some(format!("`{{{{{reference}}}}}`: {message}"))
+        })
+        .collect()
+}
+
+/// Spec of a contract, from the wasm built for it, or else from the network
+pub async fn spec(workspace_root: &Path, name: &str, contract_id: &str) -> Result<Spec, Error> {
+    let wasm_path = workspace_root.join(format!("target/loam/{name}.wasm"));
+    let wasm = if wasm_path.exists() {
+        std::fs::read(wasm_path)?
+    } else {
+        cli::contract::fetch::Cmd::parse_arg_vec(&["--id", contract_id])?
+            .run_against_rpc_server(None, None)
+            .await?
+    };
+    Spec::from_wasm(&wasm).map_err(|e| Error::Spec(name.to_string(), e))
+}
+
+/// The calls of an init as they are written: where each is, and its text
+pub fn calls(init: &Init) -> Vec<(String, String)> {
+    match init {
+        Init::Script(script) => script
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| (format!("line {}", i + 1), line.trim().to_string()))
+            .collect(),
+        Init::Steps(steps) => steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| {
+                let text = serde_json::to_string(step).expect("steps can be serialized");
+                (format!("init[{i}] ({})", step.function), text)
+            })
+            .collect(),
+    }
+}
+
+/// Run a contract's init, making one call per line of a script, or per step, and report which
+/// succeeded. `completed` has the hashes of the calls which already succeeded against the
+/// contract, from an earlier run which failed: the calls at the start of the init which are
+/// unchanged since are skipped. The hashes of the calls which succeed are added to it. Calls
+/// which don't name their own source are signed by `signers`, or else the default account.
+pub async fn run(
+    workspace_root: &Path,
+    name: &str,
+    contract_id: &str,
+    init: &Init,
+    signers: &[String],
+    references: &References<'_>,
+    completed: &mut Vec<String>,
+) -> Result<(), Error> {
+    let calls = calls(init);
+    let ids = references.ids_referenced_by(init);
+    let done = already_done(&calls, &ids, completed);
+    completed.truncate(done);
+
+    // every call is resolved, and checked, before any is made
+    let mut invocations = Vec::new();
+    match init {
+        Init::Script(_) => {
+            for (_, line) in &calls[done..] {
+                invocations.push(script_invocation(&references.resolve(name, line)?)?);
+            }
+        }
+        Init::Steps(steps) if done < steps.len() => {
+            let spec = spec(workspace_root, name, contract_id).await?;
+            for (i, step) in steps.iter().enumerate().skip(done) {
+                invocations.push(step_invocation(name, i + 1, step, &spec, references)?);
+            }
+        }
+        Init::Steps(_) => {}
+    }
+
+    let mut report = calls[..done]
+        .iter()
+        .map(|(label, _)| format!("    ⏭️ already done: {label}"))
+        .collect::<Vec<_>>();
+    let result = invoke_all(
+        contract_id,
+        signers,
+        &calls[done..],
+        &ids,
+        &invocations,
+        completed,
+        &mut report,
+    )
+    .await;
+    for (label, _) in &calls[report.len()..] {
+        report.push(format!("    ⏸️ not run: {label}"));
+    }
+
+    eprintln!("📋 init of {name:?}:");
+    for line in report {
+        eprintln!("{line}");
+    }
+    result?;
+    eprintln!("✅ Initialization script for {name:?} completed successfully");
+    Ok(())
+}
+
+/// How many calls at the start of an init already succeeded, which are those unchanged since
+/// their hashes were recorded, as are the `referenced` contracts
+fn already_done(
+    calls: &[(String, String)],
+    referenced: &[(String, String)],
+    completed: &[String],
+) -> usize {
+    calls
+        .iter()
+        .zip(completed)
+        .take_while(|((_, text), hash)| State::hash_call(text, referenced) == **hash)
+        .count()
+}
+
+/// Make calls in order, until one fails, adding the hashes of those which succeed to `completed`
+async fn invoke_all(
+    contract_id: &str,
+    signers: &[String],
+    calls: &[(String, String)],
+    referenced: &[(String, String)],
+    invocations: &[Invocation],
+    completed: &mut Vec<String>,
+    report: &mut Vec<String>,
+) -> Result<(), Error> {
+    for ((label, text), invocation) in calls.iter().zip(invocations) {
+        if let Err(e) = invoke(contract_id, signers, invocation).await {
+            report.push(format!("    ❌ failed: {label}"));
+            return Err(e);
+        }
+        completed.push(State::hash_call(text, referenced));
+        report.push(format!("    ✅ done: {label}"));
+    }
+    Ok(())
+}
+
+async fn invoke(
+    contract_id: &str,
+    signers: &[String],
+    invocation: &Invocation,
+) -> Result<(), Error> {
+    let signers = invocation
+        .source
+        .as_ref()
+        .map_or(signers, std::slice::from_ref);
+    let mut args = vec!["--id", contract_id, "--"];
+    args.extend(invocation.args.iter().map(String::as_str));
+
+    let signed_by = match signers {
+        [] => String::new(),
+        [signer] => format!(" (signed by {signer})"),
+        _ => format!(" (signed by each of {})", signers.join(", ")),
+    };
+    eprintln!(
+        "  ↳ Executing: stellar contract invoke {}{signed_by}",
+        args.join(" ")
+    );
+    let result = multisig::run::<cli::contract::invoke::Cmd, _, Error>(&args, signers).await?;
+    eprintln!("  ↳ Result: {result:?}");
+    Ok(())
+}
+
+/// The call of a line of a script, after running the commands of its `$(...)`s. A line can
+/// start with `STELLAR_ACCOUNT=<name>` to be signed by another account.
+fn script_invocation(line: &str) -> Result<Invocation, Error> {
+    let re = Regex::new(r"\$\((.*?)\)").expect("Invalid regex pattern");
+
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+
+    // resolve any $() patterns
+    let resolved_line = resolve_line(&re, line.trim(), shell, flag)?;
+    let parts =
+        split(&resolved_line).ok_or_else(|| Error::InitParseFailure(resolved_line.clone()))?;
+    let (source_account, args): (Vec<_>, Vec<_>) = parts
+        .into_iter()
+        .partition(|part| part.starts_with("STELLAR_ACCOUNT="));
+    let source = source_account
+        .first()
+        .and_then(|account| account.strip_prefix("STELLAR_ACCOUNT="))
+        .map(ToString::to_string);
+    Ok(Invocation { source, args })
+}
+
+fn resolve_line(re: &Regex, line: &str, shell: &str, flag: &str) -> Result<String, Error> {
+    let mut result = String::new();
+    let mut last_match = 0;
+    for cap in re.captures_iter(line) {
+        let whole_match = cap.get(0).unwrap();
+        result.push_str(&line[last_match..whole_match.start()]);
+        let cmd = &cap[1];
+        let output = execute_subcommand(shell, flag, cmd)?;
+        result.push_str(&output);
+        last_match = whole_match.end();
+    }
+    result.push_str(&line[last_match..]);
+    Ok(result)
+}
+
+fn execute_subcommand(shell: &str, flag: &str, cmd: &str) -> Result<String, Error> {
+    match Command::new(shell).arg(flag).arg(cmd).output() {
+        Ok(output) => {
+            let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+
+            if output.status.success() {
+                Ok(stdout)
+            } else {
+                Err(Error::SubCommandExecutionFailure(cmd.to_string(), stderr))
+            }
+        }
+        Err(e) => Err(Error::SubCommandExecutionFailure(
+            cmd.to_string(),
+            e.to_string(),
+        )),
+    }
+}
+
+/// The type of an argument, looking through `Option`
+fn inner_type(type_: &ScSpecTypeDef) -> &ScSpecTypeDef {
+    match type_ {
+        ScSpecTypeDef::Option(option) => &option.value_type,
+        type_ => type_,
+    }
+}
+
+/// Check a step against the function it calls, and turn it into the arguments of `stellar
+/// contract invoke`. Addresses given as the name of another contract are replaced with its id.
+/// Those given as the name of an account are left for the stellar CLI, which signs for them.
+fn step_invocation(
+    contract: &str,
+    step_number: usize,
+    step: &InitStep,
+    spec: &Spec,
+    references: &References<'_>,
+) -> Result<Invocation, Error> {
+    let invalid = |message: String| Error::InvalidStep {
+        contract: contract.to_string(),
+        step: step_number,
+        message,
+    };
+    if let Some(source) = &step.source {
+        if references.watch_only.contains(source) {
+            return Err(invalid(format!(
+                "`{source}` is watch-only, so it can't sign"
+            )));
+        }
+    }
+    let function = find_function(spec, &step.function).map_err(invalid)?;
+    let inputs = function
+        .inputs
+        .iter()
+        .map(|input| (input.name.to_utf8_string_lossy(), &input.type_))
+        .collect::<Vec<_>>();
+    let input_names = inputs
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>();
+
+    let mut args = vec![step.function.clone()];
+    for (name, value) in &step.args {
+        let Some((_, type_)) = inputs.iter().find(|(input, _)| input == name) else {
+            return Err(invalid(env_toml::unknown("argument", name, &input_names)));
+        };
+        let value =
+            references.resolve(contract, &argument(value, inner_type(type_), references))?;
+        let is_account = references.accounts.contains_key(&value);
+        if !(is_account && inner_type(type_) == &ScSpecTypeDef::Address) {
+            spec.from_string(&value, type_)
+                .map_err(|e| invalid(format!("argument `{name}` is not a valid {type_:?}: {e}")))?;
+        }
+        args.extend([format!("--{name}"), value]);
+    }
+    for (name, type_) in &inputs {
+        // like soroban-cli, a missing bool is false
+        if !step.args.contains_key(name)
+            && !matches!(type_, ScSpecTypeDef::Option(_) | ScSpecTypeDef::Bool)
+        {
+            return Err(invalid(format!("missing argument `{name}`")));
+        }
+    }
+    Ok(Invocation {
+        source: step.source.clone(),
+        args,
+    })
+}
+
+fn find_function<'a>(spec: &'a Spec, name: &str) -> Result<&'a ScSpecFunctionV0, String> {
+    spec.find_function(name).map_err(|_| {
+        let functions = spec
+            .find_functions()
+            .map(|functions| {
+                functions
+                    .map(|function| function.name.to_utf8_string_lossy())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let functions = functions.iter().map(String::as_str).collect::<Vec<_>>();
+        env_toml::unknown("function", name, &functions)
+    })
+}
+
+/// An argument as the stellar CLI takes it. Tables and arrays are given as JSON.
+fn argument(value: &toml::Value, type_: &ScSpecTypeDef, references: &References<'_>) -> String {
+    match value {
+        toml::Value::String(name) if type_ == &ScSpecTypeDef::Address => references
+            .contracts
+            .get(name)
+            .or_else(|| {
+                references
+                    .watch_only
+                    .contains(name)
+                    .then(|| references.accounts.get(name))
+                    .flatten()
+            })
+            .cloned()
+            .unwrap_or_else(|| name.clone()),
+        toml::Value::String(string) => string.clone(),
+        toml::Value::Integer(integer) => integer.to_string(),
+        toml::Value::Float(float) => float.to_string(),
+        toml::Value::Boolean(boolean) => boolean.to_string(),
+        toml::Value::Datetime(datetime) => datetime.to_string(),
+        toml::Value::Array(_) | toml::Value::Table(_) => {
+            serde_json::to_string(value).expect("toml values are valid json")
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use stellar_xdr::curr::{ScSpecEntry, ScSpecFunctionInputV0, ScSpecTypeOption};
+
+    const TOKEN: &str = "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAD2KM";
+    const ALICE: &str = "GAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAWHF";
+    const DAVE: &str = "GAAQCAIBAEAQCAIBAEAQCAIBAEAQCAIBAEAQCAIBAEAQCAIBAEAQDZ7H";
+
+    fn function(name: &str, inputs: &[(&str, ScSpecTypeDef)]) -> ScSpecEntry {
+        ScSpecEntry::FunctionV0(ScSpecFunctionV0 {
+            doc: "".try_into().unwrap(),
+            name: name.try_into().unwrap(),
+            inputs: inputs
+                .iter()
+                .map(|(name, type_)| ScSpecFunctionInputV0 {
+                    doc: "".try_into().unwrap(),
+                    name: (*name).try_into().unwrap(),
+                    type_: type_.clone(),
+                })
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap(),
+            outputs: vec![].try_into().unwrap(),
+        })
+    }
+
+    /// A vault with `init(token: Address, admin: Address, limit: Option<i128>, paused: bool)` and
+    /// `deposit(from: Address, amount: i128)`
+    fn vault() -> Spec {
+        let limit = ScSpecTypeDef::Option(Box::new(ScSpecTypeOption {
+            value_type: Box::new(ScSpecTypeDef::I128),
+        }));
+        Spec::new(vec![
+            function(
+                "init",
+                &[
+                    ("token", ScSpecTypeDef::Address),
+                    ("admin", ScSpecTypeDef::Address),
+                    ("limit", limit),
+                    ("paused", ScSpecTypeDef::Bool),
+                ],
+            ),
+            function(
+                "deposit",
+                &[
+                    ("from", ScSpecTypeDef::Address),
+                    ("amount", ScSpecTypeDef::I128),
+                ],
+            ),
+        ])
+    }
+
+    fn step(toml: &str) -> InitStep {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn invocation(step: &InitStep) -> Result<Invocation, Error> {
+        let accounts = IndexMap::from([
+            ("alice".to_string(), ALICE.to_string()),
+            ("dave".to_string(), DAVE.to_string()),
+        ]);
+        let contracts = IndexMap::from([("token".to_string(), TOKEN.to_string())]);
+        let references = References {
+            accounts: &accounts,
+            contracts: &contracts,
+            watch_only: &["dave".to_string()],
+        };
+        step_invocation("vault", 1, step, &vault(), &references)
+    }
+
+    fn message(result: Result<Invocation, Error>) -> String {
+        match result {
+            Err(Error::InvalidStep { message, .. }) => message,
+            result => panic!("expected an invalid step, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn steps_refer_to_contracts_and_accounts_by_name() {
+        let init = step(
+            r#"
+fn = "init"
+args = { token = "token", admin = "alice" }
+source = "alice"
+"#,
+        );
+        assert_eq!(
+            invocation(&init).unwrap(),
+            Invocation {
+                source: Some("alice".to_string()),
+                args: ["init", "--token", TOKEN, "--admin", "alice"]
+                    .map(ToString::to_string)
+                    .to_vec(),
+            }
+        );
+        let deposit = step(
+            r#"fn = "deposit"
+args = { from = "alice", amount = 2000000 }"#,
+        );
+        assert_eq!(
+            invocation(&deposit).unwrap().args,
+            ["deposit", "--from", "alice", "--amount", "2000000"]
+        );
+    }
+
+    #[test]
+    fn bool_arguments_can_be_omitted() {
+        let paused = step(
+            r#"fn = "init"
+args = { token = "token", admin = "alice", paused = true }"#,
+        );
+        assert_eq!(
+            invocation(&paused).unwrap().args,
+            ["init", "--token", TOKEN, "--admin", "alice", "--paused", "true"]
+        );
+        // soroban-cli passes `false` for a bool which isn't given
+        let unpaused = step(
+            r#"fn = "init"
+args = { token = "token", admin = "alice" }"#,
+        );
+        assert_eq!(
+            invocation(&unpaused).unwrap().args,
+            ["init", "--token", TOKEN, "--admin", "alice"]
+        );
+    }
+
+    #[test]
+    fn watch_only_accounts_are_given_by_address_and_cannot_sign() {
+        let init = step(
+            r#"fn = "init"
+args = { token = "token", admin = "dave" }"#,
+        );
+        assert_eq!(
+            invocation(&init).unwrap().args,
+            ["init", "--token", TOKEN, "--admin", DAVE]
+        );
+        let signed = step(
+            r#"fn = "deposit"
+args = { from = "dave", amount = 1 }
+source = "dave""#,
+        );
+        assert_eq!(
+            message(invocation(&signed)),
+            "`dave` is watch-only, so it can't sign"
+        );
+    }
+
+    #[test]
+    fn steps_are_checked_against_the_spec() {
+        assert_eq!(
+            message(invocation(&step(r#"fn = "depost""#))),
+            "unknown function `depost`, did you mean `deposit`?"
+        );
+        assert_eq!(
+            message(invocation(&step(
+                r#"fn = "deposit"
+args = { form = "alice", amount = 1 }"#
+            ))),
+            "unknown argument `form`, did you mean `from`?"
+        );
+        assert_eq!(
+            message(invocation(&step(
+                r#"fn = "deposit"
+args = { from = "alice" }"#
+            ))),
+            "missing argument `amount`"
+        );
+        assert!(message(invocation(&step(
+            r#"fn = "deposit"
+args = { from = "alice", amount = "lots" }"#
+        )))
+        .starts_with("argument `amount` is not a valid I128"));
+        assert!(message(invocation(&step(
+            r#"fn = "init"
+args = { token = "nobody", admin = "alice" }"#
+        )))
+        .starts_with("argument `token` is not a valid Address"));
+    }
+
+    #[test]
+    fn script_lines_can_be_signed_by_another_account() {
+        let script = "init --token token\n\n  STELLAR_ACCOUNT=bob deposit --amount 1 \n";
+        let invocations = calls(&Init::Script(script.to_string()))
+            .iter()
+            .map(|(_, line)| script_invocation(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            invocations,
+            vec![
+                Invocation {
+                    source: None,
+                    args: ["init", "--token", "token"]
+                        .map(ToString::to_string)
+                        .to_vec(),
+                },
+                Invocation {
+                    source: Some("bob".to_string()),
+                    args: ["deposit", "--amount", "1"]
+                        .map(ToString::to_string)
+                        .to_vec(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_inits_resume_after_the_calls_which_are_unchanged() {
+        let script = |script: &str| calls(&Init::Script(script.to_string()));
+        let failed = script("init --admin alice\n\nmint --to bob --amount 1\nmint --to alice");
+        assert_eq!(
+            failed
+                .iter()
+                .map(|(label, _)| label.as_str())
+                .collect::<Vec<_>>(),
+            ["line 1", "line 3", "line 4"]
+        );
+        let completed = failed[..2]
+            .iter()
+            .map(|(_, text)| State::hash_call(text, &[]))
+            .collect::<Vec<_>>();
+        assert_eq!(already_done(&failed, &[], &completed), 2);
+
+        let fixed =
+            script("init --admin alice\nmint --to bob --amount 1\nmint --to alice --amount 1");
+        assert_eq!(already_done(&fixed, &[], &completed), 2);
+        let edited = script("init --admin bob\nmint --to bob --amount 1\nmint --to alice");
+        assert_eq!(already_done(&edited, &[], &completed), 0);
+        // a contract the init refers to was deployed anew since
+        let token = [("token".to_string(), TOKEN.to_string())];
+        assert_eq!(already_done(&failed, &token, &completed), 0);
+    }
+
+    #[test]
+    fn placeholders_are_replaced() {
+        let accounts = IndexMap::from([("alice".to_string(), ALICE.to_string())]);
+        let contracts = IndexMap::from([("token".to_string(), TOKEN.to_string())]);
+        let references = References {
+            accounts: &accounts,
+            contracts: &contracts,
+            watch_only: &[],
+        };
+        assert_eq!(
+            references
+                .resolve(
+                    "vault",
+                    "init --token {{contracts.token.id}} --admin {{ accounts.alice.address }}"
+                )
+                .unwrap(),
+            format!("init --token {TOKEN} --admin {ALICE}")
+        );
+        for (reference, reason) in [
+            (
+                "{{contracts.oracle.id}}",
+                "\"oracle\" isn't deployed yet, or isn't a contract of the environment",
+            ),
+            (
+                "{{accounts.bob.address}}",
+                "\"bob\" isn't an account of the environment",
+            ),
+            (
+                "{{contract.token.id}}",
+                "placeholders are either `{{contracts.<name>.id}}` or `{{accounts.<name>.address}}`",
+            ),
+        ] {
+            let Err(Error::UnresolvedReference(_, _, message)) =
+                references.resolve("vault", reference)
+            else {
+                panic!("{reference} can't be resolved");
+            };
+            assert_eq!(message, reason);
+        }
+
+        let deposit = step(
+            r#"fn = "init"
+args = { token = "{{contracts.token.id}}", admin = "{{accounts.alice.address}}" }"#,
+        );
+        assert_eq!(
+            invocation(&deposit).unwrap().args,
+            ["init", "--token", TOKEN, "--admin", ALICE]
+        );
+    }
+
+    fn problems(init: &Init) -> Vec<String> {
+        let names = Names {
+            accounts: &["alice", "dave"],
+            contracts: &["token", "vault"],
+            identities: &["bob"],
+            watch_only: &["dave"],
+        };
+        check("development.contracts.vault.init", init, &vault(), &names)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn scripts_are_checked_line_by_line() {
+        let script = Init::Script(
+            r#"init --token {{contracts.token.id}} --admin alice --limit=5
+STELLAR_ACCOUNT=bob deposit --from bob --amount "$(echo 1)"
+
+depost --from alice
+deposit --form alice --amount lots
+STELLAR_ACCOUNT=carol deposit --from {{accounts.carol.address}} --amount
+init --token {{contracts.oracle.id}} alice"#
+                .to_string(),
+        );
+        assert_eq!(
+            problems(&script),
+            [
+                "development.contracts.vault.init: line 4: unknown function `depost`, did you mean `deposit`?",
+                "development.contracts.vault.init: line 5: unknown argument `form`, did you mean `from`?",
+                "development.contracts.vault.init: line 5: argument `amount` is not a valid I128: `lots`",
+                "development.contracts.vault.init: line 5: missing argument `from`",
+                "development.contracts.vault.init: line 6: `{{accounts.carol.address}}`: unknown account `carol`, expected one of `alice`, `dave`",
+                "development.contracts.vault.init: line 6: argument `amount` needs a value",
+                "development.contracts.vault.init: line 6: unknown account `carol`, expected one of `alice`, `bob`",
+                "development.contracts.vault.init: line 7: expected an argument such as `--name value`, not `alice`",
+            ]
+        );
+    }
+
+    #[test]
+    fn steps_are_checked_without_the_network() {
+        let steps = Init::Steps(vec![
+            step(
+                r#"fn = "init"
+args = { token = "token", admin = "{{accounts.alice.address}}" }
+source = "alice""#,
+            ),
+            step(
+                r#"fn = "deposit"
+args = { from = "bob", amount = 1 }
+source = "bob""#,
+            ),
+            step(
+                r#"fn = "deposit"
+args = { from = "dave", amount = 1 }
+source = "dave""#,
+            ),
+        ]);
+        assert_eq!(
+            problems(&steps),
+            [
+                "development.contracts.vault.init[1]: argument `from` is not a valid Address: `bob`",
+                "development.contracts.vault.init[1]: unknown account `bob`, expected one of `alice`",
+                "development.contracts.vault.init[2]: `dave` is watch-only, so it can't sign",
+            ]
+        );
+    }
+
+    #[test]
+    fn dependencies_are_the_contracts_referred_to() {
+        let contracts = ["token", "oracle", "vault"];
+        let script = Init::Script(
+            "init --token {{contracts.token.id}} --oracle {{contracts.oracle.id}}\nset --admin {{accounts.alice.address}}"
+                .to_string(),
+        );
+        assert_eq!(dependencies(&script, &contracts), ["oracle", "token"]);
+        let steps = Init::Steps(vec![step(
+            r#"fn = "init"
+args = { token = "token", admin = "alice", assets = [{ Stellar = "{{contracts.oracle.id}}" }] }"#,
+        )]);
+        assert_eq!(dependencies(&steps, &contracts), ["oracle", "token"]);
+
+        let ids = IndexMap::from([("token".to_string(), TOKEN.to_string())]);
+        assert_eq!(referenced_ids(&steps, &contracts, &ids), None);
+        let ids = IndexMap::from([
+            ("token".to_string(), TOKEN.to_string()),
+            ("oracle".to_string(), "C2".to_string()),
+        ]);
+        assert_eq!(
+            referenced_ids(&steps, &contracts, &ids).unwrap(),
+            [
+                ("oracle".to_string(), "C2".to_string()),
+                ("token".to_string(), TOKEN.to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("reading manifest {0:?}: {1}")]
+    Reading(PathBuf, io::Error),
+    #[error("writing manifest {0:?}: {1}")]
+    Writing(PathBuf, io::Error),
+    #[error("parsing manifest {0:?}: {1}")]
+    Parsing(PathBuf, serde_json::Error),
+}
+
+/// Record of how the contracts of a workspace were built, written by `loam build --locked-manifest`
+/// to `target/loam/manifest.json`, and used by `loam verify` to reproduce the build.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Manifest {
+    /// Output of `rustc --version`
+    pub rustc: Option<String>,
+    pub cargo_lock_sha256: Option<String>,
+    /// Commit of the workspace, marked as `-dirty` if it had uncommitted changes
+    pub git_commit: Option<String>,
+    /// Arguments to `loam build` which affect the built wasm
+    pub build_args: Vec<String>,
+    pub contracts: BTreeMap<String, Contract>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Contract {
+    pub wasm_sha256: String,
+    /// The cargo command the contract was built with, including the flags passed to rustc
+    pub command: String,
+    pub rustflags: Option<String>,
+    pub features: Vec<String>,
+}
+
+impl Manifest {
+    pub fn path(target_dir: &Path) -> PathBuf {
+        target_dir.join("loam").join("manifest.json")
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let json = fs::read_to_string(path).map_err(|e| Error::Reading(path.to_path_buf(), e))?;
+        serde_json::from_str(&json).map_err(|e| Error::Parsing(path.to_path_buf(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(self).expect("manifest serializes to json");
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| Error::Writing(parent.to_path_buf(), e))?;
+        }
+        fs::write(path, json).map_err(|e| Error::Writing(path.to_path_buf(), e))
+    }
+
+    /// Name of the contract whose wasm has the given hash
+    pub fn find(&self, wasm_sha256: &str) -> Option<&str> {
+        self.contracts
+            .iter()
+            .find(|(_, contract)| contract.wasm_sha256 == wasm_sha256)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+pub fn sha256_file(path: &Path) -> Result<String, io::Error> {
+    Ok(hex::encode(Sha256::digest(fs::read(path)?)))
+}
+
+/// `rustc --version` of the toolchain used in `dir`, which may be pinned by a `rust-toolchain.toml`
+pub fn rustc_version(dir: &Path) -> Option<String> {
+    let output = Command::new("rustc")
+        .current_dir(dir)
+        .arg("--version")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_fs::TempDir;
+
+    fn contract(wasm_sha256: &str) -> Contract {
+        Contract {
+            wasm_sha256: wasm_sha256.to_string(),
+            command: "cargo rustc --crate-type=cdylib".to_string(),
+            rustflags: None,
+            features: vec!["testutils".to_string()],
+        }
+    }
+
+    #[test]
+    fn manifests_are_saved_and_loaded() {
+        let dir = TempDir::new().unwrap();
+        let manifest = Manifest {
+            rustc: Some("rustc 1.79.0".to_string()),
+            git_commit: Some("abc-dirty".to_string()),
+            build_args: vec!["--strip".to_string()],
+            contracts: [
+                ("a".to_string(), contract("aa")),
+                ("b".to_string(), contract("bb")),
+            ]
+            .into_iter()
+            .collect(),
+            ..Manifest::default()
+        };
+        // the directory of the manifest is created if need be
+        let path = Manifest::path(&dir.path().join("target"));
+        manifest.save(&path).unwrap();
+        let loaded = Manifest::load(&path).unwrap();
+        assert_eq!(loaded.rustc, manifest.rustc);
+        assert_eq!(loaded.git_commit, manifest.git_commit);
+        assert_eq!(loaded.build_args, manifest.build_args);
+        assert_eq!(loaded.contracts["b"].features, ["testutils"]);
+        assert_eq!(loaded.find("bb"), Some("b"));
+        assert_eq!(loaded.find("cc"), None);
+    }
+
+    #[test]
+    fn manifests_which_are_missing_or_invalid_are_errors() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("manifest.json");
+        assert!(matches!(Manifest::load(&path), Err(Error::Reading(..))));
+        fs::write(&path, "{").unwrap();
+        assert!(matches!(Manifest::load(&path), Err(Error::Parsing(..))));
+    }
+
+    #[test]
+    fn files_are_hashed_with_sha256() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("empty.wasm");
+        fs::write(&path, "").unwrap();
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
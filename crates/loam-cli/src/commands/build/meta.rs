@@ -0,0 +1,87 @@
+use cargo_metadata::Package;
+use itertools::Itertools;
+use loam_build::deps::{DepKind, PackageExt};
+use std::{path::Path, process::Command};
+
+pub const CRATE_NAME: &str = "loam_crate_name";
+pub const CRATE_VERSION: &str = "loam_crate_version";
+pub const GIT_COMMIT: &str = "loam_git_commit";
+pub const SDK_VERSION: &str = "loam_sdk_version";
+pub const SUBCONTRACTS: &str = "loam_subcontracts";
+
+/// Build metadata embedded by `loam build` into the `contractmetav0` section of each contract,
+/// linking a deployed wasm hash back to the source it was built from.
+pub struct BuildMeta {
+    git_commit: Option<String>,
+    embed_commit: bool,
+}
+
+impl BuildMeta {
+    /// Metadata of the contracts in `workspace_root`, which only includes its git commit if
+    /// `embed_commit` is set, because the commit changes the wasm hash of every contract
+    pub fn new(workspace_root: &Path, embed_commit: bool) -> Self {
+        Self {
+            git_commit: git(workspace_root, &["rev-parse", "HEAD"]),
+            embed_commit,
+        }
+    }
+
+    /// Commit of the workspace, if it is in a git repository, marked as `-dirty` if `dir` has
+    /// uncommitted changes
+    pub fn git_commit(&self, dir: &Path) -> Option<String> {
+        let commit = self.git_commit.as_ref()?;
+        let dirty = git(dir, &["status", "--porcelain", "--", "."])
+            .is_some_and(|status| !status.is_empty());
+        Some(if dirty {
+            format!("{commit}-dirty")
+        } else {
+            commit.clone()
+        })
+    }
+
+    /// Metadata entries for `package`.
+    ///
+    /// An embedded commit is marked as `-dirty` if the package's directory has uncommitted
+    /// changes.
+    /// Subcontracts are the `[package.metadata.loam] subcontract = true` dependencies of the
+    /// package, which are the crates `derive_contract` composes the contract from.
+    pub fn entries(
+        &self,
+        package: &Package,
+    ) -> Result<Vec<(String, String)>, loam_build::deps::Error> {
+        let manifest_path = package.manifest_path.as_std_path();
+        let mut entries = vec![
+            (CRATE_NAME.to_string(), package.name.clone()),
+            (CRATE_VERSION.to_string(), package.version.to_string()),
+        ];
+        if self.embed_commit {
+            let package_dir = manifest_path.parent().unwrap_or(Path::new("."));
+            if let Some(commit) = self.git_commit(package_dir) {
+                entries.push((GIT_COMMIT.to_string(), commit));
+            }
+        }
+        let deps = loam_build::deps::all(manifest_path)?;
+        if let Some(sdk) = deps.iter().find(|dep| dep.name == "loam-sdk") {
+            entries.push((SDK_VERSION.to_string(), sdk.version.to_string()));
+        }
+        let subcontracts = deps
+            .iter()
+            .filter(|dep| dep.id != package.id && dep.is_dep(&DepKind::Subcontract))
+            .map(|dep| format!("{}@{}", dep.name, dep.version))
+            .join(",");
+        if !subcontracts.is_empty() {
+            entries.push((SUBCONTRACTS.to_string(), subcontracts));
+        }
+        Ok(entries)
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -8,12 +8,29 @@ use std::{
     ffi::OsStr,
     fmt::Debug,
     fs, io,
-    path::Path,
+    path::{Path, PathBuf},
     process::{Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
 };
 
+use fingerprint::Fingerprints;
+use manifest::Manifest;
+use meta::BuildMeta;
+use wasm::Sizes;
+
 pub mod clients;
 pub mod env_toml;
+pub mod fingerprint;
+pub mod init_script;
+pub mod manifest;
+pub mod meta;
+pub mod multisig;
+pub mod plan;
+pub mod state;
+pub mod wasm;
 
 /// Build a contract from source
 ///
@@ -22,6 +39,23 @@ pub mod env_toml;
 /// target. Unless configured otherwise, crates are built with their default
 /// features and with their release profile.
 ///
+/// The crate name and version, loam-sdk version and subcontracts of each
+/// contract, and with --embed-commit the git commit it was built from, are
+/// embedded in its `contractmetav0` section, and can be read back with
+/// `loam inspect`.
+///
+/// After building, the size of each contract is reported, along with how much
+/// it changed since the previous build. The build fails if any contract is
+/// larger than --max-wasm-size.
+///
+/// With --locked-manifest, cargo is run with --locked and the hash of each
+/// contract is recorded in `target/loam/manifest.json`, along with the
+/// toolchain and flags it was built with, so that `loam verify` can reproduce
+/// the build.
+///
+/// Contracts whose sources, `Cargo.lock` and build options have not changed
+/// since their last successful build are skipped, unless --force is used.
+///
 /// To view the commands that will be executed, without executing them, use the
 /// --print-commands-only option.
 #[derive(Parser, Debug, Clone)]
@@ -65,6 +99,54 @@ pub struct Cmd {
     /// Print commands to build without executing them
     #[arg(long, conflicts_with = "out_dir", help_heading = "Other")]
     pub print_commands_only: bool,
+    /// Number of contracts to build at once
+    ///
+    /// Contracts that do not depend on each other are built by up to this
+    /// many `cargo rustc` processes at once, each running the same command as
+    /// it would on its own. Cargo locks its target directory while it builds,
+    /// so every process after the first builds in its own directory under
+    /// `target/loam/jobs`, which builds the contracts' dependencies again the
+    /// first time.
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub jobs: u16,
+    /// Rebuild all contracts, even those whose inputs have not changed
+    #[arg(long)]
+    pub force: bool,
+    /// Optimize the wasm files for size, like `stellar contract optimize`
+    ///
+    /// Requires loam-cli to be installed with the "opt" feature. Optimized
+    /// files are written to the out directory, rather than linked to the cargo
+    /// target directory.
+    #[arg(long, help_heading = "Post-processing")]
+    pub optimize: bool,
+    /// Remove custom sections other than the contract spec and metadata,
+    /// such as `name` and `producers`
+    #[arg(long, help_heading = "Post-processing")]
+    pub strip: bool,
+    /// Don't embed build metadata in the contracts
+    #[arg(long, help_heading = "Post-processing")]
+    pub no_meta: bool,
+    /// Also embed the git commit of the workspace in the contracts' metadata
+    ///
+    /// The commit is marked as `-dirty` if the contract's directory has
+    /// uncommitted changes. Every new commit changes the hash of every
+    /// contract, so all of them are rebuilt and redeployed.
+    #[arg(long, help_heading = "Post-processing", conflicts_with = "no_meta")]
+    pub embed_commit: bool,
+    /// Build with `cargo --locked` and write `target/loam/manifest.json`,
+    /// recording the hash, toolchain and flags of each contract
+    #[arg(long, conflicts_with = "print_commands_only")]
+    pub locked_manifest: bool,
+    /// Write the manifest of --locked-manifest here instead
+    #[arg(long, requires = "locked_manifest")]
+    pub manifest_out: Option<PathBuf>,
+    /// Fail if a contract is larger than this many bytes
+    ///
+    /// Defaults to the `contract_max_size_bytes` setting of the network of the
+    /// environment, with --build-clients, or else to its value on testnet and
+    /// mainnet.
+    #[arg(long, help_heading = "Post-processing")]
+    pub max_wasm_size: Option<u64>,
     /// Build client code in addition to building the contract
     #[arg(long)]
     pub build_clients: bool,
@@ -92,105 +174,140 @@ pub enum Error {
     Loam(#[from] loam_build::deps::Error),
     #[error(transparent)]
     BuildClients(#[from] clients::Error),
+    #[error("fingerprinting contract inputs: {0}")]
+    Fingerprint(io::Error),
+    #[error(transparent)]
+    Wasm(#[from] wasm::Error),
+    #[error(transparent)]
+    Manifest(#[from] manifest::Error),
+    #[error("hashing {0:?}: {1}")]
+    Hashing(PathBuf, io::Error),
+}
+
+/// Failure of one of the jobs of [`Cmd::run_builds`], which unlike [`Error`] can be sent between
+/// threads
+enum JobError {
+    Cargo(io::Error),
+    Exit(ExitStatus),
+    Copying(io::Error),
+}
+
+impl From<JobError> for Error {
+    fn from(e: JobError) -> Self {
+        match e {
+            JobError::Cargo(e) => Error::CargoCmd(e),
+            JobError::Exit(status) => Error::Exit(status),
+            JobError::Copying(e) => Error::CopyingWasmFile(e),
+        }
+    }
 }
 
 impl Cmd {
     pub fn list_packages(&self) -> Result<Vec<Package>, Error> {
+        Ok(self.list_package_levels()?.into_iter().flatten().collect())
+    }
+
+    /// Packages grouped so that each group only depends on contracts in earlier groups
+    pub fn list_package_levels(&self) -> Result<Vec<Vec<Package>>, Error> {
         let metadata = self.metadata()?;
         let packages = self.packages(&metadata)?;
-        Ok(loam_build::deps::get_workspace(&packages)?)
+        Ok(loam_build::deps::get_workspace_levels(&packages)?)
     }
 
     pub async fn run(&self) -> Result<(), Error> {
         let working_dir = env::current_dir().map_err(Error::GettingCurrentDir)?;
         let metadata = self.metadata()?;
-        let packages = self.list_packages()?;
+        let levels = self.list_package_levels()?;
         if self.list {
-            for p in packages {
+            for p in levels.iter().flatten() {
                 println!("{}", p.name);
             }
             return Ok(());
         }
-        let target_dir = &metadata.target_directory;
+        let target_dir = metadata.target_directory.as_std_path();
 
         if let Some(package) = &self.package {
-            if packages.is_empty() {
+            if levels.is_empty() {
                 return Err(Error::PackageNotFound {
                     package: package.clone(),
                 });
             }
         }
 
+        let fingerprints = Fingerprints::new(target_dir);
+        let mut sizes = Sizes::load(target_dir);
+        let workspace_root = metadata.workspace_root.as_std_path();
+        let build_meta = BuildMeta::new(workspace_root, self.embed_commit);
+        let rustc = manifest::rustc_version(workspace_root);
+        let mut outputs = Vec::new();
         let mut package_names: Vec<String> = Vec::new();
-        for p in packages {
-            package_names.push(p.name.clone().replace('-', "_"));
-            let mut cmd = Command::new("cargo");
-            cmd.stdout(Stdio::piped());
-            cmd.arg("rustc");
-            let manifest_path = pathdiff::diff_paths(&p.manifest_path, &working_dir)
-                .unwrap_or(p.manifest_path.clone().into());
-            cmd.arg(format!(
-                "--manifest-path={}",
-                manifest_path.to_string_lossy()
-            ));
-            cmd.arg("--crate-type=cdylib");
-            cmd.arg("--target=wasm32-unknown-unknown");
-            let profile = self.profile.as_deref().unwrap_or("release");
-            if profile == "release" {
-                cmd.arg("--release");
-            } else if profile != "debug" {
-                cmd.arg(format!("--profile={profile}"));
-            }
-            if self.all_features {
-                cmd.arg("--all-features");
-            }
-            if self.no_default_features {
-                cmd.arg("--no-default-features");
-            }
-            if let Some(features) = self.features() {
-                let requested: HashSet<String> = features.iter().cloned().collect();
-                let available = p.features.iter().map(|f| f.0).cloned().collect();
-                let activate = requested.intersection(&available).join(",");
-                if !activate.is_empty() {
-                    cmd.arg(format!("--features={activate}"));
+        for level in &levels {
+            let mut stale = Vec::new();
+            for p in level {
+                package_names.push(p.name.clone().replace('-', "_"));
+                if self.print_commands_only {
+                    stale.push((p, String::new()));
+                    continue;
                 }
+                let fingerprint = fingerprints
+                    .compute(
+                        &self.build_key(p, &working_dir, &build_meta, rustc.as_deref()),
+                        p,
+                        &metadata.packages,
+                        workspace_root,
+                    )
+                    .map_err(Error::Fingerprint)?;
+                if !self.force
+                    && fingerprints.is_fresh(&p.name, &fingerprint)
+                    && self.target_file_path(target_dir, p).exists()
+                {
+                    eprintln!("✅ {:?} is up to date, skipping build", p.name);
+                    let out_file_path = self.output_wasm(target_dir, p, &build_meta, false)?;
+                    sizes.record(&p.name, &out_file_path)?;
+                    outputs.push((p, out_file_path));
+                    continue;
+                }
+                stale.push((p, fingerprint));
             }
-            if self.profile.is_none() {
-                set_default_profile_flags(&mut cmd);
-            }
-            let cmd_str = format!(
-                "cargo {}",
-                cmd.get_args().map(OsStr::to_string_lossy).join(" ")
-            );
 
             if self.print_commands_only {
-                println!("{cmd_str}");
-            } else {
-                eprintln!("{cmd_str}");
-                let status = cmd.status().map_err(Error::CargoCmd)?;
-                if !status.success() {
-                    return Err(Error::Exit(status));
-                }
-
-                let out_dir = self
-                    .out_dir
-                    .clone()
-                    .unwrap_or_else(|| Path::new(target_dir).join("loam"));
-
-                fs::create_dir_all(&out_dir).map_err(Error::CreatingOutDir)?;
-                let file = format!("{}.wasm", p.name.replace('-', "_"));
-                let target_file_path = Path::new(target_dir)
-                    .join("wasm32-unknown-unknown")
-                    .join(profile)
-                    .join(&file);
-                let out_file_path = out_dir.join(&file);
-                if !out_file_path.exists() {
-                    symlink::symlink_file(target_file_path, out_file_path)
-                        .map_err(Error::CopyingWasmFile)?;
+                for (p, _) in stale {
+                    println!("{}", command_string(&self.cargo_rustc(p, &working_dir)));
                 }
+                continue;
+            }
+            self.run_builds(
+                stale.iter().map(|(p, _)| *p).collect(),
+                target_dir,
+                &working_dir,
+            )?;
+            for (p, fingerprint) in stale {
+                let out_file_path = self.output_wasm(target_dir, p, &build_meta, true)?;
+                sizes.record(&p.name, &out_file_path)?;
+                outputs.push((p, out_file_path.clone()));
+                fingerprints
+                    .save(&p.name, &fingerprint)
+                    .map_err(Error::Fingerprint)?;
             }
         }
 
+        if !self.print_commands_only {
+            sizes.report();
+            sizes.save()?;
+            sizes.check_max(self.max_wasm_size(workspace_root).await)?;
+        }
+
+        if self.locked_manifest {
+            self.write_manifest(
+                target_dir,
+                workspace_root,
+                &working_dir,
+                rustc,
+                build_meta.git_commit(workspace_root).as_deref(),
+                &outputs,
+            )?;
+        }
+
         if self.build_clients {
             self.build_clients_args
                 .run(&metadata.workspace_root.into_std_path_buf(), package_names)
@@ -200,6 +317,318 @@ impl Cmd {
         Ok(())
     }
 
+    /// The --max-wasm-size, or else that of the environment's network, if clients are built and
+    /// it can be reached, or else that of testnet and mainnet
+    async fn max_wasm_size(&self, workspace_root: &Path) -> u64 {
+        if let Some(max) = self.max_wasm_size {
+            return max;
+        }
+        if self.build_clients {
+            if let Some(max) = self
+                .build_clients_args
+                .network_max_wasm_size(workspace_root)
+                .await
+            {
+                return max;
+            }
+        }
+        wasm::DEFAULT_MAX_WASM_SIZE
+    }
+
+    /// Build the `packages` of one level of the dependency graph, which don't depend on each
+    /// other, with up to `--jobs` of them being built at once. Job `n` builds in
+    /// [`job_target_dir`], from where the wasm is copied to the cargo target directory, as if it
+    /// had been built there. Once a build fails, no other is started.
+    fn run_builds(
+        &self,
+        packages: Vec<&Package>,
+        target_dir: &Path,
+        working_dir: &Path,
+    ) -> Result<(), Error> {
+        let queue = Mutex::new(packages.into_iter());
+        let failed = AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            let jobs = (0..usize::from(self.jobs))
+                .map(|job| {
+                    let (queue, failed) = (&queue, &failed);
+                    scope.spawn(move || -> Result<(), JobError> {
+                        while !failed.load(Ordering::Relaxed) {
+                            let Some(p) = queue.lock().expect("jobs don't panic").next() else {
+                                break;
+                            };
+                            let result = self.run_build(p, job, target_dir, working_dir);
+                            if result.is_err() {
+                                failed.store(true, Ordering::Relaxed);
+                                return result;
+                            }
+                        }
+                        Ok(())
+                    })
+                })
+                .collect::<Vec<_>>();
+            jobs.into_iter()
+                .try_for_each(|job| job.join().expect("jobs don't panic"))
+        })
+        .map_err(Error::from)
+    }
+
+    /// Build `p` with `cargo rustc` as job number `job`
+    fn run_build(
+        &self,
+        p: &Package,
+        job: usize,
+        target_dir: &Path,
+        working_dir: &Path,
+    ) -> Result<(), JobError> {
+        let mut cmd = self.cargo_rustc(p, working_dir);
+        let job_dir = job_target_dir(target_dir, job);
+        let mut cmd_str = command_string(&cmd);
+        if let Some(job_dir) = &job_dir {
+            cmd.env("CARGO_TARGET_DIR", job_dir);
+            cmd_str = format!("CARGO_TARGET_DIR={} {cmd_str}", job_dir.display());
+        }
+        eprintln!("{cmd_str}");
+        let status = cmd.status().map_err(JobError::Cargo)?;
+        if !status.success() {
+            return Err(JobError::Exit(status));
+        }
+        if let Some(job_dir) = &job_dir {
+            let target_file_path = self.target_file_path(target_dir, p);
+            if let Some(parent) = target_file_path.parent() {
+                fs::create_dir_all(parent).map_err(JobError::Copying)?;
+            }
+            fs::copy(self.target_file_path(job_dir, p), target_file_path)
+                .map_err(JobError::Copying)?;
+        }
+        Ok(())
+    }
+
+    /// Record the hash, toolchain and flags of each of the `outputs` of a build
+    fn write_manifest(
+        &self,
+        target_dir: &Path,
+        workspace_root: &Path,
+        working_dir: &Path,
+        rustc: Option<String>,
+        git_commit: Option<&str>,
+        outputs: &[(&Package, PathBuf)],
+    ) -> Result<(), Error> {
+        let manifest = Manifest {
+            rustc,
+            cargo_lock_sha256: manifest::sha256_file(&workspace_root.join("Cargo.lock")).ok(),
+            git_commit: git_commit.map(String::from),
+            build_args: self.reproducible_args(),
+            contracts: outputs
+                .iter()
+                .map(|(p, out_file_path)| {
+                    let cmd = self.cargo_rustc(p, working_dir);
+                    let contract = manifest::Contract {
+                        wasm_sha256: manifest::sha256_file(out_file_path)
+                            .map_err(|e| Error::Hashing(out_file_path.clone(), e))?,
+                        command: command_string(&cmd),
+                        rustflags: cmd
+                            .get_envs()
+                            .find(|(key, _)| *key == "RUSTFLAGS")
+                            .and_then(|(_, val)| val)
+                            .map(|val| val.to_string_lossy().to_string())
+                            .or_else(|| env::var("RUSTFLAGS").ok()),
+                        features: self.package_features(p),
+                    };
+                    Ok((p.name.clone(), contract))
+                })
+                .collect::<Result<_, Error>>()?,
+        };
+        let path = self
+            .manifest_out
+            .clone()
+            .unwrap_or_else(|| Manifest::path(target_dir));
+        manifest.save(&path)?;
+        eprintln!("📝 wrote build manifest to {}", path.display());
+        Ok(())
+    }
+
+    /// `cargo rustc` command building a single package as a cdylib
+    fn cargo_rustc(&self, p: &Package, working_dir: &Path) -> Command {
+        let mut cmd = Command::new("cargo");
+        cmd.stdout(Stdio::piped());
+        cmd.arg("rustc");
+        let manifest_path = pathdiff::diff_paths(&p.manifest_path, working_dir)
+            .unwrap_or(p.manifest_path.clone().into());
+        cmd.arg(format!(
+            "--manifest-path={}",
+            manifest_path.to_string_lossy()
+        ));
+        cmd.arg("--crate-type=cdylib");
+        cmd.arg("--target=wasm32-unknown-unknown");
+        if self.locked_manifest {
+            cmd.arg("--locked");
+        }
+        self.add_profile_args(&mut cmd);
+        self.add_feature_args(&mut cmd);
+        if let Some(features) = self.features() {
+            let requested: HashSet<String> = features.iter().cloned().collect();
+            let available = p.features.iter().map(|f| f.0).cloned().collect();
+            let activate = requested.intersection(&available).join(",");
+            if !activate.is_empty() {
+                cmd.arg(format!("--features={activate}"));
+            }
+        }
+        if self.profile.is_none() {
+            set_default_profile_flags(&mut cmd);
+        }
+        cmd
+    }
+
+    /// Describes how `p` will be built, so that a change in build options or toolchain
+    /// invalidates its fingerprint
+    fn build_key(
+        &self,
+        p: &Package,
+        working_dir: &Path,
+        build_meta: &BuildMeta,
+        rustc: Option<&str>,
+    ) -> String {
+        let cmd = self.cargo_rustc(p, working_dir);
+        let git_commit = if self.embed_commit {
+            build_meta.git_commit(
+                p.manifest_path
+                    .parent()
+                    .map_or(working_dir, |dir| dir.as_std_path()),
+            )
+        } else {
+            None
+        };
+        format!(
+            "{} optimize={} strip={} meta={} commit={} rustc={}",
+            command_string(&cmd),
+            self.optimize,
+            self.strip,
+            !self.no_meta,
+            git_commit.as_deref().unwrap_or_default(),
+            rustc.unwrap_or_default()
+        )
+    }
+
+    /// Arguments which affect the built wasm, for `loam verify` to build with the same options
+    fn reproducible_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(package) = &self.package {
+            args.push(format!("--package={package}"));
+        }
+        if let Some(profile) = &self.profile {
+            args.push(format!("--profile={profile}"));
+        }
+        if let Some(features) = &self.features {
+            args.push(format!("--features={features}"));
+        }
+        for (flag, set) in [
+            ("--all-features", self.all_features),
+            ("--no-default-features", self.no_default_features),
+            ("--optimize", self.optimize),
+            ("--strip", self.strip),
+            ("--no-meta", self.no_meta),
+            ("--embed-commit", self.embed_commit),
+        ] {
+            if set {
+                args.push(flag.to_string());
+            }
+        }
+        args
+    }
+
+    /// Requested features which `p` has
+    fn package_features(&self, p: &Package) -> Vec<String> {
+        self.features()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|f| p.features.contains_key(f))
+            .collect()
+    }
+
+    fn add_profile_args(&self, cmd: &mut Command) {
+        let profile = self.profile_name();
+        if profile == "release" {
+            cmd.arg("--release");
+        } else if profile != "debug" {
+            cmd.arg(format!("--profile={profile}"));
+        }
+    }
+
+    fn add_feature_args(&self, cmd: &mut Command) {
+        if self.all_features {
+            cmd.arg("--all-features");
+        }
+        if self.no_default_features {
+            cmd.arg("--no-default-features");
+        }
+    }
+
+    fn profile_name(&self) -> &str {
+        self.profile.as_deref().unwrap_or("release")
+    }
+
+    fn target_file_path(&self, target_dir: &Path, p: &Package) -> PathBuf {
+        target_dir
+            .join("wasm32-unknown-unknown")
+            .join(self.profile_name())
+            .join(wasm_file_name(p))
+    }
+
+    /// Make the built wasm available in the out directory, post-processing it if requested.
+    ///
+    /// Without post-processing, the out directory only links to the cargo target directory.
+    /// A post-processed file is only regenerated if the contract was `rebuilt` or is missing.
+    fn output_wasm(
+        &self,
+        target_dir: &Path,
+        p: &Package,
+        build_meta: &BuildMeta,
+        rebuilt: bool,
+    ) -> Result<PathBuf, Error> {
+        let out_dir = self
+            .out_dir
+            .clone()
+            .unwrap_or_else(|| target_dir.join("loam"));
+        fs::create_dir_all(&out_dir).map_err(Error::CreatingOutDir)?;
+        let target_file_path = self.target_file_path(target_dir, p);
+        let out_file_path = out_dir.join(wasm_file_name(p));
+        let is_link = out_file_path.is_symlink();
+        let post_process = self.optimize || self.strip || !self.no_meta;
+
+        let up_to_date = if post_process {
+            !rebuilt && !is_link && out_file_path.is_file()
+        } else {
+            is_link
+        };
+        if up_to_date {
+            return Ok(out_file_path);
+        }
+        // Never write through a link into the cargo target directory
+        if is_link || out_file_path.exists() {
+            fs::remove_file(&out_file_path).map_err(Error::CopyingWasmFile)?;
+        }
+        if !post_process {
+            symlink::symlink_file(&target_file_path, &out_file_path)
+                .map_err(Error::CopyingWasmFile)?;
+            return Ok(out_file_path);
+        }
+
+        let mut input = target_file_path.as_path();
+        if !self.no_meta {
+            wasm::add_meta(input, &out_file_path, &build_meta.entries(p)?)?;
+            input = &out_file_path;
+        }
+        if self.optimize {
+            wasm::optimize(input, &out_file_path)?;
+            input = &out_file_path;
+        }
+        if self.strip {
+            wasm::strip(input, &out_file_path)?;
+        }
+        wasm::check_sections(&p.name, &target_file_path, &out_file_path)?;
+        Ok(out_file_path)
+    }
+
     fn features(&self) -> Option<Vec<String>> {
         self.features
             .as_ref()
@@ -224,12 +653,8 @@ impl Cmd {
         Ok(metadata
             .packages
             .iter()
-            .filter(|p| {
-                // Filter crates by those that build to cdylib (wasm)
-                p.targets
-                    .iter()
-                    .any(|t| t.crate_types.iter().any(|c| c == "cdylib"))
-            })
+            // Filter crates by those that build to cdylib (wasm)
+            .filter(|p| is_cdylib(p))
             .cloned()
             .collect())
     }
@@ -267,3 +692,26 @@ fn set_default_profile_flags(cmd: &mut Command) {
     ]);
     cmd.env("RUSTFLAGS", "-C embed-bitcode=yes");
 }
+
+/// Directory which job number `job` of [`Cmd::run_builds`] builds in, if not the cargo target
+/// directory
+fn job_target_dir(target_dir: &Path, job: usize) -> Option<PathBuf> {
+    (job > 0).then(|| target_dir.join("loam").join("jobs").join(job.to_string()))
+}
+
+fn is_cdylib(p: &Package) -> bool {
+    p.targets
+        .iter()
+        .any(|t| t.crate_types.iter().any(|c| c == "cdylib"))
+}
+
+fn wasm_file_name(p: &Package) -> String {
+    format!("{}.wasm", p.name.replace('-', "_"))
+}
+
+fn command_string(cmd: &Command) -> String {
+    format!(
+        "cargo {}",
+        cmd.get_args().map(OsStr::to_string_lossy).join(" ")
+    )
+}
@@ -0,0 +1,202 @@
+use ed25519_dalek::SigningKey;
+use soroban_cli::commands::{contract::id::wasm::get_contract_id, txn_result::TxnResult};
+use soroban_cli::{commands::NetworkRunnable, CommandParser};
+use stellar_xdr::curr::{
+    CreateContractArgs, HostFunction, InvokeHostFunctionOp, OperationBody, Transaction,
+    TransactionEnvelope, TransactionV1Envelope,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    ConfigLocator(#[from] soroban_cli::config::locator::Error),
+    #[error(transparent)]
+    Secret(#[from] soroban_cli::config::secret::Error),
+    #[error(transparent)]
+    Signer(#[from] soroban_cli::signer::Error),
+    #[error(transparent)]
+    Rpc(Box<soroban_rpc::Error>),
+    #[error(transparent)]
+    ContractId(Box<soroban_cli::commands::contract::id::wasm::Error>),
+    #[error(transparent)]
+    Xdr(#[from] stellar_xdr::curr::Error),
+}
+
+impl_from_boxed!(Error {
+    ContractId(soroban_cli::commands::contract::id::wasm::Error),
+    Rpc(soroban_rpc::Error),
+});
+
+/// What became of the transaction made by a command run with [`run`]
+#[derive(Debug)]
+pub enum Outcome<T> {
+    /// The command signed and sent the transaction itself, returning this
+    Sent(T),
+    /// The command simulated the transaction, which every signer then signed before it was sent
+    Signed(Box<Transaction>),
+}
+
+/// Run a command of the stellar CLI which makes a transaction, with `args` as its arguments and
+/// the first of `signers` as the transaction's source. With a single signer, the command signs
+/// and sends the transaction itself. With several, it only simulates it, and it is sent once
+/// every signer has signed it. With none, the default account signs it.
+pub async fn run<C, T, E>(args: &[&str], signers: &[String]) -> Result<Outcome<T>, E>
+where
+    C: CommandParser<C> + NetworkRunnable<Result = TxnResult<T>> + Send + Sync,
+    E: From<C::Error> + From<clap::Error> + From<Error>,
+{
+    let mut all_args = Vec::new();
+    if let Some(source) = signers.first() {
+        all_args.extend(["--source-account", source.as_str()]);
+    }
+    if signers.len() > 1 {
+        all_args.push("--sim-only");
+    }
+    all_args.extend_from_slice(args);
+    let result = C::parse_arg_vec(&all_args)?
+        .run_against_rpc_server(None, None)
+        .await?;
+    match result {
+        TxnResult::Res(result) => Ok(Outcome::Sent(result)),
+        TxnResult::Txn(tx) => {
+            send(&tx, signers).await?;
+            Ok(Outcome::Signed(Box::new(tx)))
+        }
+    }
+}
+
+/// Sign a transaction with the keys of each of `signers`, and send it
+async fn send(tx: &Transaction, signers: &[String]) -> Result<(), Error> {
+    let locator = soroban_cli::config::locator::Args::default();
+    let keys = signers
+        .iter()
+        .map(|signer| Ok(locator.read_identity(signer)?.key_pair(None)?))
+        .collect::<Result<Vec<_>, Error>>()?;
+    eprintln!("✍️ signing with {}", signers.join(", "));
+    let passphrase = std::env::var("STELLAR_NETWORK_PASSPHRASE")
+        .expect("No STELLAR_NETWORK_PASSPHRASE environment variable set");
+    let envelope = sign(tx, &keys, &passphrase)?;
+    let rpc_url =
+        std::env::var("STELLAR_RPC_URL").expect("No STELLAR_RPC_URL environment variable set");
+    soroban_rpc::Client::new(&rpc_url)?
+        .send_transaction_polling(&envelope)
+        .await?;
+    Ok(())
+}
+
+/// A transaction signed by each of `keys`
+pub fn sign(
+    tx: &Transaction,
+    keys: &[SigningKey],
+    network_passphrase: &str,
+) -> Result<TransactionEnvelope, Error> {
+    let mut signatures = Vec::new();
+    for key in keys {
+        if let TransactionEnvelope::Tx(signed) =
+            soroban_cli::signer::sign_tx(key, tx, network_passphrase)?
+        {
+            signatures.extend(signed.signatures.iter().cloned());
+        }
+    }
+    Ok(TransactionEnvelope::Tx(TransactionV1Envelope {
+        tx: tx.clone(),
+        signatures: signatures.try_into()?,
+    }))
+}
+
+/// Id of the contract which a transaction deploys, if it deploys one
+pub fn deployed_contract_id(
+    tx: &Transaction,
+    network_passphrase: &str,
+) -> Result<Option<String>, Error> {
+    for operation in tx.operations.iter() {
+        if let OperationBody::InvokeHostFunction(InvokeHostFunctionOp {
+            host_function:
+                HostFunction::CreateContract(CreateContractArgs {
+                    contract_id_preimage,
+                    ..
+                }),
+            ..
+        }) = &operation.body
+        {
+            let id = get_contract_id(contract_id_preimage.clone(), network_passphrase)?;
+            return Ok(Some(stellar_strkey::Contract(id.0).to_string()));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ed25519_dalek::{Signature, Verifier};
+    use soroban_cli::commands::contract::id::wasm::contract_preimage;
+    use stellar_xdr::curr::{
+        ContractExecutable, Hash, Memo, MuxedAccount, Operation, Preconditions, SequenceNumber,
+        TransactionExt, Uint256,
+    };
+
+    const PASSPHRASE: &str = "Test SDF Network ; September 2015";
+
+    fn transaction(key: &SigningKey, body: OperationBody) -> Transaction {
+        Transaction {
+            source_account: MuxedAccount::Ed25519(Uint256(key.verifying_key().to_bytes())),
+            fee: 100,
+            seq_num: SequenceNumber(1),
+            cond: Preconditions::None,
+            memo: Memo::None,
+            operations: vec![Operation {
+                source_account: None,
+                body,
+            }]
+            .try_into()
+            .unwrap(),
+            ext: TransactionExt::V0,
+        }
+    }
+
+    fn deploy(key: &SigningKey, salt: [u8; 32]) -> OperationBody {
+        OperationBody::InvokeHostFunction(InvokeHostFunctionOp {
+            host_function: HostFunction::CreateContract(CreateContractArgs {
+                contract_id_preimage: contract_preimage(&key.verifying_key(), salt),
+                executable: ContractExecutable::Wasm(Hash([7; 32])),
+            }),
+            auth: vec![].try_into().unwrap(),
+        })
+    }
+
+    #[test]
+    fn every_signer_signs_the_transaction() {
+        let keys = [
+            SigningKey::from_bytes(&[1; 32]),
+            SigningKey::from_bytes(&[2; 32]),
+        ];
+        let tx = transaction(&keys[0], deploy(&keys[0], [0; 32]));
+        let TransactionEnvelope::Tx(envelope) = sign(&tx, &keys, PASSPHRASE).unwrap() else {
+            panic!("expected a transaction envelope");
+        };
+        assert_eq!(envelope.tx, tx);
+        assert_eq!(envelope.signatures.len(), 2);
+        let hash = soroban_cli::signer::hash(&tx, PASSPHRASE).unwrap();
+        for (key, signature) in keys.iter().zip(envelope.signatures.iter()) {
+            let public_key = key.verifying_key();
+            assert_eq!(signature.hint.0, public_key.to_bytes()[28..]);
+            let signature = Signature::from_slice(&signature.signature.0).unwrap();
+            assert!(public_key.verify(&hash, &signature).is_ok());
+        }
+    }
+
+    #[test]
+    fn contract_id_is_read_from_the_deploy_transaction() {
+        let key = SigningKey::from_bytes(&[1; 32]);
+        let salt = [3; 32];
+        let id =
+            get_contract_id(contract_preimage(&key.verifying_key(), salt), PASSPHRASE).unwrap();
+        assert_eq!(
+            deployed_contract_id(&transaction(&key, deploy(&key, salt)), PASSPHRASE).unwrap(),
+            Some(stellar_strkey::Contract(id.0).to_string())
+        );
+        let other = transaction(&key, OperationBody::Inflation);
+        assert_eq!(deployed_contract_id(&other, PASSPHRASE).unwrap(), None);
+    }
+}
@@ -0,0 +1,332 @@
+use serde::Serialize;
+use std::fmt;
+
+use super::{
+    env_toml::{self, Network, Upgrade},
+    state::InitStatus,
+};
+
+/// How to print a plan with `--dry-run`
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, clap::ValueEnum)]
+pub enum Format {
+    Table,
+    Json,
+}
+
+/// What will be done to get a contract deployed
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    /// Install the built wasm and deploy a new contract from it, saving it as an alias
+    Deploy,
+    /// Install the built wasm and deploy a new contract from it, replacing an alias to a
+    /// contract which runs another wasm, or no longer exists
+    Replace,
+    /// Install the built wasm and switch the aliased contract to it with its `redeploy`
+    /// function, keeping its id and storage
+    Upgrade,
+    /// Keep using the aliased contract, which already runs the built wasm
+    Reuse,
+    /// Use the contract id given in `environments.toml`
+    BindOnly,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Deploy => write!(f, "deploy"),
+            Action::Replace => write!(f, "replace"),
+            Action::Upgrade => write!(f, "upgrade"),
+            Action::Reuse => write!(f, "reuse"),
+            Action::BindOnly => write!(f, "bind-only"),
+        }
+    }
+}
+
+/// The contract a saved alias points to, if there is one
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Alias {
+    None,
+    /// The contract runs the built wasm
+    UpToDate(String),
+    /// The contract runs another wasm
+    Outdated(String),
+    /// The contract no longer exists, e.g. because the network was reset
+    Missing(String),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct ContractPlan {
+    pub name: String,
+    pub action: Action,
+    /// Hash of the built wasm, if the contract is deployed from it
+    pub wasm_hash: Option<String>,
+    /// Id of the contract, unless it has yet to be deployed
+    pub contract_id: Option<String>,
+    /// Whether the contract's init script is run
+    pub init: bool,
+    /// Whether a TypeScript client is generated for the contract
+    pub bind: bool,
+    /// Network the contract is on, if it isn't the environment's
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network: Option<Network>,
+}
+
+/// Everything `loam build --build-clients` will do for the contracts of an environment, in order
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Plan {
+    pub env: String,
+    pub contracts: Vec<ContractPlan>,
+}
+
+impl ContractPlan {
+    /// Plan a contract with an id given in `environments.toml`
+    pub fn bind_only(name: &str, contract_id: &str, init: bool) -> Self {
+        ContractPlan {
+            name: name.to_string(),
+            action: Action::BindOnly,
+            wasm_hash: None,
+            contract_id: Some(contract_id.to_string()),
+            init,
+            bind: true,
+            network: None,
+        }
+    }
+
+    /// Plan a contract built from the workspace, depending on whether its alias points to a
+    /// contract running the built wasm. Contracts which are up to date are left alone, and
+    /// upgraded contracts keep their storage, so they are not initialized again.
+    pub fn from_wasm(
+        name: &str,
+        wasm_hash: &str,
+        alias: Alias,
+        upgrade: Upgrade,
+        init: bool,
+    ) -> Self {
+        let (action, contract_id) = match alias {
+            Alias::None => (Action::Deploy, None),
+            Alias::UpToDate(contract_id) => (Action::Reuse, Some(contract_id)),
+            Alias::Outdated(contract_id) if upgrade == Upgrade::Redeploy => {
+                (Action::Upgrade, Some(contract_id))
+            }
+            Alias::Outdated(_) | Alias::Missing(_) => (Action::Replace, None),
+        };
+        ContractPlan {
+            name: name.to_string(),
+            action,
+            wasm_hash: Some(wasm_hash.to_string()),
+            contract_id,
+            init: init && matches!(action, Action::Deploy | Action::Replace),
+            bind: action != Action::Reuse,
+            network: None,
+        }
+    }
+
+    /// Run the init of a contract which is kept rather than newly deployed, if it has changed
+    /// since it last ran against the contract, if it failed and is resumed, or if `reinit` forces
+    /// it. A contract given by id is also initialized if no init has been recorded for it.
+    #[must_use]
+    pub fn init_again(mut self, init: bool, status: InitStatus, reinit: bool) -> Self {
+        if init && self.contract_id.is_some() {
+            self.init = reinit
+                || matches!(status, InitStatus::Outdated | InitStatus::Failed)
+                || (self.action == Action::BindOnly && status == InitStatus::Unknown);
+        }
+        self
+    }
+
+    /// Put the contract on its own network, if it has one
+    #[must_use]
+    pub fn on_network(mut self, network: Option<&Network>) -> Self {
+        self.network = network.cloned();
+        self
+    }
+}
+
+impl Plan {
+    /// Whether the init script of a newly deployed contract is run in `environment`
+    pub fn runs_init(
+        environment: &env_toml::Environment,
+        settings: Option<&env_toml::Contract>,
+    ) -> bool {
+        environment.runs_init() && settings.is_some_and(|settings| settings.init.is_some())
+    }
+
+    pub fn print(&self, format: Format) -> Result<(), serde_json::Error> {
+        match format {
+            Format::Json => println!("{}", serde_json::to_string_pretty(self)?),
+            Format::Table => print!("{self}"),
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "plan for LOAM_ENV={}", self.env)?;
+        if self.contracts.is_empty() {
+            return writeln!(f, "nothing to do");
+        }
+        writeln!(
+            f,
+            "{:<32} {:<10} {:<5} {:<5} contract id / wasm hash",
+            "contract", "action", "init", "bind"
+        )?;
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+        for contract in &self.contracts {
+            let id = match (&contract.contract_id, &contract.wasm_hash) {
+                (Some(id), _) => id.clone(),
+                (None, Some(hash)) => format!("(new) {hash}"),
+                (None, None) => String::new(),
+            };
+            let network = contract
+                .network
+                .as_ref()
+                .map(|network| format!(" on {}", network.label()))
+                .unwrap_or_default();
+            writeln!(
+                f,
+                "{:<32} {:<10} {:<5} {:<5} {id}{network}",
+                contract.name,
+                contract.action.to_string(),
+                yes_no(contract.init),
+                yes_no(contract.bind),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+    const ID: &str = "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAD2KM";
+
+    #[test]
+    fn new_contracts_are_deployed() {
+        let plan = ContractPlan::from_wasm("hello", HASH, Alias::None, Upgrade::Redeploy, true);
+        assert_eq!(plan.action, Action::Deploy);
+        assert_eq!(plan.contract_id, None);
+        assert!(plan.init && plan.bind);
+    }
+
+    #[test]
+    fn outdated_aliases_are_replaced() {
+        let outdated = Alias::Outdated(ID.to_string());
+        let plan = ContractPlan::from_wasm("hello", HASH, outdated, Upgrade::Deploy, true);
+        assert_eq!(plan.action, Action::Replace);
+        assert_eq!(plan.contract_id, None);
+        assert!(plan.init && plan.bind);
+    }
+
+    #[test]
+    fn outdated_aliases_are_upgraded_in_place() {
+        let outdated = Alias::Outdated(ID.to_string());
+        let plan = ContractPlan::from_wasm("hello", HASH, outdated, Upgrade::Redeploy, true);
+        assert_eq!(plan.action, Action::Upgrade);
+        assert_eq!(plan.contract_id.as_deref(), Some(ID));
+        assert!(!plan.init && plan.bind);
+
+        // a contract which no longer exists can't be upgraded
+        let missing = Alias::Missing(ID.to_string());
+        let plan = ContractPlan::from_wasm("hello", HASH, missing, Upgrade::Redeploy, true);
+        assert_eq!(plan.action, Action::Replace);
+    }
+
+    #[test]
+    fn up_to_date_aliases_are_left_alone() {
+        let up_to_date = Alias::UpToDate(ID.to_string());
+        let plan = ContractPlan::from_wasm("hello", HASH, up_to_date, Upgrade::Redeploy, true);
+        assert_eq!(plan.action, Action::Reuse);
+        assert_eq!(plan.contract_id.as_deref(), Some(ID));
+        assert!(!plan.init && !plan.bind);
+    }
+
+    #[test]
+    fn kept_contracts_are_initialized_again_when_their_init_changes() {
+        let reuse = || {
+            let up_to_date = Alias::UpToDate(ID.to_string());
+            ContractPlan::from_wasm("hello", HASH, up_to_date, Upgrade::Deploy, true)
+        };
+        assert!(!reuse().init_again(true, InitStatus::Unknown, false).init);
+        assert!(!reuse().init_again(true, InitStatus::Current, false).init);
+        assert!(reuse().init_again(true, InitStatus::Outdated, false).init);
+        assert!(reuse().init_again(true, InitStatus::Failed, false).init);
+        assert!(reuse().init_again(true, InitStatus::Current, true).init);
+        assert!(!reuse().init_again(false, InitStatus::Outdated, true).init);
+
+        let bound = || ContractPlan::bind_only("hello", ID, true);
+        assert!(bound().init_again(true, InitStatus::Unknown, false).init);
+        assert!(!bound().init_again(true, InitStatus::Current, false).init);
+
+        let new = ContractPlan::from_wasm("hello", HASH, Alias::None, Upgrade::Deploy, true);
+        assert!(new.init_again(true, InitStatus::Current, false).init);
+    }
+
+    fn environment(name: &str, policy: env_toml::Policy) -> env_toml::Environment {
+        env_toml::Environment {
+            name: name.to_string(),
+            policy,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn init_runs_by_default_in_development_and_testing() {
+        let settings = env_toml::Contract {
+            init: Some(env_toml::Init::Script("init".to_string())),
+            ..Default::default()
+        };
+        let default = env_toml::Policy::default();
+        assert!(Plan::runs_init(
+            &environment("development", default),
+            Some(&settings)
+        ));
+        assert!(Plan::runs_init(
+            &environment("testing", default),
+            Some(&settings)
+        ));
+        assert!(!Plan::runs_init(
+            &environment("staging", default),
+            Some(&settings)
+        ));
+        assert!(!Plan::runs_init(&environment("development", default), None));
+    }
+
+    #[test]
+    fn init_runs_when_the_environment_says_so() {
+        let settings = env_toml::Contract {
+            init: Some(env_toml::Init::Script("init".to_string())),
+            ..Default::default()
+        };
+        let deploys = env_toml::Policy {
+            deploy: Some(true),
+            ..Default::default()
+        };
+        assert!(Plan::runs_init(
+            &environment("preview", deploys),
+            Some(&settings)
+        ));
+        let skips_init = env_toml::Policy {
+            run_init: Some(false),
+            ..Default::default()
+        };
+        assert!(!Plan::runs_init(
+            &environment("development", skips_init),
+            Some(&settings)
+        ));
+    }
+
+    #[test]
+    fn json() {
+        let plan = Plan {
+            env: "staging".to_string(),
+            contracts: vec![ContractPlan::bind_only("hello", ID, false)],
+        };
+        let json: serde_json::Value = serde_json::to_value(&plan).unwrap();
+        assert_eq!(json["contracts"][0]["action"], "bind-only");
+        assert_eq!(json["contracts"][0]["contract_id"], ID);
+    }
+}
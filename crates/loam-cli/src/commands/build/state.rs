@@ -0,0 +1,249 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::env_toml::Init;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("⛔ ️failed to read or write {0:?}: {1}")]
+    Io(PathBuf, io::Error),
+    #[error("⛔ ️{0:?} is not valid: {1}; delete it to run every init again")]
+    Json(PathBuf, serde_json::Error),
+}
+
+/// Whether an init has run against a contract
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InitStatus {
+    /// No init is recorded for the contract
+    Unknown,
+    /// The init ran, and hasn't changed since
+    Current,
+    /// Another version of the init ran
+    Outdated,
+    /// The init failed partway through, so it is resumed from the call which failed
+    Failed,
+}
+
+/// An init which ran against a contract, or failed partway through
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct InitRecord {
+    /// Name of the contract in `environments.toml`
+    pub contract: String,
+    /// Hash of the init and of the ids of the contracts it refers to, see [`State::hash`]
+    pub hash: String,
+    /// Whether a call of the init failed, so that the calls after it didn't run
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub failed: bool,
+    /// Hashes of the calls which succeeded before one failed, see [`State::hash_call`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub completed: Vec<String>,
+}
+
+/// What `loam build` has done to the contracts of a project which can't be read back from the
+/// network, kept in `.stellar/loam-state.json`
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct State {
+    /// Inits which ran, by contract id
+    #[serde(default)]
+    pub inits: BTreeMap<String, InitRecord>,
+}
+
+impl State {
+    pub fn path(workspace_root: &Path) -> PathBuf {
+        workspace_root.join(".stellar").join("loam-state.json")
+    }
+
+    /// Read the state of the project, which is empty until something is recorded
+    pub fn load(workspace_root: &Path) -> Result<Self, Error> {
+        let path = Self::path(workspace_root);
+        match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).map_err(|e| Error::Json(path, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(Error::Io(path, e)),
+        }
+    }
+
+    pub fn save(&self, workspace_root: &Path) -> Result<(), Error> {
+        let path = Self::path(workspace_root);
+        let json = serde_json::to_string_pretty(self).map_err(|e| Error::Json(path.clone(), e))?;
+        path.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&path, json + "\n"))
+            .map_err(|e| Error::Io(path, e))
+    }
+
+    /// Hash of an init as written in `environments.toml`, along with the names and ids of the
+    /// contracts it refers to, which changes whenever it is edited or one of those contracts is
+    /// deployed anew
+    pub fn hash(init: &Init, referenced: &[(String, String)]) -> String {
+        let init = serde_json::to_string(&(init, referenced)).expect("inits can be serialized");
+        hex::encode(Sha256::digest(init))
+    }
+
+    /// Hash of a call of an init as it is written, along with the names and ids of the contracts
+    /// the init refers to
+    pub fn hash_call(call: &str, referenced: &[(String, String)]) -> String {
+        let call = serde_json::to_string(&(call, referenced)).expect("calls can be serialized");
+        hex::encode(Sha256::digest(call))
+    }
+
+    /// Whether `init` ran against a contract, where `referenced` are the contracts it refers to,
+    /// or `None` if one of them is yet to be deployed, so that its id will change
+    pub fn init_status(
+        &self,
+        contract_id: &str,
+        init: &Init,
+        referenced: Option<&[(String, String)]>,
+    ) -> InitStatus {
+        match (self.inits.get(contract_id), referenced) {
+            (None, _) => InitStatus::Unknown,
+            (Some(record), _) if record.failed => InitStatus::Failed,
+            (Some(record), Some(referenced)) if record.hash == Self::hash(init, referenced) => {
+                InitStatus::Current
+            }
+            (Some(_), _) => InitStatus::Outdated,
+        }
+    }
+
+    /// Record that `init`, referring to the `referenced` contracts, ran against the contract
+    /// `name`, deployed as `contract_id`
+    pub fn record_init(
+        &mut self,
+        contract_id: &str,
+        name: &str,
+        init: &Init,
+        referenced: &[(String, String)],
+    ) {
+        self.inits.insert(
+            contract_id.to_string(),
+            InitRecord {
+                contract: name.to_string(),
+                hash: Self::hash(init, referenced),
+                failed: false,
+                completed: Vec::new(),
+            },
+        );
+    }
+
+    /// Record that `init` failed against the contract `name` after the `completed` calls
+    pub fn record_failed_init(
+        &mut self,
+        contract_id: &str,
+        name: &str,
+        init: &Init,
+        referenced: &[(String, String)],
+        completed: Vec<String>,
+    ) {
+        self.inits.insert(
+            contract_id.to_string(),
+            InitRecord {
+                contract: name.to_string(),
+                hash: Self::hash(init, referenced),
+                failed: true,
+                completed,
+            },
+        );
+    }
+
+    /// Hashes of the calls of the init which already succeeded against a contract, if it failed
+    pub fn completed_calls(&self, contract_id: &str) -> Vec<String> {
+        self.inits
+            .get(contract_id)
+            .filter(|record| record.failed)
+            .map(|record| record.completed.clone())
+            .unwrap_or_default()
+    }
+
+    /// Forget the init of a contract which is dropped
+    pub fn forget(&mut self, contract_id: &str) {
+        self.inits.remove(contract_id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ID: &str = "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAD2KM";
+
+    #[test]
+    fn inits_are_current_until_they_change() {
+        let init = Init::Script("init --admin alice".to_string());
+        let mut state = State::default();
+        assert_eq!(state.init_status(ID, &init, Some(&[])), InitStatus::Unknown);
+        state.record_init(ID, "token", &init, &[]);
+        assert_eq!(state.init_status(ID, &init, Some(&[])), InitStatus::Current);
+        let changed = Init::Script("init --admin bob".to_string());
+        assert_eq!(
+            state.init_status(ID, &changed, Some(&[])),
+            InitStatus::Outdated
+        );
+    }
+
+    #[test]
+    fn inits_are_outdated_once_a_contract_they_refer_to_is_deployed_anew() {
+        let init = Init::Script("init --token {{contracts.token.id}}".to_string());
+        let token = [("token".to_string(), "C1".to_string())];
+        let mut state = State::default();
+        state.record_init(ID, "vault", &init, &token);
+        assert_eq!(
+            state.init_status(ID, &init, Some(&token)),
+            InitStatus::Current
+        );
+        // about to be deployed, or deployed with another id
+        assert_eq!(state.init_status(ID, &init, None), InitStatus::Outdated);
+        let redeployed = [("token".to_string(), "C2".to_string())];
+        assert_eq!(
+            state.init_status(ID, &init, Some(&redeployed)),
+            InitStatus::Outdated
+        );
+        assert_ne!(
+            State::hash_call("init", &token),
+            State::hash_call("init", &redeployed)
+        );
+    }
+
+    #[test]
+    fn failed_inits_are_resumed() {
+        let init = Init::Script("init --admin alice\nmint --to bob".to_string());
+        let mut state = State::default();
+        let completed = vec![State::hash_call("init --admin alice", &[])];
+        state.record_failed_init(ID, "token", &init, &[], completed.clone());
+        assert_eq!(state.init_status(ID, &init, Some(&[])), InitStatus::Failed);
+        assert_eq!(state.completed_calls(ID), completed);
+
+        state.record_init(ID, "token", &init, &[]);
+        assert_eq!(state.init_status(ID, &init, Some(&[])), InitStatus::Current);
+        assert!(state.completed_calls(ID).is_empty());
+        state.forget(ID);
+        assert_eq!(state.init_status(ID, &init, Some(&[])), InitStatus::Unknown);
+    }
+
+    #[test]
+    fn state_is_saved_in_the_workspace() {
+        let root = assert_fs::TempDir::new().unwrap();
+        assert!(State::load(&root).unwrap().inits.is_empty());
+
+        let init = Init::Script("init --admin alice".to_string());
+        let mut state = State::default();
+        state.record_init(ID, "token", &init, &[]);
+        state.save(&root).unwrap();
+        assert!(root.join(".stellar/loam-state.json").exists());
+        let saved = State::load(&root).unwrap();
+        assert_eq!(saved.init_status(ID, &init, Some(&[])), InitStatus::Current);
+        assert_eq!(saved.inits[ID].contract, "token");
+    }
+
+    #[test]
+    fn invalid_state_is_reported() {
+        let root = assert_fs::TempDir::new().unwrap();
+        fs::create_dir_all(root.join(".stellar")).unwrap();
+        fs::write(State::path(&root), "{").unwrap();
+        assert!(matches!(State::load(&root), Err(Error::Json(..))));
+    }
+}
@@ -0,0 +1,471 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use stellar_xdr::curr::{Limited, Limits, ReadXdr, ScMetaEntry, ScMetaV0, WriteXdr};
+
+/// Custom sections the network and tooling read from a contract, which must survive post-processing
+pub const CONTRACT_SECTIONS: &[&str] = &["contractspecv0", "contractenvmetav0", "contractmetav0"];
+
+/// Value of the `contract_max_size_bytes` network setting on testnet and mainnet, for when the
+/// network isn't known or can't be reached
+pub const DEFAULT_MAX_WASM_SIZE: u64 = 64 * 1024;
+
+const HEADER: &[u8] = b"\0asm\x01\0\0\0";
+const CUSTOM_SECTION_ID: u8 = 0;
+const CONTRACT_META_SECTION: &str = "contractmetav0";
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("reading {0:?}: {1}")]
+    Reading(PathBuf, io::Error),
+    #[error("writing {0:?}: {1}")]
+    Writing(PathBuf, io::Error),
+    #[error("⛔ ️{0:?} is not a valid wasm module")]
+    Malformed(PathBuf),
+    #[error("⛔ ️{name:?} is missing the {section:?} custom section")]
+    MissingSection { name: String, section: String },
+    #[error("⛔ ️post-processing {name:?} changed its {section:?} custom section")]
+    SectionChanged { name: String, section: String },
+    #[error("⛔ ️{name:?} is {size} bytes, which exceeds the maximum contract size of {max} bytes")]
+    TooLarge { name: String, size: u64, max: u64 },
+    #[error(transparent)]
+    Xdr(#[from] stellar_xdr::curr::Error),
+    #[cfg(feature = "opt")]
+    #[error("optimizing {0:?}: {1}")]
+    Optimize(PathBuf, wasm_opt::OptimizationError),
+    #[cfg(not(feature = "opt"))]
+    #[error("⛔ ️--optimize requires loam-cli to be installed with the \"opt\" feature, e.g. `cargo install --locked loam-cli --features opt`")]
+    Install,
+}
+
+struct Section<'a> {
+    id: u8,
+    /// Name of a custom section
+    name: Option<&'a str>,
+    /// Contents of the section, excluding the name of a custom section
+    data: &'a [u8],
+    /// The whole section, including its id and size
+    raw: &'a [u8],
+}
+
+fn read_u32(bytes: &[u8], pos: &mut usize) -> Option<u32> {
+    let mut result = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = *bytes.get(*pos)?;
+        *pos += 1;
+        result |= u32::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(result);
+        }
+    }
+    None
+}
+
+fn sections(wasm: &[u8]) -> Option<Vec<Section<'_>>> {
+    if !wasm.starts_with(HEADER) {
+        return None;
+    }
+    let mut sections = Vec::new();
+    let mut pos = HEADER.len();
+    while pos < wasm.len() {
+        let start = pos;
+        let id = wasm[pos];
+        pos += 1;
+        let size = usize::try_from(read_u32(wasm, &mut pos)?).ok()?;
+        let end = pos.checked_add(size).filter(|end| *end <= wasm.len())?;
+        let mut data = &wasm[pos..end];
+        let mut name = None;
+        if id == CUSTOM_SECTION_ID {
+            let mut name_pos = 0;
+            let len = usize::try_from(read_u32(data, &mut name_pos)?).ok()?;
+            let name_bytes = data.get(name_pos..name_pos + len)?;
+            name = Some(std::str::from_utf8(name_bytes).ok()?);
+            data = &data[name_pos + len..];
+        }
+        sections.push(Section {
+            id,
+            name,
+            data,
+            raw: &wasm[start..end],
+        });
+        pos = end;
+    }
+    Some(sections)
+}
+
+fn write_u32(bytes: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = u8::try_from(value & 0x7f).expect("masked to 7 bits");
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn custom_section_bytes(name: &str, data: &[u8]) -> Vec<u8> {
+    let mut contents = Vec::new();
+    write_u32(
+        &mut contents,
+        u32::try_from(name.len()).expect("short name"),
+    );
+    contents.extend_from_slice(name.as_bytes());
+    contents.extend_from_slice(data);
+    let mut section = vec![CUSTOM_SECTION_ID];
+    write_u32(
+        &mut section,
+        u32::try_from(contents.len()).expect("section fits in a wasm module"),
+    );
+    section.extend(contents);
+    section
+}
+
+pub fn read(path: &Path) -> Result<Vec<u8>, Error> {
+    fs::read(path).map_err(|e| Error::Reading(path.to_path_buf(), e))
+}
+
+fn custom_section<'a>(path: &Path, wasm: &'a [u8], name: &str) -> Result<Option<&'a [u8]>, Error> {
+    Ok(sections(wasm)
+        .ok_or_else(|| Error::Malformed(path.to_path_buf()))?
+        .into_iter()
+        .find(|s| s.name == Some(name))
+        .map(|s| s.data))
+}
+
+/// Remove all custom sections which are not in `CONTRACT_SECTIONS`, such as `name` and `producers`
+pub fn strip(input: &Path, output: &Path) -> Result<(), Error> {
+    let wasm = read(input)?;
+    let mut stripped = HEADER.to_vec();
+    for section in sections(&wasm).ok_or_else(|| Error::Malformed(input.to_path_buf()))? {
+        if section.id != CUSTOM_SECTION_ID
+            || section
+                .name
+                .is_some_and(|name| CONTRACT_SECTIONS.contains(&name))
+        {
+            stripped.extend_from_slice(section.raw);
+        }
+    }
+    fs::write(output, stripped).map_err(|e| Error::Writing(output.to_path_buf(), e))
+}
+
+/// Append `entries` to the `contractmetav0` section, creating it if the contract has none
+pub fn add_meta(input: &Path, output: &Path, entries: &[(String, String)]) -> Result<(), Error> {
+    let wasm = read(input)?;
+    let mut data = Vec::new();
+    for (key, val) in entries {
+        let entry = ScMetaEntry::ScMetaV0(ScMetaV0 {
+            key: key.clone().try_into()?,
+            val: val.clone().try_into()?,
+        });
+        data.extend(entry.to_xdr(Limits::none())?);
+    }
+    let mut with_meta = HEADER.to_vec();
+    let mut added = false;
+    for section in sections(&wasm).ok_or_else(|| Error::Malformed(input.to_path_buf()))? {
+        if !added && section.name == Some(CONTRACT_META_SECTION) {
+            let mut meta = section.data.to_vec();
+            meta.extend_from_slice(&data);
+            with_meta.extend(custom_section_bytes(CONTRACT_META_SECTION, &meta));
+            added = true;
+        } else {
+            with_meta.extend_from_slice(section.raw);
+        }
+    }
+    if !added {
+        with_meta.extend(custom_section_bytes(CONTRACT_META_SECTION, &data));
+    }
+    fs::write(output, with_meta).map_err(|e| Error::Writing(output.to_path_buf(), e))
+}
+
+/// Entries of the `contractmetav0` section, e.g. those added by `add_meta`
+pub fn meta(path: &Path, wasm: &[u8]) -> Result<Vec<(String, String)>, Error> {
+    let Some(data) = custom_section(path, wasm, CONTRACT_META_SECTION)? else {
+        return Ok(Vec::new());
+    };
+    let mut limited = Limited::new(data, Limits::none());
+    ScMetaEntry::read_xdr_iter(&mut limited)
+        .map(|entry| {
+            let ScMetaEntry::ScMetaV0(ScMetaV0 { key, val }) = entry?;
+            Ok((key.to_utf8_string_lossy(), val.to_utf8_string_lossy()))
+        })
+        .collect()
+}
+
+/// Optimize for size, like `stellar contract optimize`
+#[cfg(feature = "opt")]
+pub fn optimize(input: &Path, output: &Path) -> Result<(), Error> {
+    use wasm_opt::{Feature, OptimizationOptions};
+    let mut options = OptimizationOptions::new_optimize_for_size_aggressively();
+    options.converge = true;
+    // Soroban supports the MVP feature set plus sign-ext and mutable-globals
+    options.mvp_features_only();
+    options.enable_feature(Feature::MutableGlobals);
+    options.enable_feature(Feature::SignExt);
+    options
+        .run(input, output)
+        .map_err(|e| Error::Optimize(input.to_path_buf(), e))
+}
+
+#[cfg(not(feature = "opt"))]
+pub fn optimize(_input: &Path, _output: &Path) -> Result<(), Error> {
+    Err(Error::Install)
+}
+
+/// Check that the contract sections of `original` made it unchanged into `processed`.
+///
+/// Entries may only be appended to `contractmetav0`, by `add_meta`.
+pub fn check_sections(name: &str, original: &Path, processed: &Path) -> Result<(), Error> {
+    let original_wasm = read(original)?;
+    let processed_wasm = read(processed)?;
+    if custom_section(original, &original_wasm, "contractenvmetav0")?.is_none() {
+        return Err(Error::MissingSection {
+            name: name.to_string(),
+            section: "contractenvmetav0".to_string(),
+        });
+    }
+    for section in CONTRACT_SECTIONS {
+        let before = custom_section(original, &original_wasm, section)?;
+        let after = custom_section(processed, &processed_wasm, section)?;
+        let preserved = match (before, after) {
+            (Some(before), Some(after)) if *section == CONTRACT_META_SECTION => {
+                after.starts_with(before)
+            }
+            (None, _) if *section == CONTRACT_META_SECTION => true,
+            _ => before == after,
+        };
+        if !preserved {
+            return Err(Error::SectionChanged {
+                name: name.to_string(),
+                section: (*section).to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Sizes of the contracts built by the last build, used to report how much each contract grew
+pub struct Sizes {
+    path: PathBuf,
+    previous: BTreeMap<String, u64>,
+    current: BTreeMap<String, u64>,
+}
+
+impl Sizes {
+    pub fn load(target_dir: &Path) -> Self {
+        let path = target_dir.join("loam").join(".sizes.json");
+        let previous = fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            previous,
+            current: BTreeMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, name: &str, wasm: &Path) -> Result<(), Error> {
+        let size = fs::metadata(wasm)
+            .map_err(|e| Error::Reading(wasm.to_path_buf(), e))?
+            .len();
+        self.current.insert(name.to_string(), size);
+        Ok(())
+    }
+
+    /// Fail if any contract recorded by this build is larger than `max` bytes
+    pub fn check_max(&self, max: u64) -> Result<(), Error> {
+        match self.current.iter().find(|(_, size)| **size > max) {
+            Some((name, size)) => Err(Error::TooLarge {
+                name: name.clone(),
+                size: *size,
+                max,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    pub fn report(&self) {
+        if self.current.is_empty() {
+            return;
+        }
+        let width = self.current.keys().map(String::len).max().unwrap_or(0);
+        eprintln!("📦 contract sizes:");
+        for (name, size) in &self.current {
+            let delta = self.delta(name, *size);
+            eprintln!("   {name:<width$}  {size:>7} bytes  {delta}");
+        }
+    }
+
+    /// How `size` compares to the size recorded for `name` by the last build
+    fn delta(&self, name: &str, size: u64) -> String {
+        match self.previous.get(name) {
+            Some(previous) if *previous == size => String::from("unchanged"),
+            #[allow(clippy::cast_possible_wrap)]
+            Some(previous) => format!("{:+} bytes", size as i64 - *previous as i64),
+            None => String::from("new"),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let mut sizes = self.previous.clone();
+        sizes.extend(self.current.clone());
+        let json = serde_json::to_string_pretty(&sizes).expect("sizes serialize to json");
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| Error::Writing(parent.to_path_buf(), e))?;
+        }
+        fs::write(&self.path, json).map_err(|e| Error::Writing(self.path.clone(), e))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_fs::TempDir;
+
+    /// A module with a type section, and a custom section for each of `custom`
+    fn module(custom: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut wasm = HEADER.to_vec();
+        // a type section declaring `() -> ()`
+        wasm.extend([1, 4, 1, 0x60, 0, 0]);
+        for (name, data) in custom {
+            wasm.extend(custom_section_bytes(name, data));
+        }
+        wasm
+    }
+
+    fn write(dir: &TempDir, name: &str, wasm: &[u8]) -> PathBuf {
+        let path = dir.path().join(name);
+        fs::write(&path, wasm).unwrap();
+        path
+    }
+
+    fn section_names(wasm: &[u8]) -> Vec<Option<&str>> {
+        sections(wasm).unwrap().iter().map(|s| s.name).collect()
+    }
+
+    #[test]
+    fn strip_keeps_only_contract_sections() {
+        let dir = TempDir::new().unwrap();
+        let input = write(
+            &dir,
+            "in.wasm",
+            &module(&[
+                ("name", b"names"),
+                ("contractspecv0", b"spec"),
+                ("producers", b"rustc"),
+                ("contractenvmetav0", b"env"),
+            ]),
+        );
+        let output = dir.path().join("out.wasm");
+        strip(&input, &output).unwrap();
+        let stripped = read(&output).unwrap();
+        assert_eq!(
+            section_names(&stripped),
+            [None, Some("contractspecv0"), Some("contractenvmetav0")]
+        );
+        check_sections("contract", &input, &output).unwrap();
+    }
+
+    #[test]
+    fn strip_rejects_what_is_not_wasm() {
+        let dir = TempDir::new().unwrap();
+        let input = write(&dir, "in.wasm", b"not wasm");
+        assert!(matches!(
+            strip(&input, &dir.path().join("out.wasm")),
+            Err(Error::Malformed(path)) if path == input
+        ));
+    }
+
+    #[test]
+    fn meta_round_trips_through_add_meta() {
+        let dir = TempDir::new().unwrap();
+        let entries = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+                .collect::<Vec<_>>()
+        };
+        let input = write(&dir, "in.wasm", &module(&[("contractenvmetav0", b"env")]));
+        let first = dir.path().join("first.wasm");
+        add_meta(&input, &first, &entries(&[("source_repo", "loam")])).unwrap();
+        let second = dir.path().join("second.wasm");
+        add_meta(&first, &second, &entries(&[("git_commit", "abc123")])).unwrap();
+        let wasm = read(&second).unwrap();
+        assert_eq!(
+            meta(&second, &wasm).unwrap(),
+            entries(&[("source_repo", "loam"), ("git_commit", "abc123")])
+        );
+        assert_eq!(
+            section_names(&wasm),
+            [None, Some("contractenvmetav0"), Some(CONTRACT_META_SECTION)]
+        );
+        check_sections("contract", &input, &second).unwrap();
+        check_sections("contract", &first, &second).unwrap();
+    }
+
+    #[test]
+    fn check_sections_reports_missing_and_changed_sections() {
+        let dir = TempDir::new().unwrap();
+        let original = write(
+            &dir,
+            "original.wasm",
+            &module(&[("contractenvmetav0", b"env"), ("contractspecv0", b"spec")]),
+        );
+        let changed = write(
+            &dir,
+            "changed.wasm",
+            &module(&[("contractenvmetav0", b"env"), ("contractspecv0", b"other")]),
+        );
+        assert!(matches!(
+            check_sections("contract", &original, &changed),
+            Err(Error::SectionChanged { section, .. }) if section == "contractspecv0"
+        ));
+        let dropped = write(
+            &dir,
+            "dropped.wasm",
+            &module(&[("contractenvmetav0", b"env")]),
+        );
+        assert!(matches!(
+            check_sections("contract", &original, &dropped),
+            Err(Error::SectionChanged { section, .. }) if section == "contractspecv0"
+        ));
+        let not_a_contract = write(&dir, "plain.wasm", &module(&[]));
+        assert!(matches!(
+            check_sections("plain", &not_a_contract, &not_a_contract),
+            Err(Error::MissingSection { name, section })
+                if name == "plain" && section == "contractenvmetav0"
+        ));
+    }
+
+    #[test]
+    fn sizes_are_compared_with_the_last_build() {
+        let dir = TempDir::new().unwrap();
+        let target_dir = dir.path().join("target");
+        let small = write(&dir, "small.wasm", &[0; 10]);
+        let large = write(&dir, "large.wasm", &[0; 100]);
+
+        let mut sizes = Sizes::load(&target_dir);
+        sizes.record("a", &small).unwrap();
+        sizes.record("b", &large).unwrap();
+        assert_eq!(sizes.delta("a", 10), "new");
+        sizes.save().unwrap();
+
+        let mut sizes = Sizes::load(&target_dir);
+        sizes.record("a", &large).unwrap();
+        assert_eq!(sizes.delta("a", 100), "+90 bytes");
+        assert_eq!(sizes.delta("b", 10), "-90 bytes");
+        assert_eq!(sizes.delta("b", 100), "unchanged");
+        sizes.check_max(100).unwrap();
+        assert!(matches!(
+            sizes.check_max(99),
+            Err(Error::TooLarge { name, size: 100, max: 99 }) if name == "a"
+        ));
+        sizes.save().unwrap();
+        assert_eq!(Sizes::load(&target_dir).previous.get("b"), Some(&100));
+    }
+}
@@ -0,0 +1,69 @@
+use clap::Parser;
+use std::ffi::OsString;
+
+use crate::registry::{self, Registry};
+
+/// Deploy a contract from a package published to a registry contract, under a unique name
+///
+/// The registry deploys the contract and invokes its initialization function in a single
+/// transaction, and the contract's id is saved as a local alias, so that it can be used like
+/// `stellar contract invoke --id <name>`.
+#[derive(Parser, Debug, Clone)]
+pub struct Cmd {
+    /// Package to deploy, as `<name>@<version>`, or `<name>` for its latest version
+    pub package: String,
+    /// Name to register the contract under, which is also used as its local alias
+    #[arg(long)]
+    pub name: String,
+    #[command(flatten)]
+    pub registry: registry::Args,
+    /// Initialization function and its arguments, as given to `stellar contract invoke`, e.g.
+    /// `loam deploy counter@1.0.0 --name my-counter -- init --admin alice`
+    #[arg(last = true, id = "INIT_FN_AND_ARGS")]
+    pub init: Vec<OsString>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("⛔ ️{0} has not been published to the registry")]
+    NotPublished(String),
+    #[error(transparent)]
+    Registry(#[from] registry::Error),
+}
+
+impl Cmd {
+    pub async fn run(&self) -> Result<(), Error> {
+        let (wasm_name, version) = match self.package.split_once('@') {
+            Some((wasm_name, version)) => (wasm_name, Some(version)),
+            None => (self.package.as_str(), None),
+        };
+        let hash = self
+            .registry
+            .published_hash(wasm_name, version)
+            .await?
+            .ok_or_else(|| Error::NotPublished(self.package.clone()))?;
+
+        let init = if self.init.is_empty() {
+            None
+        } else {
+            Some(self.registry.parse_invocation(&hash, &self.init).await?)
+        };
+
+        eprintln!("🪞 deploying {} as {:?}", self.package, self.name);
+        eprintln!("    ↳ hash: {hash}");
+        if let Some(init) = &init {
+            eprintln!(
+                "    ↳ initializing with {}",
+                init.function_name.to_utf8_string_lossy()
+            );
+        }
+        let contract_id = self
+            .registry
+            .deploy_contract(&self.name, wasm_name, version, init.as_ref())
+            .await?;
+        eprintln!("    ↳ contract_id: {contract_id}");
+        self.registry.save_alias(&self.name, &contract_id)?;
+        eprintln!("✅ deployed {:?}", self.name);
+        Ok(())
+    }
+}
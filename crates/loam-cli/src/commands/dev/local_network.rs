@@ -0,0 +1,311 @@
+use soroban_cli::{commands as cli, CommandParser};
+use std::time::Duration;
+use tokio::{process::Child, time};
+
+use crate::commands::build::{
+    clients,
+    env_toml::{Account, Fund, Network},
+};
+
+/// Name of the container started for `run-locally` networks, `stellar-local` in docker
+const CONTAINER_NAME: &str = "local";
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("⛔ ️a network which runs locally needs an rpc-url in environments.toml")]
+    NoRpcUrl,
+    #[error("⛔ ️starting the local network container: {0}")]
+    Container(String),
+    #[error("⛔ ️running {0:?} to start the local network: {1}")]
+    Command(String, std::io::Error),
+    #[error("⛔ ️the local network at {0} is still not healthy after {1:?}")]
+    Unhealthy(String, Duration),
+    #[error(transparent)]
+    Clients(#[from] clients::Error),
+    #[error(transparent)]
+    Clap(#[from] clap::Error),
+}
+
+/// How to start a node for a network with `run-locally = true`
+#[derive(Debug, Clone)]
+pub enum Launcher {
+    /// Run the `stellar/quickstart` image in a container, with the port of the network's rpc-url
+    /// mapped to it
+    Container,
+    /// Run a shell command, which keeps running for as long as the node does
+    Command(String),
+}
+
+/// A node run for the duration of `loam dev`
+pub struct LocalNetwork {
+    launcher: Launcher,
+    /// The process running the node, for [`Launcher::Command`]
+    child: Option<Child>,
+    /// Whether the node was started by us, rather than already running
+    started: bool,
+}
+
+impl LocalNetwork {
+    /// Start a node for `network` unless one is already answering at its rpc-url, and wait until
+    /// it is healthy
+    pub async fn start(
+        network: &Network,
+        launcher: Launcher,
+        timeout: Duration,
+    ) -> Result<Self, Error> {
+        let rpc_url = network.rpc_url.as_deref().ok_or(Error::NoRpcUrl)?;
+        let mut local = LocalNetwork {
+            launcher,
+            child: None,
+            started: false,
+        };
+        if is_healthy(network).await {
+            eprintln!("🌐 local network at {rpc_url} is already running");
+            return Ok(local);
+        }
+        eprintln!("🚀 starting local network for {rpc_url}");
+        match &local.launcher {
+            Launcher::Container => {
+                let ports = format!("{}:8000", port(rpc_url));
+                cli::network::container::StartCmd::parse_arg_vec(&[
+                    CONTAINER_NAME,
+                    "--ports-mapping",
+                    &ports,
+                ])?
+                .run(&cli::global::Args::default())
+                .await
+                .map_err(|e| Error::Container(e.to_string()))?;
+            }
+            Launcher::Command(command) => {
+                let child = tokio::process::Command::new("sh")
+                    .args(["-c", command])
+                    .kill_on_drop(true)
+                    .spawn()
+                    .map_err(|e| Error::Command(command.clone(), e))?;
+                local.child = Some(child);
+            }
+        }
+        local.started = true;
+
+        let start = time::Instant::now();
+        while !is_healthy(network).await {
+            if start.elapsed() > timeout {
+                local.stop().await;
+                return Err(Error::Unhealthy(rpc_url.to_string(), timeout));
+            }
+            time::sleep(Duration::from_secs(1)).await;
+        }
+        eprintln!("✅ local network is up");
+        Ok(local)
+    }
+
+    /// Fund the accounts which already have keys, since a freshly started node knows nothing of
+    /// them. The node's friendbot may take a little longer than its rpc to be ready, so each
+    /// account is retried until `timeout`. Accounts without keys are created and funded by
+    /// `loam build`.
+    pub async fn fund_accounts(
+        &self,
+        network: &Network,
+        accounts: &[Account],
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        if !self.started {
+            return Ok(());
+        }
+        clients::Args::add_network_to_env(network)?;
+        let locator = soroban_cli::config::locator::Args::default();
+        for account in accounts {
+            // accounts funded with an amount are created by `loam build`
+            if account.fund != Fund::Friendbot(true)
+                || locator.read_identity(&account.name).is_err()
+            {
+                continue;
+            }
+            eprintln!("💰 funding {:?} on the local network", account.name);
+            let start = time::Instant::now();
+            while let Err(e) = cli::keys::fund::Cmd::parse_arg_vec(&[&account.name])?
+                .run()
+                .await
+            {
+                if start.elapsed() > timeout {
+                    eprintln!("⚠️ could not fund {:?}: {e}", account.name);
+                    break;
+                }
+                time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Stop the node, if it was started by us
+    pub async fn stop(&mut self) {
+        if !self.started {
+            return;
+        }
+        eprintln!("🛑 stopping local network");
+        match &self.launcher {
+            Launcher::Container => {
+                let stopped =
+                    match cli::network::container::StopCmd::parse_arg_vec(&[CONTAINER_NAME]) {
+                        Ok(cmd) => cmd
+                            .run(&cli::global::Args::default())
+                            .await
+                            .map_err(|e| e.to_string()),
+                        Err(e) => Err(e.to_string()),
+                    };
+                if let Err(e) = stopped {
+                    eprintln!("⚠️ could not stop the local network container: {e}");
+                }
+            }
+            Launcher::Command(_) => {
+                if let Some(mut child) = self.child.take() {
+                    if let Err(e) = child.kill().await {
+                        eprintln!("⚠️ could not stop the local network: {e}");
+                    }
+                }
+            }
+        }
+        self.started = false;
+    }
+}
+
+/// Whether the rpc at the network's url answers, for the network's passphrase if it has one
+async fn is_healthy(network: &Network) -> bool {
+    let Some(rpc_url) = &network.rpc_url else {
+        return false;
+    };
+    let Ok(client) = soroban_rpc::Client::new(rpc_url) else {
+        return false;
+    };
+    client.get_network().await.is_ok_and(|response| {
+        network
+            .network_passphrase
+            .as_ref()
+            .map_or(true, |passphrase| &response.passphrase == passphrase)
+    })
+}
+
+/// Port of an rpc url, which the container's port is mapped to
+fn port(rpc_url: &str) -> u16 {
+    let authority = rpc_url
+        .split_once("://")
+        .map_or(rpc_url, |(_, rest)| rest)
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    authority
+        .rsplit_once(':')
+        .and_then(|(_, port)| port.parse().ok())
+        .unwrap_or(if rpc_url.starts_with("https") {
+            443
+        } else {
+            80
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const PASSPHRASE: &str = "Standalone Network ; February 2017";
+
+    /// Answer every request like an rpc's `getNetwork` would
+    async fn stub_rpc(listener: TcpListener) {
+        loop {
+            let Ok((mut stream, _)) = listener.accept().await else {
+                return;
+            };
+            tokio::spawn(async move {
+                let mut buf = vec![0; 4096];
+                let n = stream.read(&mut buf).await.unwrap_or_default();
+                let request = String::from_utf8_lossy(&buf[..n]);
+                let id = request
+                    .split("\r\n\r\n")
+                    .nth(1)
+                    .and_then(|body| serde_json::from_str::<serde_json::Value>(body).ok())
+                    .map(|body| body["id"].clone())
+                    .unwrap_or_default();
+                let body = serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "result": { "passphrase": PASSPHRASE, "protocolVersion": 21 },
+                })
+                .to_string();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    }
+
+    fn network(port: u16) -> Network {
+        Network {
+            rpc_url: Some(format!("http://127.0.0.1:{port}/rpc")),
+            network_passphrase: Some(PASSPHRASE.to_string()),
+            run_locally: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ports() {
+        assert_eq!(port("http://localhost:8000/rpc"), 8000);
+        assert_eq!(port("http://localhost:8080"), 8080);
+        assert_eq!(port("http://localhost/soroban/rpc"), 80);
+        assert_eq!(port("https://rpc.example.com"), 443);
+    }
+
+    #[tokio::test]
+    async fn reuses_a_running_network() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let network = network(listener.local_addr().unwrap().port());
+        tokio::spawn(stub_rpc(listener));
+
+        let mut local = LocalNetwork::start(&network, Launcher::Container, Duration::ZERO)
+            .await
+            .unwrap();
+        // nothing was started, so nothing is stopped
+        assert!(!local.started);
+        local.stop().await;
+    }
+
+    #[tokio::test]
+    async fn starts_and_stops_a_network() {
+        // the stub rpc only answers once the launcher has run
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let network = network(listener.local_addr().unwrap().port());
+        drop(listener);
+        let port = network.rpc_url.as_deref().map(port).unwrap();
+        let stub = tokio::spawn(async move {
+            time::sleep(Duration::from_secs(2)).await;
+            stub_rpc(TcpListener::bind(("127.0.0.1", port)).await.unwrap()).await;
+        });
+
+        let launcher = Launcher::Command("sleep 60".to_string());
+        let mut local = LocalNetwork::start(&network, launcher, Duration::from_secs(30))
+            .await
+            .unwrap();
+        assert!(local.started);
+        let mut child = local.child.take().unwrap();
+        assert!(child.try_wait().unwrap().is_none());
+        local.child = Some(child);
+        local.stop().await;
+        assert!(!local.started && local.child.is_none());
+        stub.abort();
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_an_unhealthy_network() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let network = network(listener.local_addr().unwrap().port());
+        drop(listener);
+
+        let launcher = Launcher::Command("sleep 60".to_string());
+        let result = LocalNetwork::start(&network, launcher, Duration::from_secs(1)).await;
+        assert!(matches!(result, Err(Error::Unhealthy(_, _))));
+    }
+}
@@ -5,6 +5,7 @@ use std::{
     env, fs,
     path::{Path, PathBuf},
     sync::Arc,
+    time::Duration,
 };
 use tokio::sync::mpsc;
 use tokio::sync::Mutex;
@@ -12,8 +13,11 @@ use tokio::time;
 
 use crate::commands::build;
 
-use super::build::clients::LoamEnv;
-use super::build::env_toml::ENV_FILE;
+use super::build::env_toml::{self, ENV_FILE};
+
+pub mod local_network;
+
+use local_network::{Launcher, LocalNetwork};
 
 pub enum Message {
     FileChanged,
@@ -24,6 +28,13 @@ pub enum Message {
 pub struct Cmd {
     #[command(flatten)]
     pub build_cmd: build::Cmd,
+    /// Shell command which runs a node for a network with `run-locally = true`, instead of the
+    /// `stellar/quickstart` container. It should keep running until it is killed.
+    #[arg(long, env = "LOAM_LOCAL_NETWORK_COMMAND")]
+    pub local_network_command: Option<String>,
+    /// Seconds to wait for a local network to be healthy, and for its accounts to be funded
+    #[arg(long, default_value_t = 120)]
+    pub local_network_timeout: u64,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -34,6 +45,10 @@ pub enum Error {
     Build(#[from] build::Error),
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
+    #[error(transparent)]
+    EnvToml(#[from] env_toml::Error),
+    #[error(transparent)]
+    LocalNetwork(#[from] local_network::Error),
 }
 
 fn canonicalize_path(path: &Path) -> PathBuf {
@@ -117,7 +132,7 @@ impl Watcher {
         ) {
             if let Some(path) = event.paths.first() {
                 if self.is_watched(path) {
-                    eprintln!("File changed: {path:?}");
+                    eprintln!("File changed: {}", path.display());
                     if let Err(e) = tx.blocking_send(Message::FileChanged) {
                         eprintln!("Error sending through channel: {e:?}");
                     }
@@ -168,6 +183,7 @@ impl Cmd {
         }
 
         let build_command = self.cloned_build_command();
+        let mut local_network = self.start_local_network(&build_command).await?;
         if let Err(e) = build_command.run().await {
             eprintln!("Build error: {e}");
         }
@@ -190,9 +206,58 @@ impl Cmd {
                 }
             }
         }
+        if let Some(local_network) = &mut local_network {
+            local_network.stop().await;
+        }
         Ok(())
     }
 
+    /// Start a node for the environment's network if it has `run-locally = true`, funding the
+    /// environment's accounts on it
+    async fn start_local_network(
+        &self,
+        build_command: &build::Cmd,
+    ) -> Result<Option<LocalNetwork>, Error> {
+        if !build_command.build_clients {
+            return Ok(None);
+        }
+        let workspace_root = build_command
+            .manifest_path
+            .parent()
+            .unwrap_or_else(|| Path::new("."));
+        let loam_env = build_command
+            .build_clients_args
+            .env
+            .as_deref()
+            .unwrap_or(env_toml::DEFAULT_DEV_ENV);
+        let Some(environment) = env_toml::Environment::get(workspace_root, loam_env)? else {
+            return Ok(None);
+        };
+        if !environment.network.run_locally {
+            return Ok(None);
+        }
+        let launcher = self
+            .local_network_command
+            .clone()
+            .map_or(Launcher::Container, Launcher::Command);
+        let timeout = Duration::from_secs(self.local_network_timeout);
+        let mut local_network =
+            LocalNetwork::start(&environment.network, launcher, timeout).await?;
+        let funded = local_network
+            .fund_accounts(
+                &environment.network,
+                environment.accounts.as_deref().unwrap_or_default(),
+                timeout,
+            )
+            .await;
+        if let Err(e) = funded {
+            // don't leave the node running when `loam dev` can't go on
+            local_network.stop().await;
+            return Err(e.into());
+        }
+        Ok(Some(local_network))
+    }
+
     async fn debounced_rebuild(build_command: Arc<build::Cmd>, rebuild_state: Arc<Mutex<bool>>) {
         // Debounce to avoid multiple rapid rebuilds
         time::sleep(std::time::Duration::from_secs(1)).await;
@@ -211,7 +276,7 @@ impl Cmd {
         self.build_cmd
             .build_clients_args
             .env
-            .get_or_insert(LoamEnv::Development);
+            .get_or_insert_with(|| env_toml::DEFAULT_DEV_ENV.to_string());
         Arc::new(self.build_cmd.clone())
     }
 }
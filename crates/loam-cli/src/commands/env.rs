@@ -0,0 +1,270 @@
+use clap::Parser;
+use soroban_cli::config::locator;
+use soroban_cli::CommandParser;
+use std::path::PathBuf;
+
+use crate::commands::build::{
+    self,
+    env_toml::{self, unknown, Environment, Network, Problem},
+    init_script,
+};
+
+/// JSON schema of `environments.toml`
+pub const SCHEMA: &str = include_str!("../../environments.schema.json");
+
+/// Check `environments.toml`, or print its JSON schema
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum Cmd {
+    /// Check the environments, accounts, networks and contracts of `environments.toml` against the
+    /// workspace, and the inits of built contracts against their specs, without using the network
+    Check(Check),
+    /// Print the JSON schema of `environments.toml`, for completion in editors. With the Even
+    /// Better TOML extension, refer to it from a `#:schema` comment at the top of the file.
+    Schema,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct Check {
+    /// Path to Cargo.toml, whose workspace root contains `environments.toml`
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: PathBuf,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("⛔ ️no environments.toml found in {0:?}")]
+    NoEnvironmentsToml(PathBuf),
+    #[error(transparent)]
+    EnvToml(#[from] env_toml::Error),
+    #[error(transparent)]
+    Build(#[from] build::Error),
+    #[error(transparent)]
+    Clap(#[from] clap::Error),
+    #[error(transparent)]
+    Locator(#[from] locator::Error),
+    #[error(transparent)]
+    Metadata(#[from] cargo_metadata::Error),
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result<(), Error> {
+        match self {
+            Cmd::Check(check) => check.run(),
+            Cmd::Schema => {
+                print!("{SCHEMA}");
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Check {
+    pub fn run(&self) -> Result<(), Error> {
+        let manifest_path = self
+            .manifest_path
+            .to_str()
+            .expect("we do not support non-utf8 paths");
+        let metadata = cargo_metadata::MetadataCommand::new()
+            .manifest_path(&self.manifest_path)
+            .no_deps()
+            .exec()?;
+        let workspace_root = metadata.workspace_root.into_std_path_buf();
+        let packages = build::Cmd::parse_arg_vec(&["--manifest-path", manifest_path])?
+            .list_packages()?
+            .into_iter()
+            .map(|package| package.name.replace('-', "_"))
+            .collect::<Vec<_>>();
+        let locator = locator::Args {
+            global: false,
+            config_dir: Some(workspace_root.clone()),
+        };
+        let networks = locator.list_networks()?;
+        let identities = locator.list_identities()?;
+
+        let environments = Environment::all(&workspace_root)?
+            .ok_or_else(|| Error::NoEnvironmentsToml(workspace_root.clone()))?;
+        let mut problems = Vec::new();
+        for (name, environment) in &environments {
+            match environment {
+                Ok(environment) => {
+                    let checker = Checker {
+                        workspace_root: &workspace_root,
+                        packages: &packages,
+                        networks: &networks,
+                        identities: &identities,
+                    };
+                    checker.environment(name, environment, &mut problems);
+                }
+                Err(environment_problems) => problems.extend(environment_problems.iter().cloned()),
+            }
+        }
+        if !problems.is_empty() {
+            return Err(env_toml::Error::Invalid(problems).into());
+        }
+        eprintln!(
+            "✅ environments.toml is valid, with {} environment{}",
+            environments.len(),
+            if environments.len() == 1 { "" } else { "s" }
+        );
+        Ok(())
+    }
+}
+
+/// Checks an environment against the workspace's contracts and the stellar CLI's networks and
+/// identities
+struct Checker<'a> {
+    workspace_root: &'a std::path::Path,
+    packages: &'a [String],
+    networks: &'a [String],
+    identities: &'a [String],
+}
+
+impl Checker<'_> {
+    fn environment(&self, name: &str, environment: &Environment, problems: &mut Vec<Problem>) {
+        match environment.accounts.as_deref() {
+            None | Some([]) => problems.push(Problem::new(
+                format!("{name}.accounts"),
+                "needs at least one account",
+            )),
+            Some(accounts) => {
+                for (i, account) in accounts.iter().enumerate() {
+                    problems.extend(account.problems(&format!("{name}.accounts[{i}]")));
+                }
+                let defaults = accounts.iter().filter(|account| account.default).count();
+                if defaults > 1 {
+                    problems.push(Problem::new(
+                        format!("{name}.accounts"),
+                        format!("only one account can be the default, not {defaults}"),
+                    ));
+                }
+            }
+        }
+        self.network(&format!("{name}.network"), &environment.network, problems);
+
+        let packages = self.packages.iter().map(String::as_str).collect::<Vec<_>>();
+        for (contract_name, contract) in environment.contracts.iter().flatten() {
+            let path = format!("{name}.contracts.{contract_name}");
+            match &contract.id {
+                Some(id) => {
+                    if stellar_strkey::Contract::from_string(id).is_err() {
+                        problems.push(Problem::new(&path, format!("`{id}` is not a contract id")));
+                    }
+                }
+                None if environment.requires_ids() => {
+                    problems.push(Problem::new(&path, format!("needs an `id` in {name}")));
+                }
+                None => {
+                    if !packages.contains(&contract_name.as_ref()) {
+                        problems.push(Problem::new(
+                            &path,
+                            unknown("contract", contract_name, &packages),
+                        ));
+                    }
+                }
+            }
+            if let Some(network) = &contract.network {
+                self.network(&format!("{path}.network"), network, problems);
+            }
+        }
+        problems.extend(environment.signer_problems());
+        problems.extend(environment.registry_problems());
+        problems.extend(init_script::check_environment(
+            self.workspace_root,
+            environment,
+            self.identities,
+        ));
+    }
+
+    fn network(&self, path: &str, network: &Network, problems: &mut Vec<Problem>) {
+        match network {
+            Network {
+                rpc_url: Some(_),
+                network_passphrase: Some(_),
+                ..
+            } => {}
+            Network {
+                rpc_url: Some(_),
+                network_passphrase: None,
+                ..
+            } => problems.push(Problem::new(
+                path,
+                "has an rpc-url but no network-passphrase",
+            )),
+            Network {
+                rpc_url: None,
+                network_passphrase: Some(_),
+                ..
+            } => problems.push(Problem::new(
+                path,
+                "has a network-passphrase but no rpc-url",
+            )),
+            Network {
+                run_locally: true, ..
+            } => problems.push(Problem::new(path, "runs locally, so it needs an rpc-url")),
+            Network {
+                name: Some(network_name),
+                ..
+            } => {
+                let networks = self.networks.iter().map(String::as_str).collect::<Vec<_>>();
+                if !networks.contains(&network_name.as_str()) {
+                    problems.push(Problem::new(
+                        path,
+                        unknown("network", network_name, &networks),
+                    ));
+                }
+            }
+            Network { name: None, .. } => problems.push(Problem::new(
+                path,
+                "needs a name, or an rpc-url and network-passphrase",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use itertools::Itertools;
+
+    /// Keys of an object of the schema
+    fn properties(pointer: &str) -> Vec<String> {
+        let schema: serde_json::Value = serde_json::from_str(SCHEMA).unwrap();
+        let mut keys = schema
+            .pointer(pointer)
+            .and_then(serde_json::Value::as_object)
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys
+    }
+
+    fn sorted(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(ToString::to_string).sorted().collect()
+    }
+
+    #[test]
+    fn schema_has_every_key() {
+        assert_eq!(
+            properties("/definitions/environment/properties"),
+            sorted(env_toml::ENVIRONMENT_KEYS)
+        );
+        assert_eq!(
+            properties("/definitions/networkDefinition/properties"),
+            sorted(env_toml::NETWORK_KEYS)
+        );
+        assert_eq!(
+            properties("/definitions/account/oneOf/1/properties"),
+            sorted(env_toml::ACCOUNT_KEYS)
+        );
+        assert_eq!(
+            properties("/definitions/contract/properties"),
+            sorted(env_toml::CONTRACT_KEYS)
+        );
+        assert_eq!(
+            properties("/definitions/initStep/properties"),
+            sorted(env_toml::INIT_STEP_KEYS)
+        );
+    }
+}
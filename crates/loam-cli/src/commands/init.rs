@@ -30,13 +30,17 @@ pub enum Error {
     #[error("Io error: {0}")]
     IoError(#[from] io::Error),
     #[error("Soroban init error: {0}")]
-    SorobanInitError(#[from] soroban_init::Error),
+    SorobanInitError(Box<soroban_init::Error>),
     #[error("Failed to convert bytes to string: {0}")]
     ConverBytesToStringErr(#[from] std::str::Utf8Error),
     #[error("Failed to parse toml file: {0}")]
     TomlParseError(#[from] TomlError),
 }
 
+impl_from_boxed!(Error {
+    SorobanInitError(soroban_init::Error),
+});
+
 impl Cmd {
     /// Run the initialization command by calling the soroban init command
     ///
@@ -76,7 +80,7 @@ impl Cmd {
 // update a soroban project to a loam project
 fn update_workspace_cargo_toml(cargo_path: &Path) -> Result<(), Error> {
     let cargo_toml_str = read_to_string(cargo_path).map_err(|e| {
-        eprintln!("Error reading Cargo.toml file in: {cargo_path:?}");
+        eprintln!("Error reading Cargo.toml file in: {}", cargo_path.display());
         e
     })?;
 
@@ -89,12 +93,15 @@ loam-subcontract-core = "0.7.5""#,
         );
 
     let doc = cargo_toml_str.parse::<DocumentMut>().map_err(|e| {
-        eprintln!("Error parsing Cargo.toml file in: {cargo_path:?}");
+        eprintln!("Error parsing Cargo.toml file in: {}", cargo_path.display());
         e
     })?;
 
     write(cargo_path, doc.to_string()).map_err(|e| {
-        eprintln!("Error writing to Cargo.toml file in: {cargo_path:?}");
+        eprintln!(
+            "Error writing to Cargo.toml file in: {}",
+            cargo_path.display()
+        );
         e
     })?;
 
@@ -134,7 +141,7 @@ fn copy_file(
         return Ok(());
     }
     create_dir_all(to.parent().expect("invalid path")).map_err(|e| {
-        eprintln!("Error creating directory path for: {to:?}");
+        eprintln!("Error creating directory path for: {}", to.display());
         e
     })?;
 
@@ -144,13 +151,13 @@ fn copy_file(
     };
 
     let file_contents = std::str::from_utf8(embedded_file.data.as_ref()).map_err(|e| {
-        eprintln!("Error converting file contents in {filename:?} to string",);
+        eprintln!("Error converting file contents in {filename:?} to string");
         e
     })?;
 
     println!("➕  Writing {}", &to.to_string_lossy());
     write(&to, file_contents).map_err(|e| {
-        eprintln!("Error writing file: {to:?}");
+        eprintln!("Error writing file: {}", to.display());
         e
     })?;
     Ok(())
@@ -158,16 +165,13 @@ fn copy_file(
 
 // TODO: import from stellar-cli init (not currently pub there)
 fn file_exists(file_path: &Path) -> bool {
-    metadata(file_path)
-        .as_ref()
-        .map(Metadata::is_file)
-        .unwrap_or(false)
+    metadata(file_path).as_ref().is_ok_and(Metadata::is_file)
 }
 
 fn rename_cargo_toml_remove(project: &Path, name: &str) -> Result<(), Error> {
     let from = project.join(format!("contracts/{name}/Cargo.toml.remove"));
     let to = from.with_extension("");
-    println!("Renaming to {from:?} to {to:?}");
+    println!("Renaming to {} to {}", from.display(), to.display());
     fs::rename(from, to)?;
     Ok(())
 }
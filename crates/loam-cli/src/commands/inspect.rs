@@ -0,0 +1,101 @@
+use clap::Parser;
+use soroban_cli::commands::{contract::fetch, NetworkRunnable};
+use std::path::{Path, PathBuf};
+
+use crate::commands::build::wasm;
+
+/// Show the metadata embedded in a contract's `contractmetav0` section, such as the crate,
+/// version and git commit it was built from by `loam build`
+#[derive(Parser, Debug, Clone)]
+pub struct Cmd {
+    /// Path to a wasm file, or the name of a contract built into `target/loam`
+    #[arg(required_unless_present = "id")]
+    pub wasm: Option<String>,
+    /// Id of a deployed contract to fetch the wasm of, instead of reading a local file
+    #[arg(long, conflicts_with = "wasm")]
+    pub id: Option<String>,
+    /// Path to Cargo.toml, used to find `target/loam`
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: PathBuf,
+    /// Print the metadata as JSON
+    #[arg(long)]
+    pub json: bool,
+    /// RPC server endpoint
+    #[arg(long, env = "STELLAR_RPC_URL", requires = "network_passphrase")]
+    pub rpc_url: Option<String>,
+    /// Network passphrase of the RPC server
+    #[arg(long, env = "STELLAR_NETWORK_PASSPHRASE", requires = "rpc_url")]
+    pub network_passphrase: Option<String>,
+    /// Name of network to use from config
+    #[arg(long, env = "STELLAR_NETWORK")]
+    pub network: Option<String>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Wasm(#[from] wasm::Error),
+    #[error(transparent)]
+    Fetch(Box<fetch::Error>),
+    #[error(transparent)]
+    Metadata(#[from] cargo_metadata::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+impl_from_boxed!(Error {
+    Fetch(fetch::Error),
+});
+
+impl Cmd {
+    pub async fn run(&self) -> Result<(), Error> {
+        let (path, bytes) = if let Some(id) = &self.id {
+            let bytes = fetch::Cmd {
+                contract_id: id.clone(),
+                out_file: None,
+                locator: soroban_cli::config::locator::Args {
+                    global: false,
+                    config_dir: None,
+                },
+                network: soroban_cli::config::network::Args {
+                    rpc_url: self.rpc_url.clone(),
+                    network_passphrase: self.network_passphrase.clone(),
+                    network: self.network.clone(),
+                },
+            }
+            .run_against_rpc_server(None, None)
+            .await?;
+            (PathBuf::from(id), bytes)
+        } else {
+            let path = self.wasm_path()?;
+            let bytes = wasm::read(&path)?;
+            (path, bytes)
+        };
+
+        let entries = wasm::meta(&path, &bytes)?;
+        if self.json {
+            let map = entries
+                .into_iter()
+                .map(|(key, val)| (key, serde_json::Value::String(val)))
+                .collect::<serde_json::Map<_, _>>();
+            println!("{}", serde_json::to_string_pretty(&map)?);
+        } else {
+            let width = entries.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+            for (key, val) in entries {
+                println!("{key:<width$}  {val}");
+            }
+        }
+        Ok(())
+    }
+
+    /// The given path, or if no such file exists, the contract of that name in `target/loam`
+    fn wasm_path(&self) -> Result<PathBuf, Error> {
+        let wasm = self.wasm.as_deref().unwrap_or_default();
+        let path = Path::new(wasm);
+        if path.exists() {
+            return Ok(path.to_path_buf());
+        }
+        Ok(loam_build::get_target_dir(&self.manifest_path)?
+            .join(format!("{}.wasm", wasm.replace('-', "_"))))
+    }
+}
@@ -0,0 +1,74 @@
+use clap::Parser;
+use std::path::PathBuf;
+
+use crate::commands::build::{clients, env_toml};
+use crate::registry;
+
+/// Import a contract deployed through a registry contract, by the name it was deployed as
+///
+/// The contract's id is saved as a local alias, and a TypeScript client is generated for it in
+/// `packages/<name>` and `src/contracts/<name>.ts`, just as `loam build` does for the contracts
+/// listed in `environments.toml`.
+#[derive(Parser, Debug, Clone)]
+pub struct Cmd {
+    /// Name the contract was deployed as in the registry
+    pub name: String,
+    /// Local alias and package name to use, defaults to the contract's name in the registry
+    #[arg(long)]
+    pub alias: Option<String>,
+    /// Root of the frontend project, which contains `packages` and `src/contracts`
+    #[arg(long, default_value = ".")]
+    pub root: PathBuf,
+    /// Environment the generated client is for. Clients for environments with `allow-http`,
+    /// such as `development`, may use HTTP.
+    #[arg(long, env = "LOAM_ENV")]
+    pub env: Option<String>,
+    #[command(flatten)]
+    pub registry: registry::Args,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("⛔ ️no contract named {0:?} has been deployed through the registry")]
+    NotDeployed(String),
+    #[error(transparent)]
+    Registry(#[from] registry::Error),
+    #[error(transparent)]
+    Clients(#[from] clients::Error),
+    #[error(transparent)]
+    EnvToml(#[from] env_toml::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl Cmd {
+    pub async fn run(&self) -> Result<(), Error> {
+        let alias = self.alias.as_deref().unwrap_or(&self.name);
+        eprintln!("🔎 looking up {:?} in the registry", self.name);
+        let contract_id = self
+            .registry
+            .contract_id(&self.name)
+            .await?
+            .ok_or_else(|| Error::NotDeployed(self.name.clone()))?;
+        eprintln!("    ↳ contract_id: {contract_id}");
+        self.registry.save_alias(alias, &contract_id)?;
+
+        clients::Args::add_network_to_env(&self.registry.env_toml_network())?;
+        std::fs::create_dir_all(self.root.join("src/contracts"))?;
+        let env = self.env.as_deref().unwrap_or(env_toml::DEFAULT_ENV);
+        let environment =
+            env_toml::Environment::get(&self.root, env)?.unwrap_or_else(|| env_toml::Environment {
+                name: env.to_string(),
+                ..Default::default()
+            });
+        clients::Args::generate_contract_bindings(
+            &self.root,
+            alias,
+            &contract_id,
+            environment.allows_http(),
+        )
+        .await?;
+        eprintln!("✅ installed {alias:?}");
+        Ok(())
+    }
+}
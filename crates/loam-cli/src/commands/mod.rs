@@ -1,11 +1,18 @@
 use std::str::FromStr;
 
-use clap::{command, CommandFactory, FromArgMatches, Parser};
+use clap::{CommandFactory, FromArgMatches, Parser};
 
 pub mod build;
+pub mod deploy;
 pub mod dev;
+pub mod env;
 pub mod init;
+pub mod inspect;
+pub mod install;
+pub mod publish;
+pub mod ttl;
 pub mod update_env;
+pub mod verify;
 
 const ABOUT: &str = "Build contracts and generate front ends";
 
@@ -43,7 +50,14 @@ impl Root {
             Cmd::Build(build_info) => build_info.run().await?,
             Cmd::UpdateEnv(e) => e.run()?,
             Cmd::Dev(dev_info) => dev_info.run().await?,
-        };
+            Cmd::Inspect(inspect) => inspect.run().await?,
+            Cmd::Verify(verify) => verify.run().await?,
+            Cmd::Publish(publish) => publish.run().await?,
+            Cmd::Deploy(deploy) => deploy.run().await?,
+            Cmd::Install(install) => install.run().await?,
+            Cmd::Ttl(ttl) => ttl.run().await?,
+            Cmd::Env(env) => env.run()?,
+        }
         Ok(())
     }
 }
@@ -69,6 +83,29 @@ pub enum Cmd {
 
     /// Monitor contracts and environments.toml for changes and rebuild as needed
     Dev(dev::Cmd),
+
+    /// Show the build metadata embedded in a contract by `loam build`
+    Inspect(inspect::Cmd),
+
+    /// Rebuild contracts and check that one matches a given wasm file or hash
+    Verify(verify::Cmd),
+
+    /// Build a contract and publish its wasm to a registry contract under its crate name and version
+    Publish(publish::Cmd),
+
+    /// Deploy and initialize a contract from a published package, claiming a name for it in the registry contract
+    Deploy(deploy::Cmd),
+
+    /// Save a local alias for a contract deployed through a registry contract, and generate its frontend client
+    Install(install::Cmd),
+
+    /// Check and extend the time to live of the contracts in environments.toml
+    #[command(subcommand)]
+    Ttl(ttl::Cmd),
+
+    /// Check environments.toml against the workspace, or print its JSON schema
+    #[command(subcommand)]
+    Env(env::Cmd),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -82,4 +119,18 @@ pub enum Error {
     UpdateEnv(#[from] update_env::Error),
     #[error(transparent)]
     Dev(#[from] dev::Error),
+    #[error(transparent)]
+    Inspect(#[from] inspect::Error),
+    #[error(transparent)]
+    Verify(#[from] verify::Error),
+    #[error(transparent)]
+    Publish(#[from] publish::Error),
+    #[error(transparent)]
+    Deploy(#[from] deploy::Error),
+    #[error(transparent)]
+    Install(#[from] install::Error),
+    #[error(transparent)]
+    Ttl(#[from] ttl::Error),
+    #[error(transparent)]
+    Env(#[from] env::Error),
 }
@@ -0,0 +1,206 @@
+use clap::Parser;
+use std::path::{Path, PathBuf};
+
+use crate::commands::build::{self, manifest};
+use crate::registry::{self, Registry};
+
+/// Build a contract and publish it to a registry contract as `<crate name>@<crate version>`
+///
+/// The wasm is installed on the network first, so that the registry only records its hash. A
+/// version that has already been published can't be changed, so publishing it again only
+/// succeeds if the wasm is identical.
+#[derive(Parser, Debug, Clone)]
+pub struct Cmd {
+    /// Contract crate to build and publish
+    #[arg(long)]
+    pub package: String,
+    /// Path to Cargo.toml
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: PathBuf,
+    /// Source repository to record, defaults to `package.repository` in the crate's Cargo.toml
+    #[arg(long)]
+    pub repo: Option<String>,
+    #[command(flatten)]
+    pub registry: registry::Args,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("⛔ ️{name}@{version} is already published with hash {published}, which differs from the built wasm ({built}); bump the version in Cargo.toml to publish these changes")]
+    AlreadyPublished {
+        name: String,
+        version: String,
+        published: String,
+        built: String,
+    },
+    #[error("⛔ ️package {0:?} not found")]
+    PackageNotFound(String),
+    #[error("hashing {0:?}: {1}")]
+    Hashing(PathBuf, std::io::Error),
+    #[error(transparent)]
+    Build(#[from] build::Error),
+    #[error(transparent)]
+    Registry(#[from] registry::Error),
+    #[error(transparent)]
+    Metadata(#[from] cargo_metadata::Error),
+    #[error(transparent)]
+    Clap(#[from] clap::Error),
+}
+
+impl Cmd {
+    pub async fn run(&self) -> Result<(), Error> {
+        build::Cmd::try_parse_from([
+            "build".to_string(),
+            format!("--manifest-path={}", self.manifest_path.display()),
+            format!("--package={}", self.package),
+        ])?
+        .run()
+        .await?;
+
+        let metadata = cargo_metadata::MetadataCommand::new()
+            .manifest_path(&self.manifest_path)
+            .no_deps()
+            .exec()?;
+        let package = metadata
+            .packages
+            .iter()
+            .find(|p| p.name == self.package)
+            .ok_or_else(|| Error::PackageNotFound(self.package.clone()))?;
+        let name = &package.name;
+        let version = package.version.to_string();
+        let repo = self
+            .repo
+            .clone()
+            .or_else(|| package.repository.clone())
+            .unwrap_or_default();
+
+        let wasm_path = loam_build::get_target_dir(&self.manifest_path)?
+            .join(format!("{}.wasm", name.replace('-', "_")));
+        publish(&self.registry, name, &version, &repo, &wasm_path).await
+    }
+}
+
+/// Publish the wasm at `wasm_path` as `name@version`, unless that version is already published
+pub(crate) async fn publish(
+    registry: &impl Registry,
+    name: &str,
+    version: &str,
+    repo: &str,
+    wasm_path: &Path,
+) -> Result<(), Error> {
+    if is_published(registry, name, version, wasm_path).await? {
+        eprintln!("✅ {name}@{version} is already published");
+        return Ok(());
+    }
+
+    eprintln!("📲 installing {name:?} wasm bytecode on-chain...");
+    let hash = registry.install(wasm_path).await?;
+    eprintln!("    ↳ hash: {hash}");
+    eprintln!("📦 publishing {name}@{version}");
+    registry.publish(name, version, &hash, repo).await?;
+    eprintln!("✅ published {name}@{version}");
+    Ok(())
+}
+
+/// Whether `name@version` is already published with the wasm at `wasm_path`, failing if it is
+/// published with another wasm
+pub(crate) async fn is_published(
+    registry: &impl Registry,
+    name: &str,
+    version: &str,
+    wasm_path: &Path,
+) -> Result<bool, Error> {
+    let built =
+        manifest::sha256_file(wasm_path).map_err(|e| Error::Hashing(wasm_path.to_path_buf(), e))?;
+    match registry.published_hash(name, Some(version)).await? {
+        Some(published) if published == built => Ok(true),
+        Some(published) => Err(Error::AlreadyPublished {
+            name: name.to_string(),
+            version: version.to_string(),
+            published,
+            built,
+        }),
+        None => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_fs::TempDir;
+    use std::{cell::RefCell, collections::BTreeMap};
+
+    /// A registry kept in memory, which installs a wasm by hashing it like the network does
+    #[derive(Default)]
+    struct StubRegistry {
+        installed: RefCell<Vec<String>>,
+        published: RefCell<BTreeMap<(String, String), String>>,
+    }
+
+    impl Registry for StubRegistry {
+        async fn published_hash(
+            &self,
+            name: &str,
+            version: Option<&str>,
+        ) -> Result<Option<String>, registry::Error> {
+            let version = version.expect("publish fetches an exact version");
+            Ok(self
+                .published
+                .borrow()
+                .get(&(name.to_string(), version.to_string()))
+                .cloned())
+        }
+
+        async fn install(&self, wasm: &Path) -> Result<String, registry::Error> {
+            let hash = manifest::sha256_file(wasm).unwrap();
+            self.installed.borrow_mut().push(hash.clone());
+            Ok(hash)
+        }
+
+        async fn publish(
+            &self,
+            name: &str,
+            version: &str,
+            wasm_hash: &str,
+            _repo: &str,
+        ) -> Result<(), registry::Error> {
+            self.published.borrow_mut().insert(
+                (name.to_string(), version.to_string()),
+                wasm_hash.to_string(),
+            );
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn publish_refuses_to_change_a_published_version() {
+        let dir = TempDir::new().unwrap();
+        let wasm = dir.path().join("hello_world.wasm");
+        std::fs::write(&wasm, b"hello").unwrap();
+        let registry = StubRegistry::default();
+        let publish = |version| publish(&registry, "hello-world", version, "", &wasm);
+
+        publish("0.0.0").await.unwrap();
+        let hello = manifest::sha256_file(&wasm).unwrap();
+        assert_eq!(*registry.installed.borrow(), std::slice::from_ref(&hello));
+
+        // publishing the same wasm again is a no-op
+        publish("0.0.0").await.unwrap();
+        assert_eq!(registry.installed.borrow().len(), 1);
+
+        std::fs::write(&wasm, b"howdy").unwrap();
+        let howdy = manifest::sha256_file(&wasm).unwrap();
+        assert!(matches!(
+            publish("0.0.0").await,
+            Err(Error::AlreadyPublished { published, built, .. })
+                if published == hello && built == howdy
+        ));
+        assert_eq!(registry.installed.borrow().len(), 1);
+
+        publish("0.0.1").await.unwrap();
+        assert_eq!(
+            registry.published.borrow()[&("hello-world".to_string(), "0.0.1".to_string())],
+            howdy
+        );
+    }
+}
@@ -0,0 +1,393 @@
+use clap::Parser;
+use soroban_cli::commands::{contract::extend, NetworkRunnable};
+use soroban_cli::config::locator;
+use soroban_cli::CommandParser;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use stellar_xdr::curr::{
+    ContractDataDurability, ContractExecutable, Hash, LedgerEntryData, LedgerKey,
+    LedgerKeyContractCode, LedgerKeyContractData, ScAddress, ScContractInstance, ScVal,
+};
+
+use crate::commands::build::{clients, env_toml};
+
+/// About a week of ledgers, at 5 seconds per ledger
+const DEFAULT_THRESHOLD: u32 = 120_960;
+/// About a month of ledgers, at 5 seconds per ledger
+const DEFAULT_LEDGERS_TO_EXTEND: u32 = 535_679;
+
+/// Check and extend the time to live (TTL) of the contracts in `environments.toml`
+///
+/// Contract instances and their wasm are archived once their TTL runs out. `status` shows how
+/// many ledgers each of them has left, `extend` extends those with fewer than `--threshold`
+/// left, and `watch` keeps doing so.
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum Cmd {
+    /// Show the TTL of the instance and wasm of each contract
+    Status(Status),
+    /// Extend the instances and wasm whose TTL is below the threshold
+    Extend(Extend),
+    /// Periodically extend the instances and wasm whose TTL is below the threshold
+    Watch(Watch),
+}
+
+#[derive(Parser, Debug, Clone)]
+#[group(skip)]
+pub struct Args {
+    /// Path to Cargo.toml, whose workspace root contains `environments.toml`
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: PathBuf,
+    /// Entries with fewer ledgers than this left to live are extended
+    #[arg(long, default_value_t = DEFAULT_THRESHOLD)]
+    pub threshold: u32,
+    /// Environment of `environments.toml` to use
+    #[arg(env = "LOAM_ENV")]
+    pub env: Option<String>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct Status {
+    #[command(flatten)]
+    pub args: Args,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct Extend {
+    #[command(flatten)]
+    pub args: Args,
+    /// Number of ledgers to extend entries by
+    #[arg(long, default_value_t = DEFAULT_LEDGERS_TO_EXTEND)]
+    pub ledgers_to_extend: u32,
+    /// Account to pay for the extensions, defaults to the default account of the environment
+    #[arg(long, visible_alias = "source", env = "STELLAR_ACCOUNT")]
+    pub source_account: Option<String>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct Watch {
+    #[command(flatten)]
+    pub extend: Extend,
+    /// Seconds to wait between checks
+    #[arg(long, default_value_t = 600)]
+    pub interval: u64,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("⛔ ️no environments.toml found in {0:?}")]
+    NoEnvironmentsToml(PathBuf),
+    #[error(
+        "⛔ ️no source account given, and LOAM_ENV ({0:?}) has no accounts in environments.toml"
+    )]
+    NoSourceAccount(String),
+    #[error("⛔ ️extending {0} returned no ledger it lives until")]
+    NoLiveUntil(String),
+    #[error("⛔ ️invalid contract id for {0:?}: {1}")]
+    InvalidContractId(String, stellar_strkey::DecodeError),
+    #[error(transparent)]
+    EnvironmentsToml(#[from] env_toml::Error),
+    #[error(transparent)]
+    Clients(#[from] clients::Error),
+    #[error(transparent)]
+    Locator(#[from] locator::Error),
+    #[error(transparent)]
+    Rpc(Box<soroban_rpc::Error>),
+    #[error(transparent)]
+    Extend(Box<extend::Error>),
+    #[error(transparent)]
+    Clap(#[from] clap::Error),
+    #[error(transparent)]
+    Metadata(#[from] cargo_metadata::Error),
+}
+
+impl_from_boxed!(Error {
+    Rpc(soroban_rpc::Error),
+    Extend(extend::Error),
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Instance,
+    Wasm,
+}
+
+/// The instance or wasm of a contract, and the last ledger it lives until, if it hasn't been
+/// archived
+#[derive(Debug, Clone)]
+struct Entry {
+    contract: String,
+    kind: Kind,
+    /// Contract id for instances, hash for wasm
+    id: String,
+    live_until: Option<u32>,
+}
+
+impl std::fmt::Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Kind::Instance => write!(f, "instance"),
+            Kind::Wasm => write!(f, "wasm"),
+        }
+    }
+}
+
+impl Entry {
+    fn ledgers_left(&self, latest_ledger: u32) -> Option<u32> {
+        self.live_until
+            .map(|live_until| live_until.saturating_sub(latest_ledger))
+    }
+}
+
+struct Ttls {
+    environment: env_toml::Environment,
+    entries: Vec<Entry>,
+    latest_ledger: u32,
+}
+
+impl Cmd {
+    pub async fn run(&self) -> Result<(), Error> {
+        match self {
+            Cmd::Status(status) => status.run().await,
+            Cmd::Extend(extend) => extend.run().await,
+            Cmd::Watch(watch) => watch.run().await,
+        }
+    }
+}
+
+impl Status {
+    pub async fn run(&self) -> Result<(), Error> {
+        let Ttls {
+            entries,
+            latest_ledger,
+            ..
+        } = self.args.entries().await?;
+        println!("latest ledger: {latest_ledger}");
+        println!(
+            "{:<32} {:<8} {:>12} {:>12}",
+            "contract", "entry", "live until", "ledgers left"
+        );
+        for entry in &entries {
+            let kind = entry.kind.to_string();
+            match entry.ledgers_left(latest_ledger) {
+                Some(left) => println!(
+                    "{:<32} {kind:<8} {:>12} {left:>12}{}",
+                    entry.contract,
+                    entry.live_until.unwrap_or_default(),
+                    if left < self.args.threshold {
+                        "  ⚠️ below threshold"
+                    } else {
+                        ""
+                    }
+                ),
+                None => println!(
+                    "{:<32} {kind:<8} {:>12} {:>12}  🪦 archived",
+                    entry.contract, "-", "-"
+                ),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Extend {
+    pub async fn run(&self) -> Result<(), Error> {
+        let Ttls {
+            environment,
+            entries,
+            latest_ledger,
+        } = self.args.entries().await?;
+        let source_account = match &self.source_account {
+            Some(source_account) => source_account.clone(),
+            None => default_account(&environment)
+                .ok_or_else(|| Error::NoSourceAccount(self.args.env_name()))?,
+        };
+        let mut extended = 0;
+        for entry in &entries {
+            let Some(left) = entry.ledgers_left(latest_ledger) else {
+                eprintln!(
+                    "🪦 {} {} is archived, restore it with `stellar contract restore`",
+                    entry.contract, entry.kind
+                );
+                continue;
+            };
+            if left >= self.args.threshold {
+                continue;
+            }
+            eprintln!(
+                "⏳ extending {} {}, which has {left} ledgers left",
+                entry.contract, entry.kind
+            );
+            let id_flag = match entry.kind {
+                Kind::Instance => "--id",
+                Kind::Wasm => "--wasm-hash",
+            };
+            let ledgers_to_extend = self.ledgers_to_extend.to_string();
+            let live_until = extend::Cmd::parse_arg_vec(&[
+                id_flag,
+                &entry.id,
+                "--ledgers-to-extend",
+                &ledgers_to_extend,
+                "--source-account",
+                &source_account,
+            ])?
+            .run_against_rpc_server(None, None)
+            .await?
+            .into_result()
+            .ok_or_else(|| Error::NoLiveUntil(format!("{} {}", entry.contract, entry.kind)))?;
+            eprintln!("    ↳ lives until ledger {live_until}");
+            extended += 1;
+        }
+        eprintln!("✅ extended {extended} of {} entries", entries.len());
+        Ok(())
+    }
+}
+
+impl Watch {
+    pub async fn run(&self) -> Result<(), Error> {
+        loop {
+            // a check which fails, such as when the network can't be reached, is retried at the
+            // next interval rather than ending the watch
+            if let Err(e) = self.extend.run().await {
+                eprintln!("⚠️ could not extend the entries: {e}");
+            }
+            eprintln!("💤 checking again in {} seconds", self.interval);
+            tokio::time::sleep(Duration::from_secs(self.interval)).await;
+        }
+    }
+}
+
+impl Args {
+    fn env_name(&self) -> String {
+        self.env
+            .clone()
+            .unwrap_or_else(|| env_toml::DEFAULT_ENV.to_string())
+    }
+
+    /// Look up the instance and wasm of every contract of the environment, skipping those which
+    /// have not been deployed
+    async fn entries(&self) -> Result<Ttls, Error> {
+        let metadata = cargo_metadata::MetadataCommand::new()
+            .manifest_path(&self.manifest_path)
+            .no_deps()
+            .exec()?;
+        let workspace_root = metadata.workspace_root.as_std_path();
+        let environment = env_toml::Environment::get(workspace_root, &self.env_name())?
+            .ok_or_else(|| Error::NoEnvironmentsToml(workspace_root.to_path_buf()))?;
+        clients::Args::add_network_to_env(&environment.network)?;
+        let rpc_url = std::env::var("STELLAR_RPC_URL").expect("STELLAR_RPC_URL was just set");
+        let network_passphrase = std::env::var("STELLAR_NETWORK_PASSPHRASE")
+            .expect("STELLAR_NETWORK_PASSPHRASE was just set");
+        let client = soroban_rpc::Client::new(&rpc_url)?;
+
+        let mut contracts = Vec::new();
+        for (name, contract) in environment.contracts.iter().flatten() {
+            if let Some(network) = contract
+                .network
+                .as_ref()
+                .filter(|network| *network != &environment.network)
+            {
+                eprintln!("⚠️ skipping {name:?}, which is on {}", network.label());
+                continue;
+            }
+            match contract_id(workspace_root, name, contract, &network_passphrase)? {
+                Some(id) => contracts.push((name.to_string(), id)),
+                None => eprintln!("⚠️ skipping {name:?}, which has no id or alias yet"),
+            }
+        }
+
+        let instance_keys = contracts
+            .iter()
+            .map(|(_, id)| instance_key(id))
+            .collect::<Vec<_>>();
+        let instances = client.get_full_ledger_entries(&instance_keys).await?;
+        let mut entries = Vec::new();
+        let mut wasm = Vec::new();
+        for ((name, id), key) in contracts.iter().zip(&instance_keys) {
+            let instance = instances.entries.iter().find(|e| &e.key == key);
+            entries.push(Entry {
+                contract: name.clone(),
+                kind: Kind::Instance,
+                id: stellar_strkey::Contract(id.0).to_string(),
+                live_until: instance.map(|e| e.live_until_ledger_seq),
+            });
+            if let Some(LedgerEntryData::ContractData(data)) = instance.map(|e| &e.val) {
+                if let ScVal::ContractInstance(ScContractInstance {
+                    executable: ContractExecutable::Wasm(hash),
+                    ..
+                }) = &data.val
+                {
+                    wasm.push((name.clone(), hash.clone()));
+                }
+            }
+        }
+
+        let wasm_keys = wasm
+            .iter()
+            .map(|(_, hash)| LedgerKey::ContractCode(LedgerKeyContractCode { hash: hash.clone() }))
+            .collect::<Vec<_>>();
+        let code = client.get_full_ledger_entries(&wasm_keys).await?;
+        for ((name, hash), key) in wasm.iter().zip(&wasm_keys) {
+            entries.push(Entry {
+                contract: name.clone(),
+                kind: Kind::Wasm,
+                id: hex::encode(hash.0),
+                live_until: code
+                    .entries
+                    .iter()
+                    .find(|e| &e.key == key)
+                    .map(|e| e.live_until_ledger_seq),
+            });
+        }
+        entries.sort_by(|a, b| a.contract.cmp(&b.contract));
+
+        Ok(Ttls {
+            environment,
+            entries,
+            latest_ledger: u32::try_from(code.latest_ledger.max(instances.latest_ledger))
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// The id set for a contract in `environments.toml`, or else the one saved as its alias by
+/// `loam build`
+fn contract_id(
+    workspace_root: &Path,
+    name: &str,
+    contract: &env_toml::Contract,
+    network_passphrase: &str,
+) -> Result<Option<Hash>, Error> {
+    let id = if let Some(id) = &contract.id {
+        id.clone()
+    } else {
+        let locator = locator::Args {
+            global: false,
+            config_dir: Some(workspace_root.to_path_buf()),
+        };
+        let Some(id) = locator.get_contract_id(name, network_passphrase)? else {
+            return Ok(None);
+        };
+        id
+    };
+    let contract = stellar_strkey::Contract::from_string(&id)
+        .map_err(|e| Error::InvalidContractId(name.to_string(), e))?;
+    Ok(Some(Hash(contract.0)))
+}
+
+fn instance_key(contract_id: &Hash) -> LedgerKey {
+    LedgerKey::ContractData(LedgerKeyContractData {
+        contract: ScAddress::Contract(contract_id.clone()),
+        key: ScVal::LedgerKeyContractInstance,
+        durability: ContractDataDurability::Persistent,
+    })
+}
+
+/// The account marked as default in the environment, or its only account
+fn default_account(environment: &env_toml::Environment) -> Option<String> {
+    let accounts = environment.accounts.as_deref()?;
+    accounts
+        .iter()
+        .find(|account| account.default)
+        .or_else(|| accounts.first())
+        .map(|account| account.name.clone())
+}
@@ -0,0 +1,222 @@
+use clap::Parser;
+use std::path::{Path, PathBuf};
+
+use crate::commands::build::{self, manifest::Manifest};
+
+/// Rebuild the workspace's contracts and check that one of them matches a wasm file or hash
+///
+/// If a manifest written by `loam build --locked-manifest` is found, the contracts are rebuilt
+/// with the same options and `RUSTFLAGS`, and any difference in toolchain, `Cargo.lock`, commit,
+/// rustflags or features is reported.
+#[derive(Parser, Debug, Clone)]
+pub struct Cmd {
+    /// Path to a wasm file, or the sha256 hash of one, such as the hash of a deployed contract
+    pub wasm: String,
+    /// Manifest recording how the contracts were originally built
+    ///
+    /// Defaults to `target/loam/manifest.json`, if it exists.
+    #[arg(long)]
+    pub manifest: Option<PathBuf>,
+    /// Path to Cargo.toml
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: PathBuf,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("⛔ ️{0:?} is neither a wasm file nor a sha256 hash")]
+    InvalidWasmOrHash(String),
+    #[error("reading {0:?}: {1}")]
+    Reading(PathBuf, std::io::Error),
+    #[error("⛔ ️no contract built from this workspace has the hash {0}")]
+    NoMatch(String),
+    #[error(transparent)]
+    Manifest(#[from] build::manifest::Error),
+    #[error(transparent)]
+    Build(#[from] build::Error),
+    #[error(transparent)]
+    Clap(#[from] clap::Error),
+    #[error(transparent)]
+    Metadata(#[from] cargo_metadata::Error),
+}
+
+impl Cmd {
+    pub async fn run(&self) -> Result<(), Error> {
+        let expected = self.expected_hash()?;
+        let target_dir = loam_build::get_target_dir(&self.manifest_path)?;
+        let default_manifest = target_dir.join("manifest.json");
+        // Rebuild into a manifest of its own, so that the recorded one is left as it was
+        let rebuilt_manifest = target_dir.join("verify").join("manifest.json");
+        let recorded = match &self.manifest {
+            Some(path) => Some(Manifest::load(path)?),
+            None if default_manifest.exists() => Some(Manifest::load(&default_manifest)?),
+            None => None,
+        };
+
+        let mut args = vec![
+            "build".to_string(),
+            format!("--manifest-path={}", self.manifest_path.display()),
+            "--force".to_string(),
+            "--locked-manifest".to_string(),
+            format!("--manifest-out={}", rebuilt_manifest.display()),
+        ];
+        if let Some(recorded) = &recorded {
+            args.extend(recorded.build_args.iter().cloned());
+            // Contracts are built by one `loam build`, so they share the RUSTFLAGS it ran with
+            if let Some(contract) = recorded.contracts.values().next() {
+                match &contract.rustflags {
+                    Some(rustflags) => std::env::set_var("RUSTFLAGS", rustflags),
+                    None => std::env::remove_var("RUSTFLAGS"),
+                }
+            }
+        }
+        build::Cmd::try_parse_from(args)?.run().await?;
+        let rebuilt = Manifest::load(&rebuilt_manifest)?;
+
+        if let Some(recorded) = &recorded {
+            for (what, before, after) in differences(recorded, &rebuilt) {
+                eprintln!(
+                    "⚠️ {what} differs from the manifest: recorded {}, now {}",
+                    before.as_deref().unwrap_or("nothing"),
+                    after.as_deref().unwrap_or("nothing"),
+                );
+            }
+        }
+
+        let name = rebuilt
+            .find(&expected)
+            .ok_or_else(|| Error::NoMatch(expected.clone()))?;
+        eprintln!("✅ {expected} matches {name:?}");
+        Ok(())
+    }
+
+    fn expected_hash(&self) -> Result<String, Error> {
+        let path = Path::new(&self.wasm);
+        if path.is_file() {
+            return build::manifest::sha256_file(path)
+                .map_err(|e| Error::Reading(path.to_path_buf(), e));
+        }
+        if self.wasm.len() == 64 && self.wasm.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Ok(self.wasm.to_lowercase());
+        }
+        Err(Error::InvalidWasmOrHash(self.wasm.clone()))
+    }
+}
+
+/// What differs between how the contracts were `recorded` to be built and how they were `rebuilt`
+fn differences(
+    recorded: &Manifest,
+    rebuilt: &Manifest,
+) -> Vec<(String, Option<String>, Option<String>)> {
+    let mut differences = vec![
+        (
+            "rustc".to_string(),
+            recorded.rustc.clone(),
+            rebuilt.rustc.clone(),
+        ),
+        (
+            "Cargo.lock".to_string(),
+            recorded.cargo_lock_sha256.clone(),
+            rebuilt.cargo_lock_sha256.clone(),
+        ),
+        (
+            "git commit".to_string(),
+            recorded.git_commit.clone(),
+            rebuilt.git_commit.clone(),
+        ),
+    ];
+    for (name, before) in &recorded.contracts {
+        let Some(after) = rebuilt.contracts.get(name) else {
+            continue;
+        };
+        differences.push((
+            format!("rustflags of {name}"),
+            before.rustflags.clone(),
+            after.rustflags.clone(),
+        ));
+        differences.push((
+            format!("features of {name}"),
+            features(&before.features),
+            features(&after.features),
+        ));
+    }
+    differences.retain(|(_, before, after)| before != after);
+    differences
+}
+
+fn features(features: &[String]) -> Option<String> {
+    (!features.is_empty()).then(|| features.join(","))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_fs::TempDir;
+
+    fn cmd(wasm: &str) -> Cmd {
+        Cmd::try_parse_from(["verify", wasm]).unwrap()
+    }
+
+    #[test]
+    fn expected_hash_is_a_hash_or_the_hash_of_a_file() {
+        let hash = "AB".repeat(32);
+        assert_eq!(cmd(&hash).expected_hash().unwrap(), hash.to_lowercase());
+
+        let dir = TempDir::new().unwrap();
+        let wasm = dir.path().join("contract.wasm");
+        std::fs::write(&wasm, b"\0asm").unwrap();
+        assert_eq!(
+            cmd(wasm.to_str().unwrap()).expected_hash().unwrap(),
+            build::manifest::sha256_file(&wasm).unwrap()
+        );
+
+        for invalid in ["missing.wasm", "abc", &"z".repeat(64)] {
+            assert!(matches!(
+                cmd(invalid).expected_hash(),
+                Err(Error::InvalidWasmOrHash(wasm)) if wasm == invalid
+            ));
+        }
+    }
+
+    #[test]
+    fn only_what_changed_since_the_manifest_is_reported() {
+        let contract = |rustflags: Option<&str>, features: &[&str]| build::manifest::Contract {
+            wasm_sha256: "hash".to_string(),
+            command: "cargo rustc".to_string(),
+            rustflags: rustflags.map(str::to_string),
+            features: features.iter().map(ToString::to_string).collect(),
+        };
+        let recorded = Manifest {
+            rustc: Some("rustc 1.79.0".to_string()),
+            cargo_lock_sha256: Some("lock".to_string()),
+            git_commit: Some("abc".to_string()),
+            contracts: [
+                ("a".to_string(), contract(Some("-C opt"), &[])),
+                ("b".to_string(), contract(None, &["x", "y"])),
+            ]
+            .into(),
+            ..Manifest::default()
+        };
+        let rebuilt = Manifest {
+            git_commit: Some("abc-dirty".to_string()),
+            cargo_lock_sha256: None,
+            contracts: [
+                ("a".to_string(), contract(None, &[])),
+                ("b".to_string(), contract(None, &["x"])),
+            ]
+            .into(),
+            ..recorded.clone()
+        };
+        let some = |s: &str| Some(s.to_string());
+        assert!(differences(&recorded, &recorded).is_empty());
+        assert_eq!(
+            differences(&recorded, &rebuilt),
+            [
+                ("Cargo.lock".to_string(), some("lock"), None),
+                ("git commit".to_string(), some("abc"), some("abc-dirty")),
+                ("rustflags of a".to_string(), some("-C opt"), None),
+                ("features of b".to_string(), some("x,y"), some("x")),
+            ]
+        );
+    }
+}
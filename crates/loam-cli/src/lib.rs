@@ -3,7 +3,23 @@
     clippy::must_use_candidate,
     clippy::missing_panics_doc
 )]
+
+/// Implement `From` for the errors an error enum keeps boxed, so that `?` still converts them.
+/// The errors of soroban-cli are large, and boxing them keeps every `Result` of loam small.
+macro_rules! impl_from_boxed {
+    ($error:ty { $($variant:ident($source:ty)),+ $(,)? }) => {
+        $(
+            impl From<$source> for $error {
+                fn from(e: $source) -> Self {
+                    Self::$variant(Box::new(e))
+                }
+            }
+        )+
+    };
+}
+
 pub mod commands;
+pub mod registry;
 
 use std::path::Path;
 
@@ -0,0 +1,482 @@
+use regex::Regex;
+use soroban_cli::commands::{
+    contract::{arg_parsing, install, invoke},
+    NetworkRunnable,
+};
+use soroban_cli::config::{self, locator, network};
+use soroban_cli::CommandParser;
+use std::{ffi::OsString, path::Path};
+use stellar_xdr::curr::{Hash, InvokeContractArgs};
+
+use crate::commands::build::env_toml;
+
+/// The `RegistryError`s returned by `loam-subcontract-registry`, by code
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistryError {
+    #[error("names must be 1-64 lowercase letters, digits, `-` or `_`")]
+    InvalidName = 1,
+    #[error("versions must be of the form `MAJOR.MINOR.PATCH`")]
+    InvalidVersion = 2,
+    #[error("no package has been published with this name")]
+    NoSuchPackage = 3,
+    #[error("the package has no such version")]
+    NoSuchVersion = 4,
+    #[error("this version has already been published")]
+    AlreadyPublished = 5,
+    #[error("only the owner of a package or contract can change it")]
+    NotOwner = 6,
+    #[error("every version of the package has been yanked")]
+    AllVersionsYanked = 7,
+    #[error("a contract has already been deployed with this name")]
+    AlreadyDeployed = 8,
+    #[error("no contract has been deployed with this name")]
+    NoSuchContract = 9,
+    #[error("the contract has a Core subcontract, but making its owner the admin failed")]
+    AdminNotSet = 10,
+}
+
+impl RegistryError {
+    fn from_code(code: u32) -> Option<Self> {
+        [
+            Self::InvalidName,
+            Self::InvalidVersion,
+            Self::NoSuchPackage,
+            Self::NoSuchVersion,
+            Self::AlreadyPublished,
+            Self::NotOwner,
+            Self::AllVersionsYanked,
+            Self::AlreadyDeployed,
+            Self::NoSuchContract,
+            Self::AdminNotSet,
+        ]
+        .into_iter()
+        .find(|error| *error as u32 == code)
+    }
+}
+
+/// Options shared by the commands which talk to a registry contract, such as one built from
+/// [`examples/soroban/registry`](https://github.com/loambuild/loam/tree/main/examples/soroban/registry)
+#[derive(clap::Args, Debug, Clone)]
+pub struct Args {
+    /// Contract id or alias of the registry contract
+    #[arg(long, env = "LOAM_REGISTRY")]
+    pub registry: String,
+    /// Account to sign with, which is also the author of published packages: an identity name,
+    /// secret key or seed phrase. Only needed to publish and deploy, as lookups are simulated.
+    #[arg(long, visible_alias = "source", env = "STELLAR_ACCOUNT")]
+    pub source_account: Option<String>,
+    /// RPC server endpoint
+    #[arg(long, env = "STELLAR_RPC_URL", requires = "network_passphrase")]
+    pub rpc_url: Option<String>,
+    /// Network passphrase of the RPC server
+    #[arg(long, env = "STELLAR_NETWORK_PASSPHRASE", requires = "rpc_url")]
+    pub network_passphrase: Option<String>,
+    /// Name of network to use from config
+    #[arg(long, env = "STELLAR_NETWORK")]
+    pub network: Option<String>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("⛔ ️a source account is needed to sign for {0}, pass --source-account or set STELLAR_ACCOUNT")]
+    NoSourceAccount(String),
+    #[error("⛔ ️the registry refused {0}: {1}")]
+    Registry(String, RegistryError),
+    #[error(transparent)]
+    Install(Box<install::Error>),
+    #[error(transparent)]
+    Invoke(Box<invoke::Error>),
+    #[error(transparent)]
+    Clap(#[from] clap::Error),
+    #[error(transparent)]
+    Config(Box<config::Error>),
+    #[error(transparent)]
+    Locator(#[from] locator::Error),
+    #[error(transparent)]
+    Rpc(Box<soroban_rpc::Error>),
+    #[error(transparent)]
+    Spec(#[from] soroban_spec_tools::contract::Error),
+    #[error(transparent)]
+    ArgParsing(Box<arg_parsing::Error>),
+    #[error(transparent)]
+    Xdr(#[from] stellar_xdr::curr::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+impl_from_boxed!(Error {
+    Install(install::Error),
+    Invoke(invoke::Error),
+    Config(config::Error),
+    Rpc(soroban_rpc::Error),
+    ArgParsing(arg_parsing::Error),
+});
+
+impl Args {
+    /// The source account, which is needed to sign for `what`
+    fn source_account(&self, what: &str) -> Result<&str, Error> {
+        self.source_account
+            .as_deref()
+            .ok_or_else(|| Error::NoSourceAccount(what.to_string()))
+    }
+
+    /// Source account and network arguments, as taken by `stellar contract` subcommands. Without
+    /// a source account, invocations are only simulated with `--is-view`, which never reads the
+    /// source account, though one still has to be given.
+    fn config_args(&self, send: bool, what: &str) -> Result<Vec<&str>, Error> {
+        let mut args = match &self.source_account {
+            Some(source_account) => vec!["--source-account", source_account.as_str()],
+            None if send => return Err(Error::NoSourceAccount(what.to_string())),
+            None => vec!["--is-view", "--source-account", "unused"],
+        };
+        for (flag, value) in [
+            ("--rpc-url", &self.rpc_url),
+            ("--network-passphrase", &self.network_passphrase),
+            ("--network", &self.network),
+        ] {
+            if let Some(value) = value {
+                args.extend([flag, value.as_str()]);
+            }
+        }
+        Ok(args)
+    }
+
+    fn config(&self) -> config::Args {
+        config::Args {
+            network: network::Args {
+                rpc_url: self.rpc_url.clone(),
+                network_passphrase: self.network_passphrase.clone(),
+                network: self.network.clone(),
+            },
+            // only the network and locator are used, which don't depend on the source account
+            source_account: self.source_account.clone().unwrap_or_default(),
+            hd_path: None,
+            locator: locator::Args {
+                global: false,
+                config_dir: None,
+            },
+        }
+    }
+
+    /// Invoke a function of the registry, only simulating it unless `send` is set. Errors returned
+    /// by the registry are described, rather than given by their code.
+    async fn invoke<S: AsRef<str>>(
+        &self,
+        send: bool,
+        function_and_args: &[S],
+    ) -> Result<String, Error> {
+        self.invoke_raw(send, function_and_args)
+            .await
+            .map_err(|e| match e {
+                Error::Invoke(e) => match contract_error(&e).and_then(RegistryError::from_code) {
+                    Some(error) => {
+                        // the function and the name it was called with, e.g. `fetch --name hello`
+                        let call = function_and_args
+                            .iter()
+                            .take(3)
+                            .map(AsRef::as_ref)
+                            .collect::<Vec<_>>()
+                            .join(" ");
+                        Error::Registry(call, error)
+                    }
+                    None => Error::Invoke(e),
+                },
+                e => e,
+            })
+    }
+
+    /// Invoke a function of the registry, leaving the errors of contracts as they are
+    async fn invoke_raw<S: AsRef<str>>(
+        &self,
+        send: bool,
+        function_and_args: &[S],
+    ) -> Result<String, Error> {
+        let mut args = vec!["--id", &self.registry];
+        let function = function_and_args.first().map_or("", AsRef::as_ref);
+        args.extend(self.config_args(send, function)?);
+        args.extend(["--send", if send { "yes" } else { "no" }, "--"]);
+        args.extend(function_and_args.iter().map(AsRef::as_ref));
+        Ok(invoke::Cmd::parse_arg_vec(&args)?
+            .run_against_rpc_server(None, None)
+            .await?
+            .into_result()
+            .expect("no result returned by 'contract invoke'"))
+    }
+
+    /// Parse the name and arguments of a function of the published wasm with hash `wasm_hash`,
+    /// given as they would be to `stellar contract invoke`
+    pub async fn parse_invocation(
+        &self,
+        wasm_hash: &str,
+        function_and_args: &[OsString],
+    ) -> Result<InvokeContractArgs, Error> {
+        let config = self.config();
+        let network = config.get_network()?;
+        let client = soroban_rpc::Client::new(&network.rpc_url)?;
+        let hash: Hash = wasm_hash.parse()?;
+        let wasm = soroban_cli::utils::rpc::get_remote_wasm_from_hash(&client, &hash).await?;
+        let spec = soroban_spec_tools::contract::Spec::new(&wasm)?.spec;
+        // The contract doesn't exist yet, so the registry's id is used to name the parsed command
+        let registry = config
+            .locator
+            .resolve_contract_id(&self.registry, &network.network_passphrase)?;
+        let (_, _, invocation, _) = arg_parsing::build_host_function_parameters(
+            &registry,
+            function_and_args,
+            &spec,
+            &config,
+        )?;
+        Ok(invocation)
+    }
+
+    /// Deploy `wasm_name@version`, or its latest version, as a contract named `contract_name`
+    /// owned by the source account, invoking `init` in the same transaction. Returns the id of
+    /// the new contract.
+    ///
+    /// Accounts passed to `init` can only authorize it if they are the source account.
+    pub async fn deploy_contract(
+        &self,
+        contract_name: &str,
+        wasm_name: &str,
+        version: Option<&str>,
+        init: Option<&InvokeContractArgs>,
+    ) -> Result<String, Error> {
+        let mut args = vec![
+            "deploy_contract".to_string(),
+            "--contract_name".to_string(),
+            contract_name.to_string(),
+            "--wasm_name".to_string(),
+            wasm_name.to_string(),
+            "--owner".to_string(),
+            self.source_account("deploy_contract")?.to_string(),
+        ];
+        if let Some(version) = version {
+            args.extend(["--version".to_string(), version.to_string()]);
+        }
+        if let Some(init) = init {
+            args.extend([
+                "--init_fn".to_string(),
+                init.function_name.to_utf8_string_lossy(),
+                "--init_args".to_string(),
+                serde_json::to_string(&init.args.to_vec())?,
+            ]);
+        }
+        // An error returned by the init function can't be told apart from one returned by the
+        // registry, so it is only described when there is no init function
+        let contract_id = if init.is_some() {
+            self.invoke_raw(true, &args).await?
+        } else {
+            self.invoke(true, &args).await?
+        };
+        Ok(contract_id.trim_matches('"').to_string())
+    }
+
+    /// Redeploy the contract deployed as `contract_name` with `version`, or the latest version,
+    /// of the package it was deployed from, keeping its id and storage
+    pub async fn redeploy_contract(
+        &self,
+        contract_name: &str,
+        version: Option<&str>,
+    ) -> Result<(), Error> {
+        let mut args = vec!["redeploy_contract", "--contract_name", contract_name];
+        if let Some(version) = version {
+            args.extend(["--version", version]);
+        }
+        self.invoke(true, &args).await?;
+        Ok(())
+    }
+
+    /// Id of the contract deployed as `contract_name`, if there is one
+    pub async fn contract_id(&self, contract_name: &str) -> Result<Option<String>, Error> {
+        match self
+            .invoke(
+                false,
+                &["fetch_contract_id", "--contract_name", contract_name],
+            )
+            .await
+        {
+            Ok(contract_id) => Ok(Some(contract_id.trim_matches('"').to_string())),
+            Err(Error::Registry(_, RegistryError::NoSuchContract)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The network settings of the registry, as they would be given in `environments.toml`
+    pub fn env_toml_network(&self) -> env_toml::Network {
+        env_toml::Network {
+            name: self.network.clone(),
+            rpc_url: self.rpc_url.clone(),
+            network_passphrase: self.network_passphrase.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Save `contract_id` as the alias `name`, for the network of the registry
+    pub fn save_alias(&self, name: &str, contract_id: &str) -> Result<(), Error> {
+        let config = self.config();
+        let network = config.get_network()?;
+        config
+            .locator
+            .save_contract_id(&network.network_passphrase, contract_id, name)?;
+        Ok(())
+    }
+}
+
+/// The calls `loam publish` makes to a registry, so that publishing can be tested without a
+/// network
+#[allow(async_fn_in_trait)]
+pub trait Registry {
+    /// Hash of the wasm published as `name@version`, or as the latest version of `name`, if
+    /// it has been published
+    async fn published_hash(
+        &self,
+        name: &str,
+        version: Option<&str>,
+    ) -> Result<Option<String>, Error>;
+
+    /// Install `wasm` on the network, returning its hash
+    async fn install(&self, wasm: &Path) -> Result<String, Error>;
+
+    /// Publish an installed wasm as `name@version`, with the source account as its author
+    async fn publish(
+        &self,
+        name: &str,
+        version: &str,
+        wasm_hash: &str,
+        repo: &str,
+    ) -> Result<(), Error>;
+}
+
+impl Registry for Args {
+    async fn published_hash(
+        &self,
+        name: &str,
+        version: Option<&str>,
+    ) -> Result<Option<String>, Error> {
+        let mut args = vec!["fetch_hash", "--name", name];
+        if let Some(version) = version {
+            args.extend(["--version", version]);
+        }
+        match self.invoke(false, &args).await {
+            Ok(hash) => Ok(Some(hash.trim_matches('"').to_string())),
+            Err(Error::Registry(
+                _,
+                RegistryError::NoSuchPackage | RegistryError::NoSuchVersion,
+            )) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn install(&self, wasm: &Path) -> Result<String, Error> {
+        let mut args = vec![
+            "--wasm",
+            wasm.to_str().expect("we do not support non-utf8 paths"),
+        ];
+        args.extend(self.config_args(true, "install")?);
+        Ok(install::Cmd::parse_arg_vec(&args)?
+            .run_against_rpc_server(None, None)
+            .await?
+            .into_result()
+            .expect("no hash returned by 'contract install'")
+            .to_string())
+    }
+
+    async fn publish(
+        &self,
+        name: &str,
+        version: &str,
+        wasm_hash: &str,
+        repo: &str,
+    ) -> Result<(), Error> {
+        let author = self.source_account("publish")?;
+        self.invoke(
+            true,
+            &[
+                "publish",
+                "--name",
+                name,
+                "--version",
+                version,
+                "--wasm_hash",
+                wasm_hash,
+                "--author",
+                author,
+                "--repo",
+                repo,
+            ],
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// Code of the error returned by a contract, if invoking it failed because of one
+fn contract_error(e: &invoke::Error) -> Option<u32> {
+    let re = Regex::new(r"Error\(Contract, #(\d+)\)").expect("Invalid regex pattern");
+    re.captures(&e.to_string())
+        .and_then(|cap| cap[1].parse().ok())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn registry_errors_are_described() {
+        assert_eq!(
+            RegistryError::from_code(7),
+            Some(RegistryError::AllVersionsYanked)
+        );
+        assert_eq!(
+            RegistryError::from_code(10),
+            Some(RegistryError::AdminNotSet)
+        );
+        assert_eq!(RegistryError::from_code(0), None);
+        assert_eq!(RegistryError::from_code(11), None);
+        assert_eq!(
+            Error::Registry(
+                "fetch_hash --name hello".to_string(),
+                RegistryError::AllVersionsYanked
+            )
+            .to_string(),
+            "⛔ ️the registry refused fetch_hash --name hello: every version of the package has been yanked"
+        );
+    }
+
+    #[test]
+    fn lookups_need_no_source_account() {
+        let mut args = Args {
+            registry: "registry".to_string(),
+            source_account: None,
+            rpc_url: None,
+            network_passphrase: None,
+            network: Some("testnet".to_string()),
+        };
+        assert_eq!(
+            args.config_args(false, "fetch_contract_id").unwrap(),
+            [
+                "--is-view",
+                "--source-account",
+                "unused",
+                "--network",
+                "testnet"
+            ]
+        );
+        assert_eq!(
+            args.config_args(true, "deploy_contract")
+                .unwrap_err()
+                .to_string(),
+            "⛔ ️a source account is needed to sign for deploy_contract, pass --source-account or set STELLAR_ACCOUNT"
+        );
+        assert!(matches!(
+            args.source_account("publish"),
+            Err(Error::NoSourceAccount(what)) if what == "publish"
+        ));
+
+        args.source_account = Some("alice".to_string());
+        assert_eq!(
+            args.config_args(true, "deploy_contract").unwrap(),
+            ["--source-account", "alice", "--network", "testnet"]
+        );
+    }
+}
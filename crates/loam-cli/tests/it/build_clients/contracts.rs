@@ -181,9 +181,8 @@ rpc-url = "http://localhost:8000/rpc"
 network-passphrase = "Standalone Network ; February 2017"
 
 [production.contracts]
-hello_world.id = "{}"
-"#,
-            contract_id
+hello_world.id = "{contract_id}"
+"#
         ));
 
         // ensure production can identify via contract ID
@@ -214,8 +213,9 @@ soroban_token_contract.client = false
 
         // ensure contract hash change check works, should throw error in production
         assert!(!output4.status.success());
-        assert!(String::from_utf8_lossy(&output4.stderr)
-            .contains("️An ID must be set for a contract in production or staging"));
+        assert!(String::from_utf8_lossy(&output4.stderr).contains(
+            "environment \"production\" requires ids, so contract \"hello_world\" needs one"
+        ));
     });
 }
 
@@ -0,0 +1,49 @@
+use crate::util::{AssertExt, TestEnv};
+
+#[test]
+fn dry_run_prints_plan_without_deploying() {
+    TestEnv::from("soroban-init-boilerplate", |env| {
+        env.set_environments_toml(
+            r#"
+staging.accounts = [
+    { name = "alice" },
+]
+
+[staging.network]
+rpc-url = "http://localhost:8000/rpc"
+network-passphrase = "Standalone Network ; February 2017"
+
+[staging.contracts]
+hello_world.id = "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAD2KM"
+soroban_increment_contract.client = false
+"#,
+        );
+
+        let stdout = env
+            .loam_build("staging", false)
+            .arg("--dry-run")
+            .assert()
+            .success()
+            .stdout_as_str();
+        assert!(stdout.contains("plan for LOAM_ENV=staging"));
+        assert!(stdout.contains("hello_world"));
+        assert!(stdout.contains("bind-only"));
+        assert!(!stdout.contains("soroban_increment_contract"));
+
+        let stdout = env
+            .loam_build("staging", false)
+            .arg("--dry-run=json")
+            .assert()
+            .success()
+            .stdout_as_str();
+        let plan: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        assert_eq!(plan["env"], "staging");
+        assert_eq!(plan["contracts"][0]["name"], "hello_world");
+        assert_eq!(plan["contracts"][0]["action"], "bind-only");
+        assert_eq!(plan["contracts"][0]["init"], false);
+
+        // nothing was generated or created
+        assert!(!env.cwd.join("src/contracts/hello_world.ts").exists());
+        assert!(!env.cwd.join(".stellar/identity/alice.toml").exists());
+    });
+}
@@ -91,12 +91,11 @@ STELLAR_ACCOUNT=bob mint --amount 2000000 --to bob
 #[test]
 fn init_handles_quotations_and_subcommands_in_script() {
     TestEnv::from("soroban-init-boilerplate", |env| {
-        let binary_path = env
-            .find_binary("stellar")
+        let binary_path = TestEnv::find_binary("stellar")
             .expect("Stellar binary not found. Test cannot proceed.");
 
         let binary_path_str = binary_path.to_string_lossy();
-        env.set_environments_toml(&format!(
+        env.set_environments_toml(format!(
             r#"
     development.accounts = [
     {{ name = "me" }},
@@ -115,10 +114,9 @@ fn init_handles_quotations_and_subcommands_in_script() {
     [development.contracts.soroban_custom_types_contract]
     client = true
     init = """
-    test_init --resolution 300000 --assets '[{{"Stellar": "$({} contract id asset --asset native)"}} ]' --decimals 14 --base '{{"Stellar":"$({} contract id asset --asset native)"}}'
+    test_init --resolution 300000 --assets '[{{"Stellar": "$({binary_path_str} contract id asset --asset native)"}} ]' --decimals 14 --base '{{"Stellar":"$({binary_path_str} contract id asset --asset native)"}}'
     """
-    "#,
-            binary_path_str, binary_path_str
+    "#
         ));
 
         let output = env
@@ -140,14 +138,13 @@ fn init_handles_quotations_and_subcommands_in_script() {
         assert!(String::from_utf8_lossy(&output.stderr).contains(
             "✅ Initialization script for \"soroban_custom_types_contract\" completed successfully"
         ));
-    })
+    });
 }
 
 #[test]
 fn init_scripts_run_in_specified_order() {
     TestEnv::from("soroban-init-boilerplate", |env| {
-        let binary_path = env
-            .find_binary("stellar")
+        let binary_path = TestEnv::find_binary("stellar")
             .expect("Stellar binary not found. Test cannot proceed.");
         let binary_path_str = binary_path.to_string_lossy();
         // First configuration: custom_types then token
@@ -170,7 +167,7 @@ soroban_auth_contract.client = false
 [development.contracts.soroban_custom_types_contract]
 client = true
 init = """
-test_init --resolution 300000 --assets '[{{"Stellar": "$({} contract id asset --asset native)"}} ]' --decimals 14 --base '{{"Stellar":"$({} contract id asset --asset native)"}}'
+test_init --resolution 300000 --assets '[{{"Stellar": "$({binary_path_str} contract id asset --asset native)"}} ]' --decimals 14 --base '{{"Stellar":"$({binary_path_str} contract id asset --asset native)"}}'
 """
 
 [development.contracts.soroban_token_contract]
@@ -179,8 +176,7 @@ init = """
 STELLAR_ACCOUNT=bob initialize --symbol ABND --decimal 7 --name abundance --admin bob 
 STELLAR_ACCOUNT=bob mint --amount 2000000 --to bob 
 """
-"#,
-            binary_path_str, binary_path_str
+"#
         ));
 
         let output = env
@@ -230,10 +226,10 @@ STELLAR_ACCOUNT=bob mint --amount 2000000 --to bob
 [development.contracts.soroban_custom_types_contract]
 client = true
 init = """
-test_init --resolution 300000 --assets '[{{"Stellar": "$({} contract id asset --asset native)"}} ]' --decimals 14 --base '{{"Stellar":"$({} contract id asset --asset native)"}}'
+test_init --resolution 300000 --assets '[{{"Stellar": "$({binary_path_str} contract id asset --asset native)"}} ]' --decimals 14 --base '{{"Stellar":"$({binary_path_str} contract id asset --asset native)"}}'
 """
-"#,
-        binary_path_str, binary_path_str));
+"#
+        ));
 
         let output = env
             .loam_env("development", true)
@@ -254,5 +250,89 @@ test_init --resolution 300000 --assets '[{{"Stellar": "$({} contract id asset --
             token_index < custom_types_index,
             "Token should be initialized before custom types"
         );
-    })
+    });
+}
+
+#[test]
+fn init_steps_are_checked_and_run() {
+    TestEnv::from("soroban-init-boilerplate", |env| {
+        env.set_environments_toml(
+            r#"
+development.accounts = [
+{ name = "alice" },
+{ name = "bob" },
+]
+
+[development.network]
+rpc-url = "http://localhost:8000/rpc"
+network-passphrase = "Standalone Network ; February 2017"
+
+[development.contracts]
+hello_world.client = false
+soroban_increment_contract.client = false
+soroban_custom_types_contract.client = false
+soroban_auth_contract.client = false
+
+[development.contracts.soroban_token_contract]
+client = true
+
+[[development.contracts.soroban_token_contract.init]]
+fn = "initialize"
+args = { admin = "bob", decimal = 7, name = "abundance", symbol = "ABND" }
+
+[[development.contracts.soroban_token_contract.init]]
+fn = "mint"
+args = { to = "alice", amount = 2000000 }
+source = "bob"
+"#,
+        );
+
+        let output = env
+            .loam_env("development", true)
+            .output()
+            .expect("Failed to execute command");
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{stderr}");
+        assert!(stderr
+            .contains(" -- initialize --admin bob --decimal 7 --name abundance --symbol ABND"));
+        assert!(stderr.contains("--source-account bob -- mint --to alice --amount 2000000"));
+
+        // a step which doesn't match the contract's spec is reported before any call is made
+        env.set_environments_toml(
+            r#"
+development.accounts = [
+{ name = "alice" },
+]
+
+[development.network]
+rpc-url = "http://localhost:8000/rpc"
+network-passphrase = "Standalone Network ; February 2017"
+
+[development.contracts]
+hello_world.client = false
+soroban_increment_contract.client = false
+soroban_custom_types_contract.client = false
+soroban_auth_contract.client = false
+
+[development.contracts.soroban_token_contract]
+client = true
+
+[[development.contracts.soroban_token_contract.init]]
+fn = "initialise"
+args = { admin = "alice", decimal = 7, name = "abundance", symbol = "ABND" }
+"#,
+        );
+        let output = env
+            .loam_env("development", true)
+            .output()
+            .expect("Failed to execute command");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success());
+        assert!(
+            stderr.contains("unknown function `initialise`, did you mean `initialize`?"),
+            "{stderr}"
+        );
+        assert!(!stderr.contains("Executing: stellar contract invoke"));
+    });
 }
@@ -1,6 +1,7 @@
 mod accounts;
 mod contracts;
 mod dev;
+mod dry_run;
 mod init_script;
 mod manifest_path;
 mod network;
@@ -1,4 +1,8 @@
-use crate::util::{AssertExt, TestEnv};
+use stellar_xdr::curr::{
+    ConfigSettingEntry, ConfigSettingId, LedgerEntryData, LedgerKey, LedgerKeyConfigSetting,
+};
+
+use crate::util::{stub_rpc, AssertExt, TestEnv, LATEST_LEDGER};
 
 #[test]
 fn run_network_from_rpc_and_passphrase() {
@@ -63,3 +67,34 @@ soroban_token_contract.client = false
         assert!(stderr.contains("🌐 using lol network\n"));
     });
 }
+
+#[test]
+fn max_wasm_size_is_read_from_the_network() {
+    TestEnv::from("soroban-init-boilerplate", |env| {
+        let rpc_url = stub_rpc(vec![(
+            LedgerKey::ConfigSetting(LedgerKeyConfigSetting {
+                config_setting_id: ConfigSettingId::ContractMaxSizeBytes,
+            }),
+            LedgerEntryData::ConfigSetting(ConfigSettingEntry::ContractMaxSizeBytes(100)),
+            LATEST_LEDGER,
+        )]);
+        env.set_environments_toml(format!(
+            r#"
+[staging.network]
+rpc-url = "{rpc_url}"
+network-passphrase = "Standalone Network ; February 2017"
+
+[staging.contracts]
+hello_world.client = false
+"#
+        ));
+
+        let stderr = env
+            .loam_env("staging", false)
+            .args(["--package", "hello-world"])
+            .assert()
+            .failure()
+            .stderr_as_str();
+        assert!(stderr.contains("which exceeds the maximum contract size of 100 bytes"));
+    });
+}
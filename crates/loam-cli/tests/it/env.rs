@@ -0,0 +1,151 @@
+use crate::util::{AssertExt, TestEnv};
+use assert_cmd::Command;
+
+#[test]
+fn check_valid_environments_toml() {
+    TestEnv::from("soroban-init-boilerplate", |env| {
+        env.set_environments_toml(
+            r#"
+development.accounts = [
+    { name = "alice" },
+]
+
+[development.network]
+rpc-url = "http://localhost:8000/rpc"
+network-passphrase = "Standalone Network ; February 2017"
+
+[development.contracts]
+hello_world.client = true
+soroban_increment_contract.client = false
+"#,
+        );
+
+        let stderr = env
+            .loam("env")
+            .arg("check")
+            .assert()
+            .success()
+            .stderr_as_str();
+        assert!(
+            stderr.contains("✅ environments.toml is valid, with 1 environment"),
+            "{stderr}"
+        );
+    });
+}
+
+#[test]
+fn check_reports_every_problem() {
+    TestEnv::from("soroban-init-boilerplate", |env| {
+        env.set_environments_toml(
+            r#"
+development.accounts = [
+    { name = "alice" },
+]
+
+[development.network]
+rpc-url = "http://localhost:8000/rpc"
+network-passphrase = "Standalone Network ; February 2017"
+
+[development.contracts]
+hello_wrold.client = true
+
+[production]
+network = "testnte"
+accounts = [
+    { name = "alice", default = true },
+    { name = "bob", default = true },
+]
+
+[production.contracts]
+hello_world.client = true
+"#,
+        );
+
+        let stderr = env
+            .loam("env")
+            .arg("check")
+            .assert()
+            .failure()
+            .stderr_as_str();
+        for problem in [
+            "development.contracts.hello_wrold: unknown contract `hello_wrold`, did you mean `hello_world`?",
+            "production.accounts: only one account can be the default, not 2",
+            "production.network: unknown network `testnte`, did you mean `testnet`?",
+            "production.contracts.hello_world: needs an `id` in production",
+        ] {
+            assert!(stderr.contains(problem), "{problem:?} not in {stderr}");
+        }
+    });
+}
+
+#[test]
+fn check_rejects_unknown_keys() {
+    TestEnv::from("soroban-init-boilerplate", |env| {
+        env.set_environments_toml(
+            r#"
+development.accounts = [
+    { name = "alice" },
+]
+development.network = "testnet"
+
+[development.contracts]
+hello_world.clinet = true
+"#,
+        );
+
+        let stderr = env
+            .loam("env")
+            .arg("check")
+            .assert()
+            .failure()
+            .stderr_as_str();
+        assert!(stderr.contains(
+            "development.contracts.hello_world: unknown key `clinet`, did you mean `client`?"
+        ));
+    });
+}
+
+#[test]
+fn check_reports_init_problems_with_line_numbers() {
+    TestEnv::from("soroban-init-boilerplate", |env| {
+        env.set_environments_toml(
+            r#"
+development.accounts = [
+    { name = "alice" },
+]
+
+[development.network]
+rpc-url = "http://localhost:8000/rpc"
+network-passphrase = "Standalone Network ; February 2017"
+
+[development.contracts.hello_world]
+init = """
+hello --to world
+helo --to world
+hello --too world
+"""
+"#,
+        );
+        Command::cargo_bin("loam")
+            .unwrap()
+            .current_dir(&env.cwd)
+            .args(["build", "--package", "hello-world"])
+            .assert()
+            .success();
+
+        let stderr = env
+            .loam("env")
+            .arg("check")
+            .assert()
+            .failure()
+            .stderr_as_str();
+        for problem in [
+            "development.contracts.hello_world.init: line 2: unknown function `helo`, did you mean `hello`?",
+            "development.contracts.hello_world.init: line 3: unknown argument `too`, did you mean `to`?",
+            "development.contracts.hello_world.init: line 3: missing argument `to`",
+        ] {
+            assert!(stderr.contains(problem), "{problem:?} not in {stderr}");
+        }
+        assert!(!stderr.contains("line 1"), "{stderr}");
+    });
+}
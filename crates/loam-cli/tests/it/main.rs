@@ -1,2 +1,5 @@
 mod build_clients;
+mod env;
+mod registry;
+mod ttl;
 mod util;
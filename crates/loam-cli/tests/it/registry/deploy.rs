@@ -0,0 +1,60 @@
+use crate::util::{AssertExt, TestEnv};
+
+#[test]
+fn deploy_published_package_with_init() {
+    TestEnv::from("soroban-init-boilerplate", |env| {
+        let registry = env.deploy_registry("alice");
+        env.loam_publish(&registry, "alice", "hello-world")
+            .assert()
+            .success();
+
+        let stderr = env
+            .loam("deploy")
+            .args([
+                "hello-world@0.0.0",
+                "--name",
+                "my-hello",
+                "--registry",
+                &registry,
+                "--source-account",
+                "alice",
+            ])
+            .args(TestEnv::network_args())
+            .args(["--", "hello", "--to", "world"])
+            .assert()
+            .success()
+            .stderr_as_str();
+        assert!(stderr.contains("deploying hello-world@0.0.0 as \"my-hello\""));
+        assert!(stderr.contains("initializing with hello"));
+        assert!(env.cwd.join(".stellar/contract-ids/my-hello.json").exists());
+
+        // the saved alias can be used to invoke the contract
+        let stdout = env
+            .soroban("contract")
+            .args(["invoke", "--id", "my-hello", "--source-account", "alice"])
+            .args(TestEnv::network_args())
+            .args(["--", "hello", "--to", "friend"])
+            .assert()
+            .success()
+            .stdout_as_str();
+        assert!(stdout.contains("friend"));
+
+        // names can only be claimed once
+        let stderr = env
+            .loam("deploy")
+            .args([
+                "hello-world",
+                "--name",
+                "my-hello",
+                "--registry",
+                &registry,
+                "--source-account",
+                "alice",
+            ])
+            .args(TestEnv::network_args())
+            .assert()
+            .failure()
+            .stderr_as_str();
+        assert!(stderr.contains("a contract has already been deployed with this name"));
+    });
+}
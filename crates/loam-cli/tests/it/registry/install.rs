@@ -0,0 +1,48 @@
+use crate::util::{AssertExt, TestEnv};
+
+#[test]
+fn install_deployed_contract() {
+    TestEnv::from("soroban-init-boilerplate", |env| {
+        let registry = env.deploy_registry("alice");
+        env.loam_publish(&registry, "alice", "hello-world")
+            .assert()
+            .success();
+        env.loam("deploy")
+            .args([
+                "hello-world",
+                "--name",
+                "their-hello",
+                "--registry",
+                &registry,
+                "--source-account",
+                "alice",
+            ])
+            .args(TestEnv::network_args())
+            .assert()
+            .success();
+
+        let stderr = env
+            .loam("install")
+            .args(["their-hello", "--alias", "hello", "--registry", &registry])
+            .args(TestEnv::network_args())
+            .env("LOAM_ENV", "development")
+            .assert()
+            .success()
+            .stderr_as_str();
+        assert!(stderr.contains("🎭 binding \"hello\" contract"));
+        assert!(stderr.contains("✅ installed \"hello\""));
+        assert!(env.cwd.join(".stellar/contract-ids/hello.json").exists());
+        assert!(env.cwd.join("packages/hello").exists());
+        let client = std::fs::read_to_string(env.cwd.join("src/contracts/hello.ts")).unwrap();
+        assert!(client.contains("allowHttp: true"));
+
+        let stderr = env
+            .loam("install")
+            .args(["nobody-deployed-this", "--registry", &registry])
+            .args(TestEnv::network_args())
+            .assert()
+            .failure()
+            .stderr_as_str();
+        assert!(stderr.contains("no contract named \"nobody-deployed-this\" has been deployed"));
+    });
+}
@@ -0,0 +1,2 @@
+mod deploy;
+mod install;
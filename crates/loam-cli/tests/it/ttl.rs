@@ -0,0 +1,116 @@
+use stellar_xdr::curr::{
+    ContractDataDurability, ContractDataEntry, ContractExecutable, ExtensionPoint, Hash,
+    LedgerEntryData, LedgerKey, LedgerKeyContractCode, LedgerKeyContractData, ScAddress,
+    ScContractInstance, ScVal,
+};
+
+use crate::util::{stub_rpc, AssertExt, TestEnv, LATEST_LEDGER};
+
+const PASSPHRASE: &str = "Standalone Network ; February 2017";
+
+fn instance(contract: [u8; 32], wasm: [u8; 32]) -> (LedgerKey, LedgerEntryData) {
+    let contract = ScAddress::Contract(Hash(contract));
+    let key = LedgerKey::ContractData(LedgerKeyContractData {
+        contract: contract.clone(),
+        key: ScVal::LedgerKeyContractInstance,
+        durability: ContractDataDurability::Persistent,
+    });
+    let data = LedgerEntryData::ContractData(ContractDataEntry {
+        ext: ExtensionPoint::V0,
+        contract,
+        key: ScVal::LedgerKeyContractInstance,
+        durability: ContractDataDurability::Persistent,
+        val: ScVal::ContractInstance(ScContractInstance {
+            executable: ContractExecutable::Wasm(Hash(wasm)),
+            storage: None,
+        }),
+    });
+    (key, data)
+}
+
+fn code(wasm: [u8; 32]) -> (LedgerKey, LedgerEntryData) {
+    let key = LedgerKey::ContractCode(LedgerKeyContractCode { hash: Hash(wasm) });
+    let data = LedgerEntryData::ContractCode(stellar_xdr::curr::ContractCodeEntry {
+        ext: stellar_xdr::curr::ContractCodeEntryExt::V0,
+        hash: Hash(wasm),
+        code: vec![0].try_into().unwrap(),
+    });
+    (key, data)
+}
+
+/// An environment with two deployed contracts, `hello_world`, whose instance has 100 ledgers
+/// left and wasm 49,000, and `archived`, which has neither
+fn deployed(env: &TestEnv) {
+    let (instance_key, instance_data) = instance([1; 32], [2; 32]);
+    let (code_key, code_data) = code([2; 32]);
+    let rpc_url = stub_rpc(vec![
+        (instance_key, instance_data, LATEST_LEDGER + 100),
+        (code_key, code_data, LATEST_LEDGER + 49_000),
+    ]);
+    env.set_environments_toml(format!(
+        r#"
+staging.accounts = [
+    {{ name = "alice" }},
+]
+
+[staging.network]
+rpc-url = "{rpc_url}"
+network-passphrase = "{PASSPHRASE}"
+
+[staging.contracts]
+hello_world.id = "{}"
+archived.id = "{}"
+"#,
+        stellar_strkey::Contract([1; 32]),
+        stellar_strkey::Contract([3; 32]),
+    ));
+}
+
+#[test]
+fn status() {
+    TestEnv::from("soroban-init-boilerplate", |env| {
+        deployed(env);
+        let stdout = env
+            .loam("ttl")
+            .args(["status", "staging", "--threshold", "1000"])
+            .assert()
+            .success()
+            .stdout_as_str();
+        let lines = stdout.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], format!("latest ledger: {LATEST_LEDGER}"));
+        assert!(lines[2].starts_with("archived") && lines[2].ends_with("🪦 archived"));
+        assert!(lines[3].starts_with("hello_world") && lines[3].contains("instance"));
+        assert!(lines[3].contains(" 1100 ") && lines[3].ends_with("100  ⚠️ below threshold"));
+        assert!(lines[4].starts_with("hello_world") && lines[4].contains("wasm"));
+        assert!(lines[4].ends_with(" 49000"));
+    });
+}
+
+#[test]
+fn extend_skips_entries_above_the_threshold() {
+    TestEnv::from("soroban-init-boilerplate", |env| {
+        deployed(env);
+        let stderr = env
+            .loam("ttl")
+            .args(["extend", "staging", "--threshold", "100"])
+            .assert()
+            .success()
+            .stderr_as_str();
+        assert!(stderr.contains("🪦 archived instance is archived"));
+        assert!(!stderr.contains("extending hello_world"));
+        assert!(stderr.contains("✅ extended 0 of 3 entries"));
+    });
+}
+
+#[test]
+fn needs_environments_toml() {
+    TestEnv::from("soroban-init-boilerplate", |env| {
+        let stderr = env
+            .loam("ttl")
+            .args(["status", "development"])
+            .assert()
+            .failure()
+            .stderr_as_str();
+        assert!(stderr.contains("no environments.toml found"));
+    });
+}
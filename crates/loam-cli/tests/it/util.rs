@@ -2,17 +2,23 @@ use assert_cmd::{assert::Assert, Command};
 use assert_fs::TempDir;
 use fs_extra::dir::{copy, CopyOptions};
 use rand::{thread_rng, Rng};
+use std::collections::HashMap;
 use std::env;
 use std::error::Error;
 use std::fs;
 use std::future::Future;
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
+use stellar_xdr::curr::{LedgerEntryData, LedgerKey, Limits, WriteXdr};
 use tokio::process::Command as ProcessCommand;
 use tokio::time::{sleep, timeout};
 use tokio_stream::StreamExt;
 use toml::Value;
-use walkdir::WalkDir;
+
+/// Latest ledger reported by [`stub_rpc`]
+pub const LATEST_LEDGER: u32 = 1_000;
 
 pub struct TestEnv {
     pub temp_dir: TempDir,
@@ -52,14 +58,14 @@ impl TestEnv {
         }
     }
 
-    pub fn find_binary(&self, name: &str) -> Option<PathBuf> {
+    pub fn find_binary(name: &str) -> Option<PathBuf> {
         let exe_path = env::current_exe().ok()?;
-        let project_root = self.find_project_root(&exe_path)?;
+        let project_root = Self::find_project_root(&exe_path)?;
         Some(project_root.join("target").join("bin").join(name))
     }
 
-    fn find_project_root(&self, start_path: &PathBuf) -> Option<PathBuf> {
-        let mut current = start_path.clone();
+    fn find_project_root(start_path: &Path) -> Option<PathBuf> {
+        let mut current = start_path.to_path_buf();
         while let Some(parent) = current.parent() {
             if parent.join("Cargo.toml").exists() {
                 return Some(parent.to_path_buf());
@@ -99,7 +105,7 @@ impl TestEnv {
                             return;
                         }
                     }
-                    Some(Err(e)) => println!("Error reading line: {:?}", e),
+                    Some(Err(e)) => println!("Error reading line: {e:?}"),
                     None => {
                         sleep(Duration::from_millis(100)).await;
                     }
@@ -229,6 +235,56 @@ impl TestEnv {
         soroban
     }
 
+    /// Arguments selecting the local network used by the tests
+    pub fn network_args() -> [&'static str; 4] {
+        [
+            "--rpc-url",
+            "http://localhost:8000/soroban/rpc",
+            "--network-passphrase",
+            "Standalone Network ; February 2017",
+        ]
+    }
+
+    /// Create and fund `account`, then build and deploy the example registry contract with it,
+    /// returning the contract's id
+    pub fn deploy_registry(&self, account: &str) -> String {
+        let repo_root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../..");
+        Command::cargo_bin("loam")
+            .unwrap()
+            .current_dir(&repo_root)
+            .args(["build", "--package", "example-registry"])
+            .assert()
+            .success();
+        self.soroban("keys")
+            .args(["generate", account])
+            .args(Self::network_args())
+            .assert()
+            .success();
+        self.soroban("contract")
+            .args(["deploy", "--source-account", account, "--wasm"])
+            .arg(repo_root.join("target/loam/example_registry.wasm"))
+            .args(Self::network_args())
+            .assert()
+            .success()
+            .stdout_as_str()
+    }
+
+    pub fn loam_publish(&self, registry: &str, account: &str, package: &str) -> Command {
+        let mut loam = Command::cargo_bin("loam").unwrap();
+        loam.current_dir(&self.cwd);
+        loam.args([
+            "publish",
+            "--package",
+            package,
+            "--registry",
+            registry,
+            "--source-account",
+            account,
+        ]);
+        loam.args(Self::network_args());
+        loam
+    }
+
     pub fn set_environments_toml(&self, contents: impl AsRef<[u8]>) {
         std::fs::write(self.cwd.join("environments.toml"), contents).unwrap();
     }
@@ -246,3 +302,68 @@ impl TestEnv {
         Ok(())
     }
 }
+
+/// An RPC server which only answers `getLedgerEntries`, with the entries it was given and the
+/// ledger they live until
+pub fn stub_rpc(entries: Vec<(LedgerKey, LedgerEntryData, u32)>) -> String {
+    let entries = entries
+        .into_iter()
+        .map(|(key, data, live_until)| {
+            (
+                key.to_xdr_base64(Limits::none()).unwrap(),
+                (data.to_xdr_base64(Limits::none()).unwrap(), live_until),
+            )
+        })
+        .collect::<HashMap<_, _>>();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let rpc_url = format!("http://{}/rpc", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { return };
+            let mut reader = BufReader::new(&mut stream);
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or_default() == 0 || line == "\r\n" {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap_or_default();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(request["method"], "getLedgerEntries");
+            let found = request["params"]["keys"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|key| {
+                    let key = key.as_str().unwrap();
+                    let (xdr, live_until) = entries.get(key)?;
+                    Some(serde_json::json!({
+                        "key": key,
+                        "xdr": xdr,
+                        "lastModifiedLedgerSeq": 1,
+                        "liveUntilLedgerSeq": live_until,
+                    }))
+                })
+                .collect::<Vec<_>>();
+            let body = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": { "entries": found, "latestLedger": LATEST_LEDGER },
+            })
+            .to_string();
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+        }
+    });
+    rpc_url
+}
@@ -110,17 +110,25 @@ pub fn derive_contract(args: TokenStream, item: TokenStream) -> TokenStream {
 }
 
 /// Generates a contract Client for a given asset.
-/// It is expected that the name of an asset, e.g. "native" or "USDC:G1...."
+/// It is expected that the name of an asset, e.g. "native" or "USDC:G...."
+///
+/// By default the address of the asset's contract is resolved at compile time for the network
+/// given by `STELLAR_NETWORK`. Passing `runtime`, e.g. `stellar_asset!("native", runtime)`,
+/// instead resolves it from the network id of the ledger, so the same wasm works on any network.
 ///
 /// # Panics
 ///
-
+/// This macro will panic if the asset is not valid.
 #[proc_macro]
 pub fn stellar_asset(input: TokenStream) -> TokenStream {
-    // Parse the input as a string literal
-    let input_str = syn::parse_macro_input!(input as syn::LitStr);
-    let network = std::env::var("STELLAR_NETWORK").unwrap_or_else(|_| "local".to_owned());
-    let asset = util::parse_asset_literal(&input_str, &network);
+    // Parse the input as a string literal with an optional `runtime` flag
+    let util::AssetInput { asset, runtime } = syn::parse_macro_input!(input as util::AssetInput);
+    let asset = if runtime {
+        util::parse_asset_literal_runtime(&asset)
+    } else {
+        let network = std::env::var("STELLAR_NETWORK").unwrap_or_else(|_| "local".to_owned());
+        util::parse_asset_literal(&asset, &network)
+    };
 
     // Return the generated code as a TokenStream
     asset.into()
@@ -160,6 +160,62 @@ pub fn parse_asset_literal(lit_str: &syn::LitStr, network: &str) -> TokenStream
     }
 }
 
+/// Input to `stellar_asset!`: an asset literal optionally followed by `, runtime`
+pub struct AssetInput {
+    pub asset: syn::LitStr,
+    pub runtime: bool,
+}
+
+impl syn::parse::Parse for AssetInput {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let asset = input.parse()?;
+        let mut runtime = false;
+        if input.parse::<Option<syn::Token![,]>>()?.is_some() && !input.is_empty() {
+            let flag: syn::Ident = input.parse()?;
+            if flag != "runtime" {
+                return Err(syn::Error::new(flag.span(), "expected `runtime`"));
+            }
+            runtime = true;
+            input.parse::<Option<syn::Token![,]>>()?;
+        }
+        Ok(AssetInput { asset, runtime })
+    }
+}
+
+// Generate the code to resolve the asset's contract address from the ledger's network id.
+// The asset itself is still parsed and validated at compile time.
+pub fn parse_asset_literal_runtime(lit_str: &syn::LitStr) -> TokenStream {
+    let asset = match parse_asset(&lit_str.value()).unwrap() {
+        xdr::Asset::Native => quote! { loam_sdk::soroban_sdk::StellarAsset::Native },
+        xdr::Asset::CreditAlphanum4(xdr::AlphaNum4 { asset_code, issuer }) => {
+            let code = asset_code.0;
+            let issuer = account_id_bytes(&issuer);
+            quote! {
+                loam_sdk::soroban_sdk::StellarAsset::CreditAlphanum4 {
+                    code: [#(#code),*],
+                    issuer: [#(#issuer),*],
+                }
+            }
+        }
+        xdr::Asset::CreditAlphanum12(xdr::AlphaNum12 { asset_code, issuer }) => {
+            let code = asset_code.0;
+            let issuer = account_id_bytes(&issuer);
+            quote! {
+                loam_sdk::soroban_sdk::StellarAsset::CreditAlphanum12 {
+                    code: [#(#code),*],
+                    issuer: [#(#issuer),*],
+                }
+            }
+        }
+    };
+    quote! { #asset.client() }
+}
+
+fn account_id_bytes(account_id: &xdr::AccountId) -> [u8; 32] {
+    let xdr::PublicKey::PublicKeyTypeEd25519(xdr::Uint256(bytes)) = &account_id.0;
+    *bytes
+}
+
 #[allow(unused)]
 pub(crate) fn equal_tokens(expected: &TokenStream, actual: &TokenStream) {
     assert_eq!(
@@ -211,6 +267,27 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_generate_asset_runtime_code() {
+        let client = parse_asset_literal_runtime(&syn::LitStr::new(
+            "native",
+            proc_macro2::Span::call_site(),
+        ));
+        assert_eq!(
+            client.to_string(),
+            quote! { loam_sdk::soroban_sdk::StellarAsset::Native.client() }.to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_asset_input() {
+        let input: AssetInput = syn::parse_quote!("native", runtime);
+        assert!(input.runtime);
+        let input: AssetInput = syn::parse_quote!("native");
+        assert!(!input.runtime);
+        assert!(syn::parse_str::<AssetInput>(r#""native", compile"#).is_err());
+    }
+
     #[test]
     fn test_generate_asset_id_code() {
         let asset_id = parse_asset_literal(
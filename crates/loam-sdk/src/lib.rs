@@ -1,6 +1,8 @@
 #![no_std]
 pub use loam_sdk_macro::*;
 
+#[cfg(feature = "loam-soroban-sdk")]
+pub mod persistent;
 #[cfg(feature = "loam-soroban-sdk")]
 pub mod soroban_sdk;
 
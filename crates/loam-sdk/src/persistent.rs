@@ -0,0 +1,44 @@
+//! Persistent storage whose entries are kept alive by being used, for subcontracts whose entries
+//! must not expire while a contract still relies on them
+use crate::soroban_sdk::{env, Env, IntoVal, TryFromVal, Val};
+
+/// About a day, at five seconds per ledger
+const DAY_IN_LEDGERS: u32 = 17_280;
+
+/// Entries live for about a month after they were last read or written
+pub const EXTEND_TO: u32 = 30 * DAY_IN_LEDGERS;
+
+/// Entries are only extended once they have less than this many ledgers left, so that most
+/// calls don't pay for extending them again
+pub const THRESHOLD: u32 = EXTEND_TO - DAY_IN_LEDGERS;
+
+/// Read a persistent entry, extending its TTL so that entries in use don't expire
+pub fn get<K, V>(key: &K) -> Option<V>
+where
+    K: IntoVal<Env, Val>,
+    V: TryFromVal<Env, Val>,
+{
+    let storage = env().storage().persistent();
+    let value = storage.get(key)?;
+    storage.extend_ttl(key, THRESHOLD, EXTEND_TO);
+    Some(value)
+}
+
+/// Write a persistent entry, extending its TTL
+pub fn set<K, V>(key: &K, value: &V)
+where
+    K: IntoVal<Env, Val>,
+    V: IntoVal<Env, Val>,
+{
+    let storage = env().storage().persistent();
+    storage.set(key, value);
+    storage.extend_ttl(key, THRESHOLD, EXTEND_TO);
+}
+
+/// Remove a persistent entry
+pub fn remove<K>(key: &K)
+where
+    K: IntoVal<Env, Val>,
+{
+    env().storage().persistent().remove(key);
+}
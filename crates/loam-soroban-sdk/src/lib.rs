@@ -2,8 +2,10 @@
 
 pub use soroban_sdk::*;
 pub mod into_key;
+pub mod stellar_asset;
 
 pub use into_key::IntoKey;
+pub use stellar_asset::StellarAsset;
 
 /// Trait for loading and setting a singleton type
 pub trait Lazy: Sized {
@@ -27,7 +29,7 @@ pub fn set_env(env: Env) {
 /// function is called in normal operation.
 #[must_use]
 pub fn env() -> &'static Env {
-    unsafe { ENV.as_ref().unwrap() }
+    unsafe { (*core::ptr::addr_of!(ENV)).as_ref().unwrap() }
 }
 
 impl<T> Lazy for T
@@ -0,0 +1,269 @@
+use core::str::FromStr;
+
+use crate::{env, token, Address, Bytes, BytesN, Env};
+
+/// `ENVELOPE_TYPE_CONTRACT_ID` discriminant of `HashIdPreimage`
+const ENVELOPE_TYPE_CONTRACT_ID: u32 = 8;
+/// `CONTRACT_ID_PREIMAGE_FROM_ASSET` discriminant of `ContractIdPreimage`
+const CONTRACT_ID_PREIMAGE_FROM_ASSET: u32 = 1;
+/// `PUBLIC_KEY_TYPE_ED25519` discriminant of `PublicKey`
+const PUBLIC_KEY_TYPE_ED25519: u32 = 0;
+
+/// Strkey version byte for `G...` account ids
+const VERSION_ACCOUNT_ID: u8 = 6 << 3;
+/// Strkey version byte for `C...` contract ids
+const VERSION_CONTRACT: u8 = 2 << 3;
+
+/// A classic Stellar asset, whose Stellar Asset Contract (SAC) address is resolved at runtime.
+///
+/// Unlike `stellar_asset!`, which bakes the SAC address for the network given by `STELLAR_NETWORK`
+/// into the wasm at compile time, the address is derived from the network id of the ledger the
+/// contract is executing on. This allows the same wasm to be deployed to any network.
+///
+/// ```ignore
+/// let usdc: StellarAsset = "USDC:GA5ZSEJYB37JRC5AVCIA5MOP4RHTM335X2KGX3IHOJAPP5RE34K4KZVN".parse().unwrap();
+/// usdc.client().balance(&env().current_contract_address());
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StellarAsset {
+    Native,
+    CreditAlphanum4 { code: [u8; 4], issuer: [u8; 32] },
+    CreditAlphanum12 { code: [u8; 12], issuer: [u8; 32] },
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// Expected either `native` or `CODE:ISSUER`
+    Malformed,
+    /// Asset code must be 1-12 alphanumeric characters
+    InvalidCode,
+    /// Issuer must be a valid `G...` account id
+    InvalidIssuer,
+}
+
+impl StellarAsset {
+    /// The asset serialized as `Asset` XDR
+    pub fn to_xdr(&self, env: &Env) -> Bytes {
+        let mut bytes = Bytes::new(env);
+        match self {
+            StellarAsset::Native => bytes.extend_from_array(&0u32.to_be_bytes()),
+            StellarAsset::CreditAlphanum4 { code, issuer } => {
+                bytes.extend_from_array(&1u32.to_be_bytes());
+                bytes.extend_from_array(code);
+                bytes.extend_from_array(&PUBLIC_KEY_TYPE_ED25519.to_be_bytes());
+                bytes.extend_from_array(issuer);
+            }
+            StellarAsset::CreditAlphanum12 { code, issuer } => {
+                bytes.extend_from_array(&2u32.to_be_bytes());
+                bytes.extend_from_array(code);
+                bytes.extend_from_array(&PUBLIC_KEY_TYPE_ED25519.to_be_bytes());
+                bytes.extend_from_array(issuer);
+            }
+        }
+        bytes
+    }
+
+    /// Derive the SAC contract id of the asset on the network with the given id
+    pub fn contract_id(&self, env: &Env, network_id: &BytesN<32>) -> BytesN<32> {
+        let mut preimage = Bytes::new(env);
+        preimage.extend_from_array(&ENVELOPE_TYPE_CONTRACT_ID.to_be_bytes());
+        preimage.extend_from_array(&network_id.to_array());
+        preimage.extend_from_array(&CONTRACT_ID_PREIMAGE_FROM_ASSET.to_be_bytes());
+        preimage.append(&self.to_xdr(env));
+        env.crypto().sha256(&preimage).to_bytes()
+    }
+
+    /// Address of the asset's SAC on the network the contract is currently executing on
+    pub fn address(&self) -> Address {
+        let env = env();
+        let contract_id = self.contract_id(env, &env.ledger().network_id());
+        let strkey = strkey::encode(VERSION_CONTRACT, &contract_id.to_array());
+        Address::from_string_bytes(&Bytes::from_array(env, &strkey))
+    }
+
+    /// Token client for the asset's SAC on the network the contract is currently executing on
+    pub fn client(&self) -> token::Client<'static> {
+        token::Client::new(env(), &self.address())
+    }
+}
+
+impl FromStr for StellarAsset {
+    type Err = Error;
+
+    /// Parse an asset of the form `native` or `CODE:ISSUER`, e.g. `USDC:G...`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "native" {
+            return Ok(StellarAsset::Native);
+        }
+        let (code, issuer) = s.split_once(':').ok_or(Error::Malformed)?;
+        if code.is_empty() || code.len() > 12 || !code.bytes().all(|b| b.is_ascii_alphanumeric()) {
+            return Err(Error::InvalidCode);
+        }
+        let issuer = strkey::decode(VERSION_ACCOUNT_ID, issuer).ok_or(Error::InvalidIssuer)?;
+        Ok(if code.len() <= 4 {
+            let mut padded = [0u8; 4];
+            padded[..code.len()].copy_from_slice(code.as_bytes());
+            StellarAsset::CreditAlphanum4 {
+                code: padded,
+                issuer,
+            }
+        } else {
+            let mut padded = [0u8; 12];
+            padded[..code.len()].copy_from_slice(code.as_bytes());
+            StellarAsset::CreditAlphanum12 {
+                code: padded,
+                issuer,
+            }
+        })
+    }
+}
+
+/// Minimal strkey support for 32 byte payloads, which is all that is needed to go between raw
+/// account and contract ids and their `G...`/`C...` string forms without an allocator.
+mod strkey {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+    /// Version byte + 32 byte payload + 2 byte checksum
+    const RAW_LEN: usize = 35;
+    /// `RAW_LEN` bytes base32 encoded without padding
+    pub const LEN: usize = 56;
+
+    fn crc16_xmodem(data: &[u8]) -> u16 {
+        let mut crc: u16 = 0;
+        for byte in data {
+            crc ^= u16::from(*byte) << 8;
+            for _ in 0..8 {
+                crc = if crc & 0x8000 == 0 {
+                    crc << 1
+                } else {
+                    (crc << 1) ^ 0x1021
+                };
+            }
+        }
+        crc
+    }
+
+    pub fn encode(version: u8, payload: &[u8; 32]) -> [u8; LEN] {
+        let mut raw = [0u8; RAW_LEN];
+        raw[0] = version;
+        raw[1..33].copy_from_slice(payload);
+        let checksum = crc16_xmodem(&raw[..33]).to_le_bytes();
+        raw[33..].copy_from_slice(&checksum);
+
+        let mut out = [0u8; LEN];
+        let mut buffer: u32 = 0;
+        let mut bits = 0;
+        let mut i = 0;
+        for byte in raw {
+            buffer = (buffer << 8) | u32::from(byte);
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                out[i] = ALPHABET[((buffer >> bits) & 0x1f) as usize];
+                i += 1;
+            }
+            buffer &= (1 << bits) - 1;
+        }
+        out
+    }
+
+    pub fn decode(version: u8, s: &str) -> Option<[u8; 32]> {
+        if s.len() != LEN {
+            return None;
+        }
+        let mut raw = [0u8; RAW_LEN];
+        let mut buffer: u32 = 0;
+        let mut bits = 0;
+        let mut i = 0;
+        for c in s.bytes() {
+            let value = ALPHABET.iter().position(|a| *a == c)?;
+            buffer = (buffer << 5) | u32::try_from(value).ok()?;
+            bits += 5;
+            if bits >= 8 {
+                bits -= 8;
+                raw[i] = u8::try_from((buffer >> bits) & 0xff).ok()?;
+                i += 1;
+            }
+            buffer &= (1 << bits) - 1;
+        }
+        let checksum = crc16_xmodem(&raw[..33]).to_le_bytes();
+        if raw[0] != version || raw[33..] != checksum {
+            return None;
+        }
+        let mut payload = [0u8; 32];
+        payload.copy_from_slice(&raw[1..33]);
+        Some(payload)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{set_env, testutils::Ledger, String};
+
+    const LOCAL: &str = "Standalone Network ; February 2017";
+    const ISSUER: &str = "GA5ZSEJYB37JRC5AVCIA5MOP4RHTM335X2KGX3IHOJAPP5RE34K4KZVN";
+
+    fn env_on_network(passphrase: &str) -> Env {
+        let env = Env::default();
+        let network_id = env
+            .crypto()
+            .sha256(&Bytes::from_slice(&env, passphrase.as_bytes()))
+            .to_bytes();
+        env.ledger().set_network_id(network_id.to_array());
+        set_env(env.clone());
+        env
+    }
+
+    #[test]
+    fn native_matches_compile_time_id() {
+        let env = env_on_network(LOCAL);
+        assert_eq!(
+            StellarAsset::Native.address().to_string(),
+            String::from_str(
+                &env,
+                "CDMLFMKMMD7MWZP3FKUBZPVHTUEDLSX4BYGYKH4GCESXYHS3IHQ4EIG4"
+            )
+        );
+    }
+
+    #[test]
+    fn matches_deployer() {
+        let env = env_on_network(LOCAL);
+        for asset in [
+            "native",
+            "USDC:GA5ZSEJYB37JRC5AVCIA5MOP4RHTM335X2KGX3IHOJAPP5RE34K4KZVN",
+            "LONGCODE:GA5ZSEJYB37JRC5AVCIA5MOP4RHTM335X2KGX3IHOJAPP5RE34K4KZVN",
+        ] {
+            let asset: StellarAsset = asset.parse().unwrap();
+            let expected = env
+                .deployer()
+                .with_stellar_asset(asset.to_xdr(&env))
+                .deployed_address();
+            assert_eq!(asset.address(), expected);
+        }
+    }
+
+    #[test]
+    fn strkey_round_trip() {
+        let issuer = strkey::decode(VERSION_ACCOUNT_ID, ISSUER).unwrap();
+        assert_eq!(
+            &strkey::encode(VERSION_ACCOUNT_ID, &issuer),
+            ISSUER.as_bytes()
+        );
+        assert!(strkey::decode(VERSION_CONTRACT, ISSUER).is_none());
+    }
+
+    #[test]
+    fn invalid_assets() {
+        assert_eq!("USDC".parse::<StellarAsset>(), Err(Error::Malformed));
+        assert_eq!(
+            "TOOLONGASSETCODE:G".parse::<StellarAsset>(),
+            Err(Error::InvalidCode)
+        );
+        assert_eq!(
+            "USDC:GABC".parse::<StellarAsset>(),
+            Err(Error::InvalidIssuer)
+        );
+    }
+}
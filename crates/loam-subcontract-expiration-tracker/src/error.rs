@@ -0,0 +1,19 @@
+use loam_sdk::soroban_sdk::{self, contracterror};
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum TrackerError {
+    /// The entry is already tracked
+    AlreadyTracked = 1,
+    /// The entry is not tracked
+    NotTracked = 2,
+    /// Target TTLs must be positive and at most the network's maximum TTL
+    InvalidTtl = 3,
+    /// Amounts and rewards can't be negative
+    InvalidAmount = 4,
+    /// Half of the target TTL must have passed since the entry was last extended
+    TooEarly = 5,
+    /// The prepaid balance of the entry can't cover the reward for extending it
+    InsufficientBalance = 6,
+}
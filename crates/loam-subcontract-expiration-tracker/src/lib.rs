@@ -0,0 +1,9 @@
+#![no_std]
+// For the full paths in the signatures of the copied subcontract methods, as in the registry
+extern crate self as loam_subcontract_expiration_tracker;
+
+pub mod error;
+pub mod tracker;
+
+pub use error::TrackerError;
+pub use tracker::{ExpirationTracker, Trackable, Tracked, Tracking};
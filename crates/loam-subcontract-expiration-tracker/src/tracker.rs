@@ -0,0 +1,286 @@
+use loam_sdk::{
+    persistent,
+    soroban_sdk::{self, contracttype, env, token, Address, BytesN, Lazy, Vec},
+    subcontract,
+};
+
+use crate::TrackerError;
+
+/// A ledger entry whose lifetime can be extended by a contract
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Tracked {
+    /// A contract's instance, along with the Wasm it runs
+    Contract(Address),
+    /// An installed Wasm, which is extended through a contract running it. Contracts can't
+    /// look up the Wasm of another contract, so the hash is not checked: it only labels the
+    /// entry, and extending it extends whichever Wasm the contract runs at the time.
+    Wasm(BytesN<32>, Address),
+}
+
+/// How a tracked entry is kept alive, and who pays for it
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Tracking {
+    /// Account which started tracking the entry, and which is refunded when it stops
+    pub sponsor: Address,
+    /// Token of the prepaid balance
+    pub token: Address,
+    pub balance: i128,
+    /// Number of ledgers the entry is extended to live for
+    pub target_ttl: u32,
+    /// Paid out of the balance to whoever extends the entry
+    pub reward: i128,
+    /// Ledger the entry was last extended in
+    pub last_extended: Option<u32>,
+}
+
+/// Tracked entries are numbered from 0 to `Count`, so that they can be listed without keeping
+/// them all in one ever growing entry
+#[contracttype(export = false)]
+pub enum DataKey {
+    Tracking(Tracked),
+    /// Number of the tracked entry
+    Position(Tracked),
+    /// Tracked entry by number
+    Entry(u32),
+    Count,
+}
+
+/// Keeps track of entries and their prepaid balances, and rewards anyone extending them
+#[derive(Lazy, Default)]
+pub struct ExpirationTracker;
+
+impl ExpirationTracker {
+    fn get(entry: &Tracked) -> Result<Tracking, TrackerError> {
+        persistent::get(&DataKey::Tracking(entry.clone())).ok_or(TrackerError::NotTracked)
+    }
+
+    fn set(entry: Tracked, tracking: &Tracking) {
+        persistent::set(&DataKey::Tracking(entry), tracking);
+    }
+
+    fn count() -> u32 {
+        persistent::get(&DataKey::Count).unwrap_or_default()
+    }
+
+    /// Number a newly tracked entry after the others
+    fn add(entry: Tracked) {
+        let count = Self::count();
+        persistent::set(&DataKey::Entry(count), &entry);
+        persistent::set(&DataKey::Position(entry), &count);
+        persistent::set(&DataKey::Count, &(count + 1));
+    }
+
+    /// Forget the number of an entry, giving it to the last entry instead
+    fn remove(entry: Tracked) {
+        let position: u32 = persistent::get(&DataKey::Position(entry.clone()))
+            .expect("tracked entries are numbered");
+        let last = Self::count() - 1;
+        if position != last {
+            let moved: Tracked =
+                persistent::get(&DataKey::Entry(last)).expect("tracked entries are numbered");
+            persistent::set(&DataKey::Entry(position), &moved);
+            persistent::set(&DataKey::Position(moved), &position);
+        }
+        persistent::remove(&DataKey::Entry(last));
+        persistent::remove(&DataKey::Position(entry));
+        persistent::set(&DataKey::Count, &last);
+    }
+
+    fn deposit(token: &Address, from: &Address, amount: i128) -> Result<(), TrackerError> {
+        if amount < 0 {
+            return Err(TrackerError::InvalidAmount);
+        }
+        if amount > 0 {
+            token::Client::new(env(), token).transfer(
+                from,
+                &env().current_contract_address(),
+                &amount,
+            );
+        }
+        Ok(())
+    }
+
+    fn pay(token: &Address, to: &Address, amount: i128) {
+        if amount > 0 {
+            token::Client::new(env(), token).transfer(
+                &env().current_contract_address(),
+                to,
+                &amount,
+            );
+        }
+    }
+}
+
+#[subcontract]
+pub trait IsTrackable {
+    /// Start tracking `entry`, prepaying `amount` of `token` from `sponsor`. Whoever extends the
+    /// entry to live for `target_ttl` more ledgers is paid `reward` out of the prepaid balance.
+    /// The hash of a `Tracked::Wasm` is taken on trust, so check that its contract runs it.
+    fn track(
+        &mut self,
+        entry: loam_subcontract_expiration_tracker::Tracked,
+        sponsor: loam_sdk::soroban_sdk::Address,
+        token: loam_sdk::soroban_sdk::Address,
+        amount: i128,
+        target_ttl: u32,
+        reward: i128,
+    ) -> Result<(), loam_subcontract_expiration_tracker::TrackerError>;
+
+    /// Add `amount` to the prepaid balance of a tracked entry. Anyone can top up any entry.
+    fn top_up(
+        &mut self,
+        entry: loam_subcontract_expiration_tracker::Tracked,
+        from: loam_sdk::soroban_sdk::Address,
+        amount: i128,
+    ) -> Result<(), loam_subcontract_expiration_tracker::TrackerError>;
+
+    /// Stop tracking an entry, refunding its balance to its sponsor, who must authorize it
+    fn untrack(
+        &mut self,
+        entry: loam_subcontract_expiration_tracker::Tracked,
+    ) -> Result<(), loam_subcontract_expiration_tracker::TrackerError>;
+
+    /// Extend a tracked entry to live for its target TTL, paying its reward to `keeper`.
+    /// Can only be done once half of its target TTL has passed since it was last extended.
+    fn extend(
+        &mut self,
+        entry: loam_subcontract_expiration_tracker::Tracked,
+        keeper: loam_sdk::soroban_sdk::Address,
+    ) -> Result<i128, loam_subcontract_expiration_tracker::TrackerError>;
+
+    /// Sponsor, balance and target TTL of a tracked entry
+    fn tracking(
+        &self,
+        entry: loam_subcontract_expiration_tracker::Tracked,
+    ) -> Result<
+        loam_subcontract_expiration_tracker::Tracking,
+        loam_subcontract_expiration_tracker::TrackerError,
+    >;
+
+    /// Up to `limit` tracked entries, starting from the `start`th. Untracking an entry moves the
+    /// last one into its place.
+    fn tracked(
+        &self,
+        start: u32,
+        limit: u32,
+    ) -> loam_sdk::soroban_sdk::Vec<loam_subcontract_expiration_tracker::Tracked>;
+
+    /// Number of tracked entries
+    fn tracked_count(&self) -> u32;
+}
+
+impl IsTrackable for ExpirationTracker {
+    fn track(
+        &mut self,
+        entry: Tracked,
+        sponsor: Address,
+        token: Address,
+        amount: i128,
+        target_ttl: u32,
+        reward: i128,
+    ) -> Result<(), TrackerError> {
+        sponsor.require_auth();
+        if Self::get(&entry).is_ok() {
+            return Err(TrackerError::AlreadyTracked);
+        }
+        if target_ttl == 0 || target_ttl > env().storage().max_ttl() {
+            return Err(TrackerError::InvalidTtl);
+        }
+        if reward < 0 {
+            return Err(TrackerError::InvalidAmount);
+        }
+        Self::deposit(&token, &sponsor, amount)?;
+        Self::set(
+            entry.clone(),
+            &Tracking {
+                sponsor,
+                token,
+                balance: amount,
+                target_ttl,
+                reward,
+                last_extended: None,
+            },
+        );
+        Self::add(entry);
+        Ok(())
+    }
+
+    fn top_up(&mut self, entry: Tracked, from: Address, amount: i128) -> Result<(), TrackerError> {
+        from.require_auth();
+        let mut tracking = Self::get(&entry)?;
+        Self::deposit(&tracking.token, &from, amount)?;
+        tracking.balance += amount;
+        Self::set(entry, &tracking);
+        Ok(())
+    }
+
+    fn untrack(&mut self, entry: Tracked) -> Result<(), TrackerError> {
+        let tracking = Self::get(&entry)?;
+        tracking.sponsor.require_auth();
+        Self::pay(&tracking.token, &tracking.sponsor, tracking.balance);
+        persistent::remove(&DataKey::Tracking(entry.clone()));
+        Self::remove(entry);
+        Ok(())
+    }
+
+    fn extend(&mut self, entry: Tracked, keeper: Address) -> Result<i128, TrackerError> {
+        let mut tracking = Self::get(&entry)?;
+        let sequence = env().ledger().sequence();
+        if let Some(last_extended) = tracking.last_extended {
+            if sequence < last_extended + tracking.target_ttl / 2 {
+                return Err(TrackerError::TooEarly);
+            }
+        }
+        if tracking.balance < tracking.reward {
+            return Err(TrackerError::InsufficientBalance);
+        }
+        let ttl = tracking.target_ttl;
+        match &entry {
+            Tracked::Contract(contract) => env().deployer().extend_ttl(contract.clone(), ttl, ttl),
+            Tracked::Wasm(_, contract) => {
+                env()
+                    .deployer()
+                    .extend_ttl_for_code(contract.clone(), ttl, ttl);
+            }
+        }
+        // The tracker has to outlive what it tracks
+        env().storage().instance().extend_ttl(ttl, ttl);
+        let position: u32 = persistent::get(&DataKey::Position(entry.clone()))
+            .expect("tracked entries are numbered");
+        for key in [
+            DataKey::Tracking(entry.clone()),
+            DataKey::Position(entry.clone()),
+            DataKey::Entry(position),
+            DataKey::Count,
+        ] {
+            env().storage().persistent().extend_ttl(&key, ttl, ttl);
+        }
+        Self::pay(&tracking.token, &keeper, tracking.reward);
+        tracking.balance -= tracking.reward;
+        tracking.last_extended = Some(sequence);
+        let reward = tracking.reward;
+        Self::set(entry, &tracking);
+        Ok(reward)
+    }
+
+    fn tracking(&self, entry: Tracked) -> Result<Tracking, TrackerError> {
+        Self::get(&entry)
+    }
+
+    fn tracked(&self, start: u32, limit: u32) -> Vec<Tracked> {
+        let end = start.saturating_add(limit).min(Self::count());
+        let mut tracked = Vec::new(env());
+        for i in start..end {
+            tracked.push_back(
+                persistent::get(&DataKey::Entry(i)).expect("tracked entries are numbered"),
+            );
+        }
+        tracked
+    }
+
+    fn tracked_count(&self) -> u32 {
+        Self::count()
+    }
+}
@@ -11,7 +11,7 @@ use loam_sdk::{soroban_sdk::Lazy, subcontract};
 /// supporting standard token functionality without the specialized behaviors of Stellar Assets.
 /// This allows for greater flexibility and interoperability among different token implementations.
 ///
-/// For full specification, see: https://github.com/stellar/stellar-protocol/blob/master/ecosystem/sep-0041.md
+/// For full specification, see: <https://github.com/stellar/stellar-protocol/blob/master/ecosystem/sep-0041.md>
 #[subcontract]
 pub trait IsSep41 {
     /// Returns the allowance for `spender` to transfer from `from`.
@@ -0,0 +1,168 @@
+use loam_sdk::{
+    persistent,
+    soroban_sdk::{
+        self, contracttype, env, symbol_short, xdr::ToXdr, Address, BytesN, IntoVal, InvokeError,
+        Lazy, String, Symbol, Val, Vec,
+    },
+    subcontract,
+};
+
+use crate::{
+    wasm::{validate_name, IsPublishable, WasmRegistry},
+    RegistryError,
+};
+
+/// A contract deployed by the registry
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Deployment {
+    pub contract_id: Address,
+    pub owner: Address,
+    /// Package and version the contract's current wasm was published as
+    pub wasm_name: String,
+    pub version: String,
+}
+
+#[contracttype(export = false)]
+pub enum DataKey {
+    Contract(String),
+}
+
+/// Deploys contracts from the packages published to the [`WasmRegistry`] of the same contract
+#[derive(Lazy, Default)]
+pub struct ContractRegistry;
+
+impl ContractRegistry {
+    fn deployment(contract_name: &String) -> Result<Deployment, RegistryError> {
+        persistent::get(&DataKey::Contract(contract_name.clone()))
+            .ok_or(RegistryError::NoSuchContract)
+    }
+
+    fn set_deployment(contract_name: String, deployment: &Deployment) {
+        persistent::set(&DataKey::Contract(contract_name), deployment);
+    }
+}
+
+#[subcontract]
+pub trait IsDeployable {
+    /// Deploy `wasm_name@version`, or its latest version, as a contract named `contract_name`,
+    /// owned by `owner`.
+    /// If the contract has a Core subcontract, `owner` is made its admin, so that it can be
+    /// redeployed. `init_fn` is then invoked with `init_args`, in the same transaction.
+    fn deploy_contract(
+        &mut self,
+        contract_name: loam_sdk::soroban_sdk::String,
+        wasm_name: loam_sdk::soroban_sdk::String,
+        version: Option<loam_sdk::soroban_sdk::String>,
+        owner: loam_sdk::soroban_sdk::Address,
+        init_fn: Option<loam_sdk::soroban_sdk::Symbol>,
+        init_args: Option<loam_sdk::soroban_sdk::Vec<loam_sdk::soroban_sdk::Val>>,
+    ) -> Result<loam_sdk::soroban_sdk::Address, loam_subcontract_registry::RegistryError>;
+
+    /// Redeploy a contract with another version of the package it was deployed from, using
+    /// its Core subcontract's `redeploy`. Must be authorized by the contract's owner.
+    fn redeploy_contract(
+        &mut self,
+        contract_name: loam_sdk::soroban_sdk::String,
+        version: Option<loam_sdk::soroban_sdk::String>,
+    ) -> Result<(), loam_subcontract_registry::RegistryError>;
+
+    /// Id of a deployed contract
+    fn fetch_contract_id(
+        &self,
+        contract_name: loam_sdk::soroban_sdk::String,
+    ) -> Result<loam_sdk::soroban_sdk::Address, loam_subcontract_registry::RegistryError>;
+
+    /// Id, owner and package of a deployed contract
+    fn fetch_contract(
+        &self,
+        contract_name: loam_sdk::soroban_sdk::String,
+    ) -> Result<loam_subcontract_registry::Deployment, loam_subcontract_registry::RegistryError>;
+}
+
+impl IsDeployable for ContractRegistry {
+    fn deploy_contract(
+        &mut self,
+        contract_name: String,
+        wasm_name: String,
+        version: Option<String>,
+        owner: Address,
+        init_fn: Option<Symbol>,
+        init_args: Option<Vec<Val>>,
+    ) -> Result<Address, RegistryError> {
+        validate_name(&contract_name)?;
+        owner.require_auth();
+        if Self::deployment(&contract_name).is_ok() {
+            return Err(RegistryError::AlreadyDeployed);
+        }
+        let release = WasmRegistry.fetch(wasm_name.clone(), version)?;
+        let env = env();
+        // Names are unique, so they can be used as the salt of the contract id
+        let salt: BytesN<32> = env
+            .crypto()
+            .sha256(&contract_name.clone().to_xdr(env))
+            .into();
+        let contract_id = env
+            .deployer()
+            .with_current_contract(salt)
+            .deploy(release.wasm_hash);
+        // Contracts without a Core subcontract can't be redeployed, but can still be deployed.
+        // The host reports a missing function like any other failed call, so a failure is only
+        // tolerated if the contract has no `admin_get` either.
+        let admin_set = env.try_invoke_contract::<(), InvokeError>(
+            &contract_id,
+            &symbol_short!("admin_set"),
+            (owner.clone(),).into_val(env),
+        );
+        if admin_set.is_err()
+            && env
+                .try_invoke_contract::<Option<Address>, InvokeError>(
+                    &contract_id,
+                    &symbol_short!("admin_get"),
+                    Vec::new(env),
+                )
+                .is_ok()
+        {
+            return Err(RegistryError::AdminNotSet);
+        }
+        if let Some(init_fn) = init_fn {
+            env.invoke_contract::<Val>(&contract_id, &init_fn, init_args.unwrap_or(Vec::new(env)));
+        }
+        Self::set_deployment(
+            contract_name,
+            &Deployment {
+                contract_id: contract_id.clone(),
+                owner,
+                wasm_name,
+                version: release.version,
+            },
+        );
+        Ok(contract_id)
+    }
+
+    fn redeploy_contract(
+        &mut self,
+        contract_name: String,
+        version: Option<String>,
+    ) -> Result<(), RegistryError> {
+        let mut deployment = Self::deployment(&contract_name)?;
+        deployment.owner.require_auth();
+        let release = WasmRegistry.fetch(deployment.wasm_name.clone(), version)?;
+        env().invoke_contract::<()>(
+            &deployment.contract_id,
+            &symbol_short!("redeploy"),
+            (release.wasm_hash,).into_val(env()),
+        );
+        deployment.version = release.version;
+        Self::set_deployment(contract_name, &deployment);
+        Ok(())
+    }
+
+    fn fetch_contract_id(&self, contract_name: String) -> Result<Address, RegistryError> {
+        Ok(Self::deployment(&contract_name)?.contract_id)
+    }
+
+    fn fetch_contract(&self, contract_name: String) -> Result<Deployment, RegistryError> {
+        Self::deployment(&contract_name)
+    }
+}
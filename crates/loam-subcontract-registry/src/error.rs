@@ -0,0 +1,27 @@
+use loam_sdk::soroban_sdk::{self, contracterror};
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum RegistryError {
+    /// Names must be 1-64 lowercase letters, digits, `-` or `_`
+    InvalidName = 1,
+    /// Versions must be of the form `MAJOR.MINOR.PATCH`
+    InvalidVersion = 2,
+    /// No package has been published with the given name
+    NoSuchPackage = 3,
+    /// The package has no such version
+    NoSuchVersion = 4,
+    /// The version has already been published
+    AlreadyPublished = 5,
+    /// Only the owner of a package can publish new versions of it or change it
+    NotOwner = 6,
+    /// Every version of the package has been yanked
+    AllVersionsYanked = 7,
+    /// A contract has already been deployed with the given name
+    AlreadyDeployed = 8,
+    /// No contract has been deployed with the given name
+    NoSuchContract = 9,
+    /// The contract has a Core subcontract, but making its owner the admin failed
+    AdminNotSet = 10,
+}
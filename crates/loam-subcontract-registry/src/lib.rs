@@ -0,0 +1,14 @@
+#![no_std]
+// Subcontract methods are copied into the contract using them, so types in their signatures
+// are referred to by their full path, which must also resolve within this crate.
+extern crate self as loam_subcontract_registry;
+
+pub mod contract;
+pub mod error;
+pub mod version;
+pub mod wasm;
+
+pub use contract::{ContractRegistry, Deployable, Deployment};
+pub use error::RegistryError;
+pub use version::Version;
+pub use wasm::{Publishable, Release, WasmRegistry};
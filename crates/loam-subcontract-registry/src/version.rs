@@ -0,0 +1,52 @@
+use loam_sdk::soroban_sdk::{self, contracttype, String};
+
+use crate::RegistryError;
+
+/// Longest version string accepted, e.g. `4294967295.4294967295.4294967295` is 32 bytes
+const MAX_LEN: usize = 32;
+
+/// A `MAJOR.MINOR.PATCH` semantic version, ordered by precedence
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    /// Parse a version such as `1.2.3`. Pre-release and build metadata are not supported.
+    ///
+    /// # Errors
+    /// `InvalidVersion` if `version` is not three dot separated numbers without leading zeros.
+    pub fn parse(version: &String) -> Result<Self, RegistryError> {
+        let len = version.len() as usize;
+        if len > MAX_LEN {
+            return Err(RegistryError::InvalidVersion);
+        }
+        let mut buf = [0u8; MAX_LEN];
+        version.copy_into_slice(&mut buf[..len]);
+        let s = core::str::from_utf8(&buf[..len]).map_err(|_| RegistryError::InvalidVersion)?;
+        let mut parts = s.split('.');
+        let mut next = || {
+            parts
+                .next()
+                .filter(|part| {
+                    !part.is_empty()
+                        && part.bytes().all(|b| b.is_ascii_digit())
+                        && !(part.len() > 1 && part.starts_with('0'))
+                })
+                .and_then(|part| part.parse().ok())
+                .ok_or(RegistryError::InvalidVersion)
+        };
+        let parsed = Version {
+            major: next()?,
+            minor: next()?,
+            patch: next()?,
+        };
+        if parts.next().is_some() {
+            return Err(RegistryError::InvalidVersion);
+        }
+        Ok(parsed)
+    }
+}
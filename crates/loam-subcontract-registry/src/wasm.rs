@@ -0,0 +1,236 @@
+use loam_sdk::{
+    persistent,
+    soroban_sdk::{self, contracttype, env, Address, BytesN, Lazy, String, Vec},
+    subcontract,
+};
+
+use crate::{RegistryError, Version};
+
+/// Longest package name accepted
+const MAX_NAME_LEN: usize = 64;
+
+/// A published version of a package
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Release {
+    pub version: String,
+    pub wasm_hash: BytesN<32>,
+    pub author: Address,
+    pub repo: String,
+    pub yanked: bool,
+}
+
+#[contracttype(export = false)]
+pub struct Package {
+    owner: Address,
+    /// Every published version, in ascending order. Releases are kept under their own keys, so
+    /// this only grows by the size of a version with each release.
+    versions: Vec<Version>,
+}
+
+#[contracttype(export = false)]
+pub enum DataKey {
+    Package(String),
+    Release(String, Version),
+}
+
+/// Packages and each of their releases are stored under their own keys, rather than loaded as a
+/// whole with every call
+#[derive(Lazy, Default)]
+pub struct WasmRegistry;
+
+impl WasmRegistry {
+    fn package(name: &String) -> Result<Package, RegistryError> {
+        persistent::get(&DataKey::Package(name.clone())).ok_or(RegistryError::NoSuchPackage)
+    }
+
+    fn set_package(name: String, package: &Package) {
+        persistent::set(&DataKey::Package(name), package);
+    }
+
+    fn release(name: &String, version: Version) -> Result<Release, RegistryError> {
+        persistent::get(&DataKey::Release(name.clone(), version))
+            .ok_or(RegistryError::NoSuchVersion)
+    }
+
+    fn set_release(name: String, version: Version, release: &Release) {
+        persistent::set(&DataKey::Release(name, version), release);
+    }
+
+    /// The owner of `name` if it has been published, requiring their authorization
+    fn owned_package(name: &String) -> Result<Package, RegistryError> {
+        let package = Self::package(name)?;
+        package.owner.require_auth();
+        Ok(package)
+    }
+}
+
+pub(crate) fn validate_name(name: &String) -> Result<(), RegistryError> {
+    let len = name.len() as usize;
+    if len == 0 || len > MAX_NAME_LEN {
+        return Err(RegistryError::InvalidName);
+    }
+    let mut buf = [0u8; MAX_NAME_LEN];
+    name.copy_into_slice(&mut buf[..len]);
+    if buf[..len]
+        .iter()
+        .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || *b == b'-' || *b == b'_')
+    {
+        Ok(())
+    } else {
+        Err(RegistryError::InvalidName)
+    }
+}
+
+#[subcontract]
+pub trait IsPublishable {
+    /// Publish `wasm_hash` as `version` of the package `name`.
+    /// The first author to publish a name becomes its owner, and only the owner can publish
+    /// further versions. A version can only be published once.
+    fn publish(
+        &mut self,
+        name: loam_sdk::soroban_sdk::String,
+        version: loam_sdk::soroban_sdk::String,
+        wasm_hash: loam_sdk::soroban_sdk::BytesN<32>,
+        author: loam_sdk::soroban_sdk::Address,
+        repo: loam_sdk::soroban_sdk::String,
+    ) -> Result<(), loam_subcontract_registry::RegistryError>;
+
+    /// Yank a version, so that it is no longer the latest version of the package.
+    /// It can still be fetched by its exact version.
+    fn yank(
+        &mut self,
+        name: loam_sdk::soroban_sdk::String,
+        version: loam_sdk::soroban_sdk::String,
+    ) -> Result<(), loam_subcontract_registry::RegistryError>;
+
+    /// Transfer ownership of a package to `new_owner`. Must be authorized by the current owner.
+    fn transfer_package(
+        &mut self,
+        name: loam_sdk::soroban_sdk::String,
+        new_owner: loam_sdk::soroban_sdk::Address,
+    ) -> Result<(), loam_subcontract_registry::RegistryError>;
+
+    /// Current owner of a package
+    fn package_owner(
+        &self,
+        name: loam_sdk::soroban_sdk::String,
+    ) -> Result<loam_sdk::soroban_sdk::Address, loam_subcontract_registry::RegistryError>;
+
+    /// Fetch a release of a package, or its latest version that hasn't been yanked
+    fn fetch(
+        &self,
+        name: loam_sdk::soroban_sdk::String,
+        version: Option<loam_sdk::soroban_sdk::String>,
+    ) -> Result<loam_subcontract_registry::Release, loam_subcontract_registry::RegistryError>;
+
+    /// Fetch the Wasm hash of a release of a package, or of its latest version that hasn't been yanked
+    fn fetch_hash(
+        &self,
+        name: loam_sdk::soroban_sdk::String,
+        version: Option<loam_sdk::soroban_sdk::String>,
+    ) -> Result<loam_sdk::soroban_sdk::BytesN<32>, loam_subcontract_registry::RegistryError>;
+
+    /// All published versions of a package, in ascending order, including yanked ones
+    fn versions(
+        &self,
+        name: loam_sdk::soroban_sdk::String,
+    ) -> Result<
+        loam_sdk::soroban_sdk::Vec<loam_sdk::soroban_sdk::String>,
+        loam_subcontract_registry::RegistryError,
+    >;
+}
+
+impl IsPublishable for WasmRegistry {
+    fn publish(
+        &mut self,
+        name: String,
+        version: String,
+        wasm_hash: BytesN<32>,
+        author: Address,
+        repo: String,
+    ) -> Result<(), RegistryError> {
+        validate_name(&name)?;
+        let parsed = Version::parse(&version)?;
+        author.require_auth();
+        let mut package = match Self::package(&name) {
+            Ok(package) if package.owner != author => return Err(RegistryError::NotOwner),
+            Ok(package) => package,
+            Err(_) => Package {
+                owner: author.clone(),
+                versions: Vec::new(env()),
+            },
+        };
+        if package.versions.contains(parsed) {
+            return Err(RegistryError::AlreadyPublished);
+        }
+        let index = package.versions.iter().take_while(|v| *v < parsed).count();
+        package
+            .versions
+            .insert(u32::try_from(index).unwrap_or(u32::MAX), parsed);
+        Self::set_release(
+            name.clone(),
+            parsed,
+            &Release {
+                version,
+                wasm_hash,
+                author,
+                repo,
+                yanked: false,
+            },
+        );
+        Self::set_package(name, &package);
+        Ok(())
+    }
+
+    fn yank(&mut self, name: String, version: String) -> Result<(), RegistryError> {
+        let parsed = Version::parse(&version)?;
+        Self::owned_package(&name)?;
+        let mut release = Self::release(&name, parsed)?;
+        release.yanked = true;
+        Self::set_release(name, parsed, &release);
+        Ok(())
+    }
+
+    fn transfer_package(&mut self, name: String, new_owner: Address) -> Result<(), RegistryError> {
+        let mut package = Self::owned_package(&name)?;
+        package.owner = new_owner;
+        Self::set_package(name, &package);
+        Ok(())
+    }
+
+    fn package_owner(&self, name: String) -> Result<Address, RegistryError> {
+        Ok(Self::package(&name)?.owner)
+    }
+
+    fn fetch(&self, name: String, version: Option<String>) -> Result<Release, RegistryError> {
+        let package = Self::package(&name)?;
+        if let Some(version) = version {
+            return Self::release(&name, Version::parse(&version)?);
+        }
+        for version in package.versions.iter().rev() {
+            let release = Self::release(&name, version)?;
+            if !release.yanked {
+                return Ok(release);
+            }
+        }
+        Err(RegistryError::AllVersionsYanked)
+    }
+
+    fn fetch_hash(
+        &self,
+        name: String,
+        version: Option<String>,
+    ) -> Result<BytesN<32>, RegistryError> {
+        Ok(self.fetch(name, version)?.wasm_hash)
+    }
+
+    fn versions(&self, name: String) -> Result<Vec<String>, RegistryError> {
+        let package = Self::package(&name)?;
+        let mut versions = Vec::new(env());
+        for version in package.versions {
+            versions.push_back(Self::release(&name, version)?.version);
+        }
+        Ok(versions)
+    }
+}
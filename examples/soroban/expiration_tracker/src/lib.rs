@@ -0,0 +1,9 @@
+#![no_std]
+use loam_sdk::derive_contract;
+use loam_subcontract_core::{admin::Admin, Core};
+use loam_subcontract_expiration_tracker::{ExpirationTracker, Trackable};
+
+#[derive_contract(Core(Admin), Trackable(ExpirationTracker))]
+pub struct Contract;
+
+mod test;
@@ -0,0 +1,212 @@
+#![cfg(test)]
+
+use super::{SorobanContract__, SorobanContract__Client};
+use loam_sdk::soroban_sdk::{
+    testutils::{Address as _, Deployer as _, Ledger as _},
+    token, vec, Address, Bytes, BytesN, Env,
+};
+use loam_subcontract_expiration_tracker::{Tracked, TrackerError};
+
+extern crate std;
+
+const TARGET_TTL: u32 = 100_000;
+const REWARD: i128 = 10;
+
+struct Setup<'a> {
+    env: Env,
+    client: SorobanContract__Client<'a>,
+    sponsor: Address,
+    keeper: Address,
+    token: token::Client<'a>,
+    /// An example contract to keep alive
+    contract: Address,
+}
+
+fn setup<'a>() -> Setup<'a> {
+    let env = Env::default();
+    env.mock_all_auths();
+    // everything else, such as the token, has to live long enough to be extended again
+    env.ledger()
+        .with_mut(|l| l.min_persistent_entry_ttl = TARGET_TTL);
+    let contract_id = env.register_contract(None, SorobanContract__);
+    let client = SorobanContract__Client::new(&env, &contract_id);
+    let sponsor = Address::generate(&env);
+    let keeper = Address::generate(&env);
+    let token_id = env.register_stellar_asset_contract(Address::generate(&env));
+    token::StellarAssetClient::new(&env, &token_id).mint(&sponsor, &1_000);
+    let token = token::Client::new(&env, &token_id);
+
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../../target/loam/example_status_message.wasm");
+    let wasm = std::fs::read(&path).unwrap_or_else(|e| {
+        std::panic!("reading {path:?}, build the example contracts first with `just build`: {e}")
+    });
+    let contract = env.register_contract_wasm(None, Bytes::from_slice(&env, &wasm));
+    Setup {
+        env,
+        client,
+        sponsor,
+        keeper,
+        token,
+        contract,
+    }
+}
+
+impl Setup<'_> {
+    fn track(&self, entry: &Tracked, amount: i128) {
+        self.client.track(
+            entry,
+            &self.sponsor,
+            &self.token.address,
+            &amount,
+            &TARGET_TTL,
+            &REWARD,
+        );
+    }
+
+    fn advance(&self, ledgers: u32) {
+        self.env.ledger().with_mut(|l| l.sequence_number += ledgers);
+    }
+}
+
+#[test]
+fn extend_contract_and_reward_keeper() {
+    let t = setup();
+    let entry = Tracked::Contract(t.contract.clone());
+    t.track(&entry, 100);
+    assert_eq!(t.token.balance(&t.sponsor), 900);
+    assert_eq!(t.client.tracked_count(), 1);
+
+    assert_eq!(t.client.extend(&entry, &t.keeper), REWARD);
+    let deployer = t.env.deployer();
+    assert_eq!(deployer.get_contract_instance_ttl(&t.contract), TARGET_TTL);
+    assert_eq!(deployer.get_contract_code_ttl(&t.contract), TARGET_TTL);
+    assert_eq!(t.token.balance(&t.keeper), REWARD);
+    assert_eq!(t.client.tracking(&entry).balance, 100 - REWARD);
+
+    // extending again right away would only drain the balance
+    assert_eq!(
+        t.client.try_extend(&entry, &t.keeper),
+        Err(Ok(TrackerError::TooEarly))
+    );
+    t.advance(TARGET_TTL / 2);
+    t.client.extend(&entry, &t.keeper);
+    assert_eq!(deployer.get_contract_instance_ttl(&t.contract), TARGET_TTL);
+    assert_eq!(t.token.balance(&t.keeper), 2 * REWARD);
+}
+
+#[test]
+fn extend_wasm_through_contract() {
+    let t = setup();
+    let entry = Tracked::Wasm(BytesN::from_array(&t.env, &[0; 32]), t.contract.clone());
+    t.track(&entry, 100);
+    t.advance(10);
+    t.client.extend(&entry, &t.keeper);
+    let deployer = t.env.deployer();
+    assert_eq!(deployer.get_contract_code_ttl(&t.contract), TARGET_TTL);
+    assert!(deployer.get_contract_instance_ttl(&t.contract) < TARGET_TTL);
+}
+
+#[test]
+fn top_up_until_balance_runs_out() {
+    let t = setup();
+    let entry = Tracked::Contract(t.contract.clone());
+    t.track(&entry, REWARD - 1);
+    assert_eq!(
+        t.client.try_extend(&entry, &t.keeper),
+        Err(Ok(TrackerError::InsufficientBalance))
+    );
+    let other = Address::generate(&t.env);
+    token::StellarAssetClient::new(&t.env, &t.token.address).mint(&other, &1);
+    t.client.top_up(&entry, &other, &1);
+    t.client.extend(&entry, &t.keeper);
+    assert_eq!(t.client.tracking(&entry).balance, 0);
+}
+
+#[test]
+fn untrack_refunds_sponsor() {
+    let t = setup();
+    let entry = Tracked::Contract(t.contract.clone());
+    t.track(&entry, 100);
+    assert_eq!(
+        t.client.try_track(
+            &entry,
+            &t.sponsor,
+            &t.token.address,
+            &0,
+            &TARGET_TTL,
+            &REWARD
+        ),
+        Err(Ok(TrackerError::AlreadyTracked))
+    );
+    t.client.extend(&entry, &t.keeper);
+    t.client.untrack(&entry);
+    assert_eq!(t.token.balance(&t.sponsor), 1_000 - REWARD);
+    assert_eq!(t.client.tracked_count(), 0);
+    assert_eq!(
+        t.client.try_extend(&entry, &t.keeper),
+        Err(Ok(TrackerError::NotTracked))
+    );
+}
+
+#[test]
+fn invalid_target_ttl() {
+    let t = setup();
+    let entry = Tracked::Contract(t.contract.clone());
+    let max_ttl = t.env.ledger().get().max_entry_ttl;
+    assert_eq!(
+        t.client.try_track(
+            &entry,
+            &t.sponsor,
+            &t.token.address,
+            &0,
+            &(max_ttl + 1),
+            &REWARD
+        ),
+        Err(Ok(TrackerError::InvalidTtl))
+    );
+}
+
+#[test]
+fn tracked_entries_are_listed_a_page_at_a_time() {
+    let t = setup();
+    let entries = [0u8, 1, 2]
+        .map(|i| Tracked::Wasm(BytesN::from_array(&t.env, &[i; 32]), t.contract.clone()));
+    for entry in &entries {
+        t.track(entry, 0);
+    }
+    assert_eq!(t.client.tracked_count(), 3);
+    assert_eq!(
+        t.client.tracked(&0, &2),
+        vec![&t.env, entries[0].clone(), entries[1].clone()]
+    );
+    assert_eq!(t.client.tracked(&2, &10), vec![&t.env, entries[2].clone()]);
+    assert_eq!(t.client.tracked(&3, &10).len(), 0);
+
+    // the last entry takes the place of an untracked one
+    t.client.untrack(&entries[0]);
+    assert_eq!(
+        t.client.tracked(&0, &u32::MAX),
+        vec![&t.env, entries[2].clone(), entries[1].clone()]
+    );
+    t.client.untrack(&entries[1]);
+    assert_eq!(t.client.tracked(&0, &10), vec![&t.env, entries[2].clone()]);
+}
+
+#[test]
+fn tracker_storage_outlives_the_minimum_ttl() {
+    let t = setup();
+    let entry = Tracked::Contract(t.contract.clone());
+    t.track(&entry, 100);
+    t.env.as_contract(&t.client.address, || {
+        t.env
+            .storage()
+            .instance()
+            .extend_ttl(3 * TARGET_TTL, 3 * TARGET_TTL);
+    });
+    // entries written with only the minimum TTL would have been archived by now
+    t.advance(2 * TARGET_TTL);
+    assert_eq!(t.client.tracking(&entry).balance, 100);
+    assert_eq!(t.client.tracked_count(), 1);
+    assert_eq!(t.client.tracked(&0, &1), vec![&t.env, entry]);
+}
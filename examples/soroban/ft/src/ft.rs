@@ -65,14 +65,8 @@ impl IsSep41 for MyFungibleToken {
     fn allowance(&self, from: Address, spender: Address) -> i128 {
         let allowance = self.allowances.get(Txn(from, spender));
         match allowance {
-            Some(a) => {
-                if env().ledger().sequence() <= a.live_until_ledger {
-                    a.amount
-                } else {
-                    0
-                }
-            }
-            None => 0,
+            Some(a) if env().ledger().sequence() <= a.live_until_ledger => a.amount,
+            _ => 0,
         }
     }
 
@@ -0,0 +1,9 @@
+#![no_std]
+use loam_sdk::derive_contract;
+use loam_subcontract_core::{admin::Admin, Core};
+use loam_subcontract_registry::{ContractRegistry, Deployable, Publishable, WasmRegistry};
+
+#[derive_contract(Core(Admin), Publishable(WasmRegistry), Deployable(ContractRegistry))]
+pub struct Contract;
+
+mod test;
@@ -0,0 +1,390 @@
+#![cfg(test)]
+
+use super::{SorobanContract__, SorobanContract__Client};
+use loam_sdk::soroban_sdk::{
+    testutils::{storage::Persistent as _, Address as _, Ledger as _},
+    vec, Address, Bytes, BytesN, Env, IntoVal, String, Symbol, Val,
+};
+use loam_subcontract_registry::{wasm::DataKey, RegistryError, Version};
+
+extern crate std;
+
+struct Setup<'a> {
+    env: Env,
+    client: SorobanContract__Client<'a>,
+    author: Address,
+}
+
+fn setup<'a>() -> Setup<'a> {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register_contract(None, SorobanContract__);
+    let client = SorobanContract__Client::new(&env, &contract_id);
+    let author = Address::generate(&env);
+    Setup {
+        env,
+        client,
+        author,
+    }
+}
+
+fn s(env: &Env, s: &str) -> String {
+    String::from_str(env, s)
+}
+
+fn hash(env: &Env, byte: u8) -> BytesN<32> {
+    BytesN::from_array(env, &[byte; 32])
+}
+
+impl Setup<'_> {
+    fn publish(&self, author: &Address, version: &str, byte: u8) -> Result<(), RegistryError> {
+        let env = &self.env;
+        match self.client.try_publish(
+            &s(env, "hello"),
+            &s(env, version),
+            &hash(env, byte),
+            author,
+            &s(env, "https://github.com/loambuild/hello"),
+        ) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.unwrap()),
+        }
+    }
+
+    /// Upload one of the example contracts built into `target/loam` and publish it
+    fn publish_example(&self, name: &str, version: &str, example: &str) {
+        self.publish_wasm(name, version, &example_wasm(example));
+    }
+
+    fn publish_wasm(&self, name: &str, version: &str, wasm: &[u8]) {
+        let env = &self.env;
+        let hash = env
+            .deployer()
+            .upload_contract_wasm(Bytes::from_slice(env, wasm));
+        self.client.publish(
+            &s(env, name),
+            &s(env, version),
+            &hash,
+            &self.author,
+            &s(env, "https://github.com/loambuild/loam"),
+        );
+    }
+
+    fn call<T: loam_sdk::soroban_sdk::TryFromVal<Env, Val>>(
+        &self,
+        contract_id: &Address,
+        func: &str,
+        args: loam_sdk::soroban_sdk::Vec<Val>,
+    ) -> T {
+        self.env
+            .invoke_contract(contract_id, &Symbol::new(&self.env, func), args)
+    }
+}
+
+fn example_wasm(example: &str) -> std::vec::Vec<u8> {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../../target/loam")
+        .join(std::format!("{example}.wasm"));
+    std::fs::read(&path).unwrap_or_else(|e| {
+        std::panic!("reading {path:?}, build the example contracts first with `just build`: {e}")
+    })
+}
+
+/// A contract without any functions, so without a Core subcontract either, made of the
+/// `contractenvmetav0` section of an example contract, which the network requires
+fn contract_without_functions() -> std::vec::Vec<u8> {
+    let core = example_wasm("example_core");
+    let read_u32 = |pos: &mut usize| {
+        let mut result = 0;
+        for shift in (0..35).step_by(7) {
+            let byte = core[*pos];
+            *pos += 1;
+            result |= usize::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        result
+    };
+    let header = &core[..8];
+    let mut pos = header.len();
+    while pos < core.len() {
+        let start = pos;
+        let id = core[pos];
+        pos += 1;
+        let size = read_u32(&mut pos);
+        let end = pos + size;
+        let name_len = read_u32(&mut pos);
+        if id == 0 && &core[pos..pos + name_len] == b"contractenvmetav0" {
+            return [header, &core[start..end]].concat();
+        }
+        pos = end;
+    }
+    std::panic!("example_core has no contractenvmetav0 section");
+}
+
+#[test]
+fn publish_and_fetch() {
+    let t = setup();
+    let env = &t.env;
+    t.publish(&t.author, "0.1.0", 1).unwrap();
+    t.publish(&t.author, "0.10.0", 3).unwrap();
+    t.publish(&t.author, "0.2.0", 2).unwrap();
+
+    let name = s(env, "hello");
+    assert_eq!(t.client.package_owner(&name), t.author);
+    assert_eq!(t.client.fetch_hash(&name, &None), hash(env, 3));
+    assert_eq!(
+        t.client.fetch_hash(&name, &Some(s(env, "0.2.0"))),
+        hash(env, 2)
+    );
+    let release = t.client.fetch(&name, &Some(s(env, "0.1.0")));
+    assert_eq!(release.version, s(env, "0.1.0"));
+    assert_eq!(release.author, t.author);
+    assert_eq!(release.repo, s(env, "https://github.com/loambuild/hello"));
+    assert!(!release.yanked);
+    assert_eq!(
+        t.client.versions(&name),
+        vec![env, s(env, "0.1.0"), s(env, "0.2.0"), s(env, "0.10.0")]
+    );
+}
+
+#[test]
+fn yanked_versions_are_skipped_by_latest() {
+    let t = setup();
+    let env = &t.env;
+    let name = s(env, "hello");
+    t.publish(&t.author, "1.0.0", 1).unwrap();
+    t.publish(&t.author, "1.0.1", 2).unwrap();
+    t.client.yank(&name, &s(env, "1.0.1"));
+
+    assert_eq!(t.client.fetch_hash(&name, &None), hash(env, 1));
+    let yanked = t.client.fetch(&name, &Some(s(env, "1.0.1")));
+    assert!(yanked.yanked);
+
+    t.client.yank(&name, &s(env, "1.0.0"));
+    assert_eq!(
+        t.client.try_fetch(&name, &None),
+        Err(Ok(RegistryError::AllVersionsYanked))
+    );
+}
+
+#[test]
+fn releases_are_kept_alive_while_in_use() {
+    let t = setup();
+    let env = &t.env;
+    let name = s(env, "hello");
+    t.publish(&t.author, "1.0.0", 1).unwrap();
+    let release = DataKey::Release(
+        name.clone(),
+        Version {
+            major: 1,
+            minor: 0,
+            patch: 0,
+        },
+    );
+    let ttl = |key: &DataKey| {
+        env.as_contract(&t.client.address, || {
+            env.storage().persistent().get_ttl(key)
+        })
+    };
+    let published = ttl(&release);
+    // only the registry's entries are under test, so keep the contract itself alive
+    env.as_contract(&t.client.address, || {
+        env.storage().instance().extend_ttl(published, published);
+    });
+
+    env.ledger()
+        .with_mut(|ledger| ledger.sequence_number += published - 10);
+    assert_eq!(ttl(&release), 10);
+    assert_eq!(t.client.fetch_hash(&name, &None), hash(env, 1));
+    assert_eq!(ttl(&release), published);
+    assert_eq!(ttl(&DataKey::Package(name)), published);
+}
+
+#[test]
+fn only_owner_can_publish() {
+    let t = setup();
+    let env = &t.env;
+    let other = Address::generate(env);
+    t.publish(&t.author, "1.0.0", 1).unwrap();
+    assert_eq!(t.publish(&other, "1.0.1", 2), Err(RegistryError::NotOwner));
+
+    t.client.transfer_package(&s(env, "hello"), &other);
+    assert_eq!(t.client.package_owner(&s(env, "hello")), other);
+    t.publish(&other, "1.0.1", 2).unwrap();
+    assert_eq!(
+        t.publish(&t.author, "1.0.2", 3),
+        Err(RegistryError::NotOwner)
+    );
+}
+
+#[test]
+fn versions_are_immutable() {
+    let t = setup();
+    t.publish(&t.author, "1.0.0", 1).unwrap();
+    assert_eq!(
+        t.publish(&t.author, "1.0.0", 2),
+        Err(RegistryError::AlreadyPublished)
+    );
+}
+
+#[test]
+fn invalid_names_and_versions() {
+    let t = setup();
+    let env = &t.env;
+    for version in ["1.0", "1.0.0.0", "01.0.0", "1.0.0-alpha", "", "a.b.c"] {
+        assert_eq!(
+            t.publish(&t.author, version, 1),
+            Err(RegistryError::InvalidVersion),
+            "{version}"
+        );
+    }
+    for name in ["", "Hello", "hello world", "hello/world"] {
+        assert_eq!(
+            t.client.try_publish(
+                &s(env, name),
+                &s(env, "1.0.0"),
+                &hash(env, 1),
+                &t.author,
+                &s(env, ""),
+            ),
+            Err(Ok(RegistryError::InvalidName)),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn missing_packages_and_versions() {
+    let t = setup();
+    let env = &t.env;
+    let name = s(env, "hello");
+    assert_eq!(
+        t.client.try_fetch(&name, &None),
+        Err(Ok(RegistryError::NoSuchPackage))
+    );
+    t.publish(&t.author, "1.0.0", 1).unwrap();
+    assert_eq!(
+        t.client.try_fetch(&name, &Some(s(env, "2.0.0"))),
+        Err(Ok(RegistryError::NoSuchVersion))
+    );
+}
+
+#[test]
+fn deploy_makes_owner_admin() {
+    let t = setup();
+    let env = &t.env;
+    t.publish_example("core", "0.1.0", "example_core");
+    let name = s(env, "my-core");
+    let contract_id =
+        t.client
+            .deploy_contract(&name, &s(env, "core"), &None, &t.author, &None, &None);
+
+    assert_eq!(t.client.fetch_contract_id(&name), contract_id);
+    let deployment = t.client.fetch_contract(&name);
+    assert_eq!(deployment.owner, t.author);
+    assert_eq!(deployment.wasm_name, s(env, "core"));
+    assert_eq!(deployment.version, s(env, "0.1.0"));
+    let admin: Option<Address> = t.call(&contract_id, "admin_get", vec![env]);
+    assert_eq!(admin, Some(t.author.clone()));
+
+    assert_eq!(
+        t.client
+            .try_deploy_contract(&name, &s(env, "core"), &None, &t.author, &None, &None),
+        Err(Ok(RegistryError::AlreadyDeployed))
+    );
+}
+
+#[test]
+fn contracts_without_core_can_be_deployed() {
+    let t = setup();
+    let env = &t.env;
+    t.publish_wasm("empty", "1.0.0", &contract_without_functions());
+    let name = s(env, "empty");
+    let contract_id = t
+        .client
+        .deploy_contract(&name, &name, &None, &t.author, &None, &None);
+    assert_eq!(t.client.fetch_contract_id(&name), contract_id);
+}
+
+#[test]
+fn deploy_runs_init() {
+    let t = setup();
+    let env = &t.env;
+    t.publish_example("status-message", "1.0.0", "example_status_message");
+    let contract_id = t.client.deploy_contract(
+        &s(env, "status"),
+        &s(env, "status-message"),
+        &Some(s(env, "1.0.0")),
+        &t.author,
+        &Some(Symbol::new(env, "messages_set")),
+        &Some(vec![
+            env,
+            t.author.into_val(env),
+            s(env, "hello").into_val(env),
+        ]),
+    );
+    let message: Option<String> = t.call(
+        &contract_id,
+        "messages_get",
+        vec![env, t.author.into_val(env)],
+    );
+    assert_eq!(message, Some(s(env, "hello")));
+}
+
+#[test]
+fn redeploy_to_a_newer_version() {
+    let t = setup();
+    let env = &t.env;
+    t.publish_example("app", "0.1.0", "example_core");
+    t.publish_example("app", "0.2.0", "example_status_message");
+    let name = s(env, "app");
+    let contract_id = t.client.deploy_contract(
+        &name,
+        &name,
+        &Some(s(env, "0.1.0")),
+        &t.author,
+        &None,
+        &None,
+    );
+
+    t.client.redeploy_contract(&name, &None);
+    assert_eq!(t.client.fetch_contract(&name).version, s(env, "0.2.0"));
+    let _: () = t.call(
+        &contract_id,
+        "messages_set",
+        vec![
+            env,
+            t.author.into_val(env),
+            s(env, "upgraded").into_val(env),
+        ],
+    );
+    let message: Option<String> = t.call(
+        &contract_id,
+        "messages_get",
+        vec![env, t.author.into_val(env)],
+    );
+    assert_eq!(message, Some(s(env, "upgraded")));
+}
+
+#[test]
+fn missing_contracts() {
+    let t = setup();
+    let env = &t.env;
+    assert_eq!(
+        t.client.try_fetch_contract_id(&s(env, "nope")),
+        Err(Ok(RegistryError::NoSuchContract))
+    );
+    assert_eq!(
+        t.client.try_deploy_contract(
+            &s(env, "nope"),
+            &s(env, "nope"),
+            &None,
+            &t.author,
+            &None,
+            &None
+        ),
+        Err(Ok(RegistryError::NoSuchPackage))
+    );
+}